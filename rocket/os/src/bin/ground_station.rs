@@ -0,0 +1,130 @@
+// Ground station binary: flies the simulated vehicle in-process (the
+// simulated board and RF link live in this process) and runs the ground
+// side of the radio link alongside it: telemetry, plus a scripted pad
+// checkout over the uplink that ends by arming the engine.
+use rocket_os::{config, flight, kernel::{self, task::{self, TaskConfig}, sync::{Mutex, sleep}}};
+use rocket_os::components::interlock::ValveId;
+use rocket_os::components::uplink_frame::{Command, Parameter};
+use rocket_os::error::{Result, RocketError};
+use rocket_os::hal::dummy_hal;
+use rocket_os::hal::interface::FullHardwareAbstraction;
+use rocket_os::drivers::radio::{Radio, RadioConfig};
+use rocket_os::ground_station::{CommandOutcome, GroundStation};
+use std::{sync::Arc, time::Duration};
+
+type Station = GroundStation<dummy_hal::DummySpi, dummy_hal::DummyPin, dummy_hal::DummyPin, dummy_hal::DummyDelay>;
+
+const COMMAND_POLL: Duration = Duration::from_millis(50); // Script checks for an answer this often
+const PING_INTERVAL: Duration = Duration::from_secs(5); // Link check once the vehicle is armed
+
+// Pad checkout: cycle a valve in test mode, show a refusal outside it, set
+// the oxidizer lead, then arm. The vehicle holds its countdown until armed.
+const CHECKOUT: [Command; 8] = [
+    Command::Ping,
+    Command::SetTestMode(true),
+    Command::OpenValve(ValveId::Oxidizer),
+    Command::CloseValve(ValveId::Oxidizer),
+    Command::SetTestMode(false),
+    Command::OpenValve(ValveId::Fuel), // Refused: not in test mode
+    Command::SetParameter(Parameter::OxidizerLead, 0.5),
+    Command::Arm,
+];
+
+fn main() -> Result<()> {
+    println!("[Main] Starting Ground Station...");
+    kernel::sim::init(config::SIM_CLOCK_MODE, config::SIM_SEED);
+
+    // Ground radio first, so it is listening before the vehicle transmits
+    let board_hal = dummy_hal::get_dummy_hal();
+    let spi_bus = board_hal
+        .get_spi_bus(config::DUMMY_GROUND_RADIO_SPI_BUS)
+        .ok_or(RocketError::Configuration("Failed to get ground radio SPI bus".into()))?;
+    let cs_pin = board_hal.get_gpio_pin(config::DUMMY_GROUND_RADIO_CS_PIN).unwrap();
+    let irq_pin = board_hal.get_gpio_pin(config::DUMMY_GROUND_RADIO_IRQ_PIN).unwrap();
+    // Must match the flight radio's modem settings to hear it at all
+    let radio = Radio::new(spi_bus, cs_pin, irq_pin, board_hal.get_delay_timer(), RadioConfig::default())?;
+    let ground_station = Arc::new(Mutex::new(GroundStation::new(radio, config::GROUND_RECORDING_PATH, config::GROUND_SEQUENCE_PATH, &config::UPLINK_KEY)?));
+
+    dummy_hal::set_arm_switch(true); // Pad crew closes the arm switch before clearing the pad
+    let flight_software = flight::start(flight::FlightConfig { auto_arm: false, ..Default::default() })?;
+
+    let poll_handle = {
+        let station = Arc::clone(&ground_station);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "GroundPoll",
+                period: config::GROUND_POLL_RATE,
+                deadline: config::GROUND_POLL_RATE,
+                priority: config::GROUND_POLL_TASK_PRIORITY,
+            },
+            move || station.lock()?.poll(),
+        )
+    };
+    let dashboard_handle = {
+        let station = Arc::clone(&ground_station);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "GroundDashboard",
+                period: config::GROUND_DASHBOARD_RATE,
+                deadline: config::GROUND_DASHBOARD_RATE,
+                priority: config::GROUND_DASHBOARD_TASK_PRIORITY,
+            },
+            move || {
+                station.lock()?.print_dashboard();
+                Ok(())
+            },
+        )
+    };
+
+    let command_handle = {
+        let station = Arc::clone(&ground_station);
+        task::spawn("GroundCommands", move || -> Result<()> {
+            for command in CHECKOUT {
+                match run_command(&station, command)? {
+                    Some(outcome) => println!("[Ground] Checkout step {:?}: {:?}", command, outcome),
+                    None => return Ok(()), // Shutting down
+                }
+            }
+            while run_command(&station, Command::Ping)?.is_some() {
+                sleep(PING_INTERVAL);
+            }
+            Ok(())
+        })
+    };
+
+    println!("[Main] All tasks spawned. Simulation running...");
+    match config::SIM_RUN_DURATION {
+        Some(duration) => task::run_for(duration),
+        None => task::run_scheduler(),
+    }
+
+    flight_software.shutdown()?;
+    // Pick up the final frame sent during the flight shutdown
+    for handle in [command_handle, poll_handle, dashboard_handle] {
+        let name = handle.name();
+        if let Err(e) = handle.join() {
+            eprintln!("[Main] Task {} ended with error: {}", name, e);
+        }
+    }
+    sleep(config::GROUND_POLL_RATE); // Let it cross the link
+    let mut station = ground_station.lock()?;
+    station.poll()?;
+    station.print_dashboard();
+    station.flush()?;
+
+    task::print_statistics();
+    println!("[Main] Ground station finished (all tasks stopped).");
+    Ok(())
+}
+
+// Send one command and wait for its outcome (None if shutting down first)
+fn run_command(station: &Mutex<Station>, command: Command) -> Result<Option<CommandOutcome>> {
+    let sequence = station.lock()?.send_command(command);
+    while !task::should_stop() {
+        if let Some(outcome) = station.lock()?.command_outcome(sequence) {
+            return Ok(Some(outcome));
+        }
+        sleep(COMMAND_POLL);
+    }
+    Ok(None)
+}
@@ -0,0 +1,270 @@
+// Apogee control: energy management during the burn. Predicts the coast
+// apogee from the estimated altitude and vertical velocity, throttles back
+// as the prediction closes on the target and cuts the engine once it gets
+// there. Without enough impulse to reach the target it simply runs at full
+// throttle.
+//
+// The throttle valve only trims the flow through the main valves, which
+// stay behind the safety interlock; cutoff goes through EngineControl. A
+// valve that stops following its command during the burn aborts the engine:
+// the flow, and so the apogee, is no longer under control.
+use crate::hal::interface::{PwmOutput, Adc, OutputPin, InputPin};
+use crate::drivers::throttle_valve::ThrottleValve;
+use crate::components::engine_control::{AbortReason, EngineCommand, EngineControl, EngineState};
+use crate::components::navigation::SharedNavState;
+use crate::kernel::sync::Mutex;
+use crate::error::{DriverError, HalError, Result, RocketError};
+use crate::config;
+use std::sync::Arc;
+
+const GRAVITY: f32 = 9.81; // m/s^2
+
+#[derive(Debug, Clone, Copy)]
+pub struct ApogeeControlConfig {
+    pub target_apogee: f32, // m above the pad
+    // Coast drag deceleration per (m/s)^2, rho Cd A / 2m with the burnt-out
+    // mass. Sets how much of the remaining climb the predictor gives up to drag.
+    pub drag_factor: f32,   // 1/m
+    pub throttle_gain: f32, // Throttle per unit of predicted shortfall (as a fraction of the target)
+    pub min_throttle: f32,  // Lowest throttle the engine runs stably at
+    pub cutoff_margin: f32, // m, cut off this far short of the target (tail-off, valve closing)
+}
+
+impl Default for ApogeeControlConfig {
+    fn default() -> Self {
+        ApogeeControlConfig {
+            target_apogee: config::TARGET_APOGEE,
+            drag_factor: 2.6e-4, // Cd 0.45, 100 mm body tube, 8 kg, air at ~500 m
+            throttle_gain: 8.0,
+            min_throttle: 0.4,
+            cutoff_margin: 0.0,
+        }
+    }
+}
+
+// Coast apogee for vertical flight with quadratic drag:
+// h + ln(1 + k v^2 / g) / 2k
+pub fn predict_apogee(altitude: f32, vertical_velocity: f32, drag_factor: f32) -> f32 {
+    if vertical_velocity <= 0.0 {
+        return altitude;
+    }
+    let v2 = vertical_velocity * vertical_velocity;
+    if drag_factor <= 0.0 {
+        return altitude + v2 / (2.0 * GRAVITY);
+    }
+    altitude + (drag_factor * v2 / GRAVITY).ln_1p() / (2.0 * drag_factor)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApogeeCommand {
+    Throttle(f32), // 0 (closed) to 1 (full)
+    Cutoff,
+}
+
+// The control law on its own, so it can be flown against the vehicle model
+#[derive(Debug, Clone, Copy)]
+pub struct ApogeeController {
+    config: ApogeeControlConfig,
+}
+
+impl ApogeeController {
+    pub fn new(config: ApogeeControlConfig) -> Self {
+        ApogeeController { config }
+    }
+
+    pub fn config(&self) -> &ApogeeControlConfig {
+        &self.config
+    }
+
+    // Throttle in proportion to the predicted shortfall; cut off when there
+    // is none left
+    pub fn command(&self, altitude: f32, vertical_velocity: f32) -> ApogeeCommand {
+        let c = &self.config;
+        let predicted = predict_apogee(altitude, vertical_velocity, c.drag_factor);
+        let shortfall = (c.target_apogee - c.cutoff_margin - predicted) / c.target_apogee;
+        if shortfall <= 0.0 {
+            return ApogeeCommand::Cutoff;
+        }
+        ApogeeCommand::Throttle((c.throttle_gain * shortfall).clamp(c.min_throttle, 1.0))
+    }
+}
+
+pub struct ApogeeControl<PWM, ADC, P, S>
+where
+    PWM: PwmOutput,
+    ADC: Adc<u16, Error = HalError>,
+    P: OutputPin,
+    S: InputPin,
+{
+    throttle: ThrottleValve<PWM, ADC>,
+    engine_control: Arc<Mutex<EngineControl<P, S>>>,
+    nav_state: SharedNavState,
+    controller: ApogeeController,
+    throttling: bool, // Has throttled back this burn
+    cut_off: bool,
+}
+
+impl<PWM, ADC, P, S> ApogeeControl<PWM, ADC, P, S>
+where
+    PWM: PwmOutput,
+    ADC: Adc<u16, Error = HalError>,
+    P: OutputPin,
+    S: InputPin,
+{
+    pub fn new(
+        throttle: ThrottleValve<PWM, ADC>,
+        engine_control: Arc<Mutex<EngineControl<P, S>>>,
+        nav_state: SharedNavState,
+        config: ApogeeControlConfig,
+    ) -> Self {
+        println!("[Component:ApogeeControl] Created, target apogee {:.0} m.", config.target_apogee);
+        Self {
+            throttle,
+            engine_control,
+            nav_state,
+            controller: ApogeeController::new(config),
+            throttling: false,
+            cut_off: false,
+        }
+    }
+
+    pub fn cut_off(&self) -> bool {
+        self.cut_off
+    }
+
+    // Close the throttle regardless of state; part of the shutdown sequence
+    pub fn safe(&mut self) -> Result<()> {
+        self.throttle.close()?;
+        Ok(())
+    }
+
+    // Called every control loop, after EngineControl::update
+    pub fn update(&mut self) -> Result<()> {
+        let engine_state = self.engine_control.lock()?.state();
+        match engine_state {
+            // Full throttle for ignition
+            EngineState::Idle | EngineState::Igniting | EngineState::OxidizerLead | EngineState::Starting => {
+                self.throttle.set_target(1.0)?
+            }
+            EngineState::Running if !self.cut_off => {
+                let nav = self.nav_state.get()?;
+                match self.controller.command(nav.altitude, nav.vertical_velocity) {
+                    ApogeeCommand::Throttle(throttle) => {
+                        if throttle < 1.0 && !self.throttling {
+                            println!("[Component:ApogeeControl] Throttling back at {:.0} m, {:.1} m/s.", nav.altitude, nav.vertical_velocity);
+                            self.throttling = true;
+                        }
+                        self.throttle.set_target(throttle)?;
+                    }
+                    ApogeeCommand::Cutoff => {
+                        let predicted = predict_apogee(nav.altitude, nav.vertical_velocity, self.controller.config().drag_factor);
+                        println!(
+                            "[Component:ApogeeControl] Cutoff at {:.0} m, {:.1} m/s: predicted apogee {:.0} m.",
+                            nav.altitude, nav.vertical_velocity, predicted
+                        );
+                        self.cut_off = true;
+                        self.engine_control.lock()?.execute_command(EngineCommand::Shutdown)?;
+                    }
+                }
+            }
+            EngineState::Aborted if self.throttle.commanded() > 0.0 => self.throttle.close()?,
+            _ => {}
+        }
+        self.throttle.update()?;
+        if engine_state == EngineState::Running && !self.cut_off {
+            self.check_tracking()?;
+        }
+        Ok(())
+    }
+
+    // Abort the burn if the valve isn't following its command. Failing to
+    // read the feedback at all is left to the caller.
+    fn check_tracking(&mut self) -> Result<()> {
+        match self.throttle.check_tracking() {
+            Err(RocketError::Driver(DriverError::ActuatorFault(fault))) => {
+                println!("[Component:ApogeeControl] Throttle fault: {}", fault);
+                self.engine_control.lock()?.abort(AbortReason::ThrottleFault)?;
+                self.throttle.close()
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::throttle_valve::ThrottleValveConfig;
+    use crate::hal::flight_sim::{FlightStatus, VehicleParams, VehicleSim};
+
+    const DT: f64 = 0.001; // s, physics step
+    const CONTROL_STEPS: usize = 20; // Physics steps per control cycle (50 Hz)
+
+    // Fly the vehicle model to apogee, with the controller in the loop on
+    // the true state when `controlled`, and return the apogee. Estimator
+    // error is left to the full simulation.
+    fn fly(thrust_scale: f64, drag_coefficient: f64, controlled: bool) -> f64 {
+        let mut params = VehicleParams { drag_coefficient, ..VehicleParams::default() };
+        for point in &mut params.thrust_curve {
+            point.1 *= thrust_scale;
+        }
+        let mut vehicle = VehicleSim::new(params);
+        let controller = ApogeeController::new(ApogeeControlConfig::default());
+        let slew = ThrottleValveConfig::default().max_slew_rate as f64 * DT;
+        let (mut throttle, mut target) = (1.0, 1.0);
+        let mut cut_off = false;
+        let mut apogee = 0.0f64;
+        for step in 0..120_000 {
+            if controlled && !cut_off && step % CONTROL_STEPS == 0 {
+                let state = vehicle.state();
+                match controller.command(state.position[2] as f32, state.velocity[2] as f32) {
+                    ApogeeCommand::Throttle(t) => target = t as f64,
+                    ApogeeCommand::Cutoff => cut_off = true,
+                }
+            }
+            throttle += (target - throttle).clamp(-slew, slew);
+            vehicle.step(DT, if cut_off { 0.0 } else { throttle });
+            let state = vehicle.state();
+            apogee = apogee.max(state.position[2]);
+            if state.status == FlightStatus::Airborne && state.velocity[2] < 0.0 {
+                break;
+            }
+        }
+        apogee
+    }
+
+    #[test]
+    fn predicted_apogee_matches_drag_free_ballistics() {
+        assert_eq!(predict_apogee(100.0, -5.0, 2.6e-4), 100.0);
+        let predicted = predict_apogee(100.0, 50.0, 0.0);
+        assert!((predicted - (100.0 + 2500.0 / (2.0 * GRAVITY))).abs() < 1e-3);
+        // Drag only ever takes height away
+        assert!(predict_apogee(100.0, 50.0, 2.6e-4) < predicted);
+    }
+
+    #[test]
+    fn reaches_target_apogee_across_thrust_and_drag() {
+        let target = config::TARGET_APOGEE as f64;
+        println!("thrust  Cd    open loop   controlled  error");
+        for thrust_scale in [0.9, 1.0, 1.1, 1.25] {
+            for drag_coefficient in [0.35, 0.45, 0.55] {
+                let open_loop = fly(thrust_scale, drag_coefficient, false);
+                let controlled = fly(thrust_scale, drag_coefficient, true);
+                let error = controlled - target;
+                println!(
+                    "{:5.2}  {:4.2}  {:8.1} m  {:8.1} m  {:+6.1} m ({:+.1}%)",
+                    thrust_scale, drag_coefficient, open_loop, controlled, error, 100.0 * error / target
+                );
+                if open_loop < target {
+                    // Can't get there: full throttle all the way
+                    assert!((controlled - open_loop).abs() < 1.0);
+                } else {
+                    // The predictor assumes the nominal Cd of 0.45; a drag
+                    // error shows up in the coast, after cutoff
+                    let tolerance = if drag_coefficient == 0.45 { 0.02 } else { 0.05 };
+                    assert!(error.abs() < tolerance * target, "{:+.1} m off the target", error);
+                }
+            }
+        }
+    }
+}
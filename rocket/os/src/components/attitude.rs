@@ -0,0 +1,249 @@
+// Attitude estimator: Mahony complementary filter on a body-to-world
+// quaternion. Gyro rates are integrated every step; the accelerometer pulls
+// the estimate back towards gravity only while it actually measures gravity
+// (|accel| close to 1 g), i.e. on the pad and after landing, never under
+// thrust or drag. Heading is unobservable without a magnetometer and drifts.
+//
+// Frames: world x east / y north / z up; body z along the nose.
+
+const GRAVITY: f32 = 9.81; // m/s^2, matches the IMU driver's scaling
+
+// Unit quaternion rotating body-frame vectors into the world frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 } // Body aligned with world: nose up
+    }
+}
+
+impl Quaternion {
+    // Rotation taking unit vector `from` onto unit vector `to` (shortest arc)
+    fn between(from: [f32; 3], to: [f32; 3]) -> Self {
+        let c = cross(from, to);
+        let d = dot(from, to);
+        if d < -0.999_999 {
+            return Quaternion { w: 0.0, x: 1.0, y: 0.0, z: 0.0 }; // Opposite: half turn about x
+        }
+        Quaternion { w: 1.0 + d, x: c[0], y: c[1], z: c[2] }.normalized()
+    }
+
+    fn normalized(self) -> Self {
+        let n = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        Quaternion { w: self.w / n, x: self.x / n, y: self.y / n, z: self.z / n }
+    }
+
+    fn conjugate(self) -> Self {
+        Quaternion { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    fn multiply(self, r: Quaternion) -> Self {
+        Quaternion {
+            w: self.w * r.w - self.x * r.x - self.y * r.y - self.z * r.z,
+            x: self.w * r.x + self.x * r.w + self.y * r.z - self.z * r.y,
+            y: self.w * r.y - self.x * r.z + self.y * r.w + self.z * r.x,
+            z: self.w * r.z + self.x * r.y - self.y * r.x + self.z * r.w,
+        }
+    }
+
+    // Body-frame vector expressed in the world frame
+    pub fn rotate(&self, v: [f32; 3]) -> [f32; 3] {
+        let p = Quaternion { w: 0.0, x: v[0], y: v[1], z: v[2] };
+        let r = self.multiply(p).multiply(self.conjugate());
+        [r.x, r.y, r.z]
+    }
+
+    // World-frame vector expressed in the body frame
+    pub fn rotate_inv(&self, v: [f32; 3]) -> [f32; 3] {
+        self.conjugate().rotate(v)
+    }
+
+    // (roll, pitch, yaw) in rad, ZYX Tait-Bryan sequence. With body z along
+    // the nose, roll and pitch are the deviations from vertical about body x
+    // and y, and yaw is the spin about the long axis.
+    pub fn euler_angles(&self) -> (f32, f32, f32) {
+        let Quaternion { w, x, y, z } = *self;
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+        (roll, pitch, yaw)
+    }
+
+    // Angle between the nose and the local vertical, rad
+    pub fn tilt(&self) -> f32 {
+        self.rotate([0.0, 0.0, 1.0])[2].clamp(-1.0, 1.0).acos()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AhrsConfig {
+    pub kp: f32,              // Proportional gain of the gravity correction, 1/s
+    pub ki: f32,              // Integral gain (gyro bias estimation), 1/s^2
+    pub accel_tolerance: f32, // m/s^2, max ||accel| - g| for the accelerometer to be trusted
+}
+
+impl Default for AhrsConfig {
+    fn default() -> Self {
+        AhrsConfig {
+            kp: 1.0,
+            ki: 0.05,
+            accel_tolerance: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ahrs {
+    config: AhrsConfig,
+    attitude: Quaternion,
+    gyro_bias: [f32; 3], // rad/s, estimated by the integral term
+    aligned: bool,       // Set once the attitude has been initialised from gravity
+}
+
+impl Ahrs {
+    pub fn new(config: AhrsConfig) -> Self {
+        Ahrs { config, attitude: Quaternion::default(), gyro_bias: [0.0; 3], aligned: false }
+    }
+
+    pub fn attitude(&self) -> Quaternion {
+        self.attitude
+    }
+
+    pub fn gyro_bias(&self) -> [f32; 3] {
+        self.gyro_bias
+    }
+
+    // Gyro rates with the estimated bias removed
+    pub fn angular_rate(&self, gyro: [f32; 3]) -> [f32; 3] {
+        [gyro[0] - self.gyro_bias[0], gyro[1] - self.gyro_bias[1], gyro[2] - self.gyro_bias[2]]
+    }
+
+    // Advance the estimate by `dt` seconds with one IMU sample (body frame)
+    pub fn update(&mut self, gyro: [f32; 3], accel: [f32; 3], dt: f32) {
+        let accel_norm = dot(accel, accel).sqrt();
+        let measures_gravity = (accel_norm - GRAVITY).abs() <= self.config.accel_tolerance;
+        if !self.aligned {
+            if measures_gravity {
+                // Level from the first clean gravity reading (heading arbitrary)
+                self.attitude = Quaternion::between(scale(accel, 1.0 / accel_norm), [0.0, 0.0, 1.0]);
+                self.aligned = true;
+            }
+            return;
+        }
+
+        let mut rate = self.angular_rate(gyro);
+        if measures_gravity {
+            // Error between measured and predicted "up", in the body frame
+            let measured = scale(accel, 1.0 / accel_norm);
+            let predicted = self.attitude.rotate_inv([0.0, 0.0, 1.0]);
+            let error = cross(measured, predicted);
+            for axis in 0..3 {
+                self.gyro_bias[axis] -= self.config.ki * error[axis] * dt;
+                rate[axis] += self.config.kp * error[axis];
+            }
+        }
+
+        let half_dt = 0.5 * dt;
+        let delta = Quaternion { w: 1.0, x: rate[0] * half_dt, y: rate[1] * half_dt, z: rate[2] * half_dt };
+        self.attitude = self.attitude.multiply(delta).normalized();
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn scale(v: [f32; 3], s: f32) -> [f32; 3] {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    const DT: f32 = 0.005; // 200 Hz IMU
+
+    fn about_axis(axis: [f32; 3], angle: f32) -> Quaternion {
+        let s = (0.5 * angle).sin();
+        Quaternion { w: (0.5 * angle).cos(), x: axis[0] * s, y: axis[1] * s, z: axis[2] * s }
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        for axis in 0..3 {
+            assert!((a[axis] - b[axis]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn quaternions_normalize_and_rotate() {
+        let q = Quaternion { w: 2.0, x: -1.0, y: 0.5, z: 3.0 }.normalized();
+        assert!((q.w * q.w + q.x * q.x + q.y * q.y + q.z * q.z - 1.0).abs() < 1e-6);
+
+        let quarter_turn = about_axis([0.0, 0.0, 1.0], FRAC_PI_2);
+        assert_close(quarter_turn.rotate([1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]);
+        assert_close(quarter_turn.rotate_inv([0.0, 1.0, 0.0]), [1.0, 0.0, 0.0]);
+        // Composition applies the right-hand rotation first
+        let pitched = about_axis([0.0, 1.0, 0.0], FRAC_PI_2);
+        assert_close(quarter_turn.multiply(pitched).rotate([0.0, 0.0, 1.0]), [0.0, 1.0, 0.0]);
+        assert_close(Quaternion::between([1.0, 0.0, 0.0], [0.0, 0.0, 1.0]).rotate([1.0, 0.0, 0.0]), [0.0, 0.0, 1.0]);
+
+        let tilted = about_axis([1.0, 0.0, 0.0], 0.3);
+        assert!((tilted.tilt() - 0.3).abs() < 1e-5);
+        let (roll, pitch, yaw) = tilted.euler_angles();
+        assert!((roll - 0.3).abs() < 1e-5 && pitch.abs() < 1e-5 && yaw.abs() < 1e-5);
+    }
+
+    #[test]
+    fn levels_out_on_the_pad() {
+        let mut ahrs = Ahrs::new(AhrsConfig::default());
+        // Nothing until a reading that measures gravity
+        ahrs.update([0.0; 3], [0.0, 0.0, 30.0], DT);
+        assert_eq!(ahrs.attitude(), Quaternion::default());
+        // Aligned to a reading taken while the pad was still rocking
+        let rocked = about_axis([1.0, 0.0, 0.0], 0.2).rotate_inv([0.0, 0.0, GRAVITY]);
+        ahrs.update([0.0; 3], rocked, DT);
+        assert!((ahrs.attitude().tilt() - 0.2).abs() < 1e-4);
+
+        // Standing level, with a gyro bias the integral term has to find
+        let bias = [0.01, -0.008, 0.0];
+        for _ in 0..(60.0 / DT) as usize {
+            ahrs.update(bias, [0.0, 0.0, GRAVITY], DT);
+        }
+        assert!(ahrs.attitude().tilt() < 0.002, "tilt {}", ahrs.attitude().tilt());
+        // Only roll and pitch bias are observable from gravity
+        for (estimated, actual) in ahrs.gyro_bias().iter().zip(bias).take(2) {
+            assert!((estimated - actual).abs() < 0.001, "bias {:?}", ahrs.gyro_bias());
+        }
+    }
+
+    #[test]
+    fn integrates_the_gyro_under_thrust() {
+        let mut ahrs = Ahrs::new(AhrsConfig::default());
+        ahrs.update([0.0; 3], [0.0, 0.0, GRAVITY], DT);
+        // 0.5 rad/s about body x for 1 s; thrust hides gravity from the correction
+        for _ in 0..(1.0 / DT) as usize {
+            ahrs.update([0.5, 0.0, 0.0], [0.0, 0.0, 3.0 * GRAVITY], DT);
+        }
+        let (roll, pitch, yaw) = ahrs.attitude().euler_angles();
+        assert!((roll - 0.5).abs() < 1e-3, "roll {}", roll);
+        assert!(pitch.abs() < 1e-3 && yaw.abs() < 1e-3);
+        assert!((ahrs.attitude().tilt() - 0.5).abs() < 1e-3);
+        // Spin about the long axis shows up as yaw and leaves the tilt alone
+        for _ in 0..(1.0 / DT) as usize {
+            ahrs.update([0.0, 0.0, 1.0], [0.0, 0.0, 3.0 * GRAVITY], DT);
+        }
+        assert!((ahrs.attitude().tilt() - 0.5).abs() < 1e-3);
+        assert_eq!(ahrs.angular_rate([0.0, 0.0, 1.0]), [0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,543 @@
+// Engine control component: drives the igniter and the fuel and oxidizer
+// valves through the ignition / run / shutdown sequence. Every actuator
+// command goes through the safety interlock: ignition needs it armed, and
+// individual valves can only be worked by hand in test mode (interlock
+// checkout).
+//
+// Ignition is staged: igniter, then oxidizer, then fuel, each after a
+// configured delay. The start is only confirmed (Running) once the chamber
+// pressure and the vehicle's acceleration show the engine has lit; if
+// either doesn't come within its window the sequence aborts, and a chamber
+// pressure that can't be read counts as one that didn't rise. Both tanks
+// have to hold feed pressure to ignite and for as long as propellant flows.
+use crate::hal::interface::{OutputPin, InputPin};
+use crate::components::interlock::{InterlockState, SafetyInterlock, ValveId};
+use crate::components::navigation::SharedNavState;
+use crate::kernel::{sim::Instant, sync};
+use crate::error::{ComponentError, Result};
+use crate::config;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineCommand {
+    Ignite,   // Start the ignition sequence (only valid from Idle)
+    Shutdown, // Nominal cutoff: fuel first, then oxidizer
+    Abort,    // Close everything immediately, from any state
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineState {
+    Idle,
+    Igniting,     // Igniter burning, valves still closed
+    OxidizerLead, // Oxidizer open, waiting before opening fuel
+    Starting,     // Both valves open, waiting for the engine to show it has lit
+    Running,
+    Shutdown,
+    Aborted,
+}
+
+impl EngineState {
+    // Anywhere between Ignite and cutoff: propellant may be flowing
+    pub fn is_firing(self) -> bool {
+        matches!(
+            self,
+            EngineState::Igniting | EngineState::OxidizerLead | EngineState::Starting | EngineState::Running
+        )
+    }
+}
+
+// Why the engine ended up Aborted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    Commanded,         // Abort command (ground or sequencer)
+    NoChamberPressure, // Chamber pressure didn't rise (or couldn't be read) after the fuel opened
+    NoAcceleration,    // The vehicle didn't accelerate after the fuel opened
+    IgnitionTimeout,   // The sequence didn't reach Running in time
+    LowFeedPressure,   // A tank lost pressure with its propellant flowing
+    Interlock,         // The interlock refused a step or the arm switch opened
+    ThrottleFault,     // The throttle valve stopped following its command
+}
+
+// Feed and combustion pressures, Pa gauge
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnginePressures {
+    pub fuel_tank: f32,
+    pub oxidizer_tank: f32,
+    pub chamber: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IgnitionConfig {
+    pub igniter_lead: Duration,    // Igniter burning before the oxidizer opens
+    pub oxidizer_lead: Duration,   // Oxidizer open before fuel
+    pub pressure_window: Duration, // After the fuel opens, for the chamber pressure to rise
+    pub min_chamber_pressure: f32, // Pa gauge, confirms combustion
+    pub accel_window: Duration,    // After the fuel opens, for the vehicle to accelerate
+    pub min_accel: f32,            // m/s^2, axial specific force (1 g standing on the pad)
+    pub min_tank_pressure: f32,    // Pa gauge, both tanks, to ignite and while propellant flows
+    pub timeout: Duration,         // Ignite to Running, whatever else happens
+}
+
+impl Default for IgnitionConfig {
+    fn default() -> Self {
+        IgnitionConfig {
+            igniter_lead: Duration::from_millis(500),
+            oxidizer_lead: config::IGNITION_OXIDIZER_LEAD,
+            pressure_window: Duration::from_millis(500),
+            min_chamber_pressure: 3.0e5,
+            accel_window: Duration::from_secs(1),
+            min_accel: 20.0,
+            min_tank_pressure: 2.0e6,
+            timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+pub struct EngineControl<P: OutputPin, S: InputPin> {
+    interlock: SafetyInterlock<P, S>, // Sole owner of the valves and igniter
+    nav_state: SharedNavState,
+    ignition: IgnitionConfig,
+    state: EngineState,
+    state_entered: Instant,
+    ignition_started: Instant,
+    pressures: Option<EnginePressures>, // None until measured, or while the transducers can't be read
+    pressure_confirmed: bool,
+    accel_confirmed: bool,
+    abort_reason: Option<AbortReason>,
+}
+
+impl<P: OutputPin, S: InputPin> EngineControl<P, S> {
+    pub fn new(interlock: SafetyInterlock<P, S>, nav_state: SharedNavState, ignition: IgnitionConfig) -> Self {
+        println!("[Component:EngineControl] Created.");
+        let now = sync::get_time();
+        Self {
+            interlock,
+            nav_state,
+            ignition,
+            state: EngineState::Idle,
+            state_entered: now,
+            ignition_started: now,
+            pressures: None,
+            pressure_confirmed: false,
+            accel_confirmed: false,
+            abort_reason: None,
+        }
+    }
+
+    pub fn state(&self) -> EngineState {
+        self.state
+    }
+
+    pub fn abort_reason(&self) -> Option<AbortReason> {
+        self.abort_reason
+    }
+
+    pub fn igniter_lit(&self) -> bool {
+        self.interlock.igniter_lit()
+    }
+
+    pub fn pressures(&self) -> Option<EnginePressures> {
+        self.pressures
+    }
+
+    // Latest transducer readings. Without them (None) the feed pressure
+    // checks are left out, and a start can't be confirmed.
+    pub fn set_pressures(&mut self, pressures: Option<EnginePressures>) {
+        self.pressures = pressures;
+    }
+
+    pub fn armed(&self) -> bool {
+        self.interlock.state() == InterlockState::Armed
+    }
+
+    pub fn test_mode(&self) -> bool {
+        self.interlock.state() == InterlockState::Checkout
+    }
+
+    pub fn oxidizer_lead(&self) -> Duration {
+        self.ignition.oxidizer_lead
+    }
+
+    pub fn fuel_valve_open(&self) -> bool {
+        self.interlock.valve_open(ValveId::Fuel)
+    }
+
+    pub fn oxidizer_valve_open(&self) -> bool {
+        self.interlock.valve_open(ValveId::Oxidizer)
+    }
+
+    pub fn execute_command(&mut self, command: EngineCommand) -> Result<()> {
+        println!("[Component:EngineControl] Command {:?} in state {:?}", command, self.state);
+        match (command, self.state) {
+            (EngineCommand::Ignite, EngineState::Idle) => {
+                if let Some((tank, pressure)) = self.low_feed_pressure() {
+                    return Err(ComponentError::LogicError(
+                        format!("Ignite rejected: {} tank at {:.0} kPa", tank, pressure / 1000.0),
+                    ).into());
+                }
+                // The interlock refuses unless armed beforehand
+                self.interlock.fire_igniter()?;
+                self.ignition_started = sync::get_time();
+                self.pressure_confirmed = false;
+                self.accel_confirmed = false;
+                self.transition(EngineState::Igniting);
+                Ok(())
+            }
+            (EngineCommand::Ignite, state) => Err(ComponentError::LogicError(
+                format!("Ignite rejected in state {:?}", state),
+            ).into()),
+            (EngineCommand::Shutdown, state) if state.is_firing() => {
+                // Close fuel first so the chamber never runs fuel-rich
+                self.interlock.close(ValveId::Fuel)?;
+                self.interlock.close(ValveId::Oxidizer)?;
+                self.interlock.igniter_off()?;
+                self.transition(EngineState::Shutdown);
+                // No restarts, so nothing left to arm for
+                self.interlock.disarm()
+            }
+            (EngineCommand::Shutdown, _) => Ok(()), // Nothing is flowing
+            (EngineCommand::Abort, _) => self.abort(AbortReason::Commanded),
+        }
+    }
+
+    // Allow ignition. Only from Idle, and the interlock refuses during test
+    // mode, so a valve left open by hand can't become part of a real start.
+    pub fn arm(&mut self) -> Result<()> {
+        if self.state != EngineState::Idle {
+            return Err(ComponentError::LogicError(format!("Arm rejected in state {:?}", self.state)).into());
+        }
+        self.interlock.arm()
+    }
+
+    // Withdraw permission to ignite. A burn in progress is stopped with
+    // Shutdown or Abort, not by disarming.
+    pub fn disarm(&mut self) -> Result<()> {
+        if self.state.is_firing() {
+            return Err(ComponentError::LogicError(format!("Disarm rejected in state {:?}", self.state)).into());
+        }
+        self.interlock.disarm()
+    }
+
+    // Test mode allows single-valve commands for checkout on the pad. It can
+    // only be entered disarmed and idle; leaving it closes both valves.
+    pub fn set_test_mode(&mut self, enabled: bool) -> Result<()> {
+        if !enabled {
+            return self.interlock.exit_checkout();
+        }
+        if self.state != EngineState::Idle {
+            return Err(ComponentError::LogicError(format!("Test mode rejected in state {:?}", self.state)).into());
+        }
+        self.interlock.enter_checkout()
+    }
+
+    pub fn set_valve(&mut self, valve: ValveId, open: bool) -> Result<()> {
+        self.interlock.checkout_valve(valve, open)
+    }
+
+    pub fn set_oxidizer_lead(&mut self, lead: Duration) -> Result<()> {
+        if self.state != EngineState::Idle {
+            return Err(ComponentError::LogicError(format!("Oxidizer lead change rejected in state {:?}", self.state)).into());
+        }
+        self.ignition.oxidizer_lead = lead;
+        Ok(())
+    }
+
+    // Close both valves regardless of state; part of the shutdown sequence.
+    // Unlike Abort this is not an anomaly: a nominal run ends in Shutdown.
+    pub fn safe(&mut self) -> Result<()> {
+        println!("[Component:EngineControl] Safing valves.");
+        let result = self.interlock.safe();
+        if self.state.is_firing() {
+            self.transition(EngineState::Shutdown);
+        }
+        result
+    }
+
+    // Advance timed steps of the sequence and check the start; called every
+    // control loop
+    pub fn update(&mut self) -> Result<()> {
+        if self.interlock.update()? && self.state.is_firing() {
+            // Arm switch opened under us: the valves have lost power
+            self.abort_reason.get_or_insert(AbortReason::Interlock);
+            self.transition(EngineState::Aborted);
+            return Ok(());
+        }
+        let flowing = matches!(self.state, EngineState::OxidizerLead | EngineState::Starting | EngineState::Running);
+        if let (true, Some((tank, pressure))) = (flowing, self.low_feed_pressure()) {
+            println!("[Component:EngineControl] {} tank at {:.0} kPa.", tank, pressure / 1000.0);
+            return self.abort(AbortReason::LowFeedPressure);
+        }
+        if !matches!(self.state, EngineState::Igniting | EngineState::OxidizerLead | EngineState::Starting) {
+            return Ok(());
+        }
+        if self.ignition_started.elapsed() >= self.ignition.timeout {
+            return self.abort(AbortReason::IgnitionTimeout);
+        }
+        let in_state = self.state_entered.elapsed();
+        match self.state {
+            EngineState::Igniting if in_state >= self.ignition.igniter_lead => {
+                self.fire(ValveId::Oxidizer)?;
+                self.transition(EngineState::OxidizerLead);
+            }
+            EngineState::OxidizerLead if in_state >= self.ignition.oxidizer_lead => {
+                self.fire(ValveId::Fuel)?;
+                self.transition(EngineState::Starting);
+            }
+            EngineState::Starting => self.check_start(in_state)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Open a valve for the sequence; a refusal aborts rather than leave one
+    // propellant flowing on its own
+    fn fire(&mut self, valve: ValveId) -> Result<()> {
+        if let Err(e) = self.interlock.fire(valve) {
+            self.abort(AbortReason::Interlock)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Both valves open: confirm combustion from chamber pressure and
+    // acceleration, each within its own window
+    fn check_start(&mut self, since_fuel_open: Duration) -> Result<()> {
+        let c = self.ignition;
+        let chamber_pressure = self.pressures.map(|p| p.chamber);
+        match chamber_pressure {
+            Some(pressure) if pressure >= c.min_chamber_pressure => self.pressure_confirmed = true,
+            _ if !self.pressure_confirmed && since_fuel_open >= c.pressure_window => {
+                if chamber_pressure.is_none() {
+                    println!("[Component:EngineControl] No chamber pressure reading to confirm the start.");
+                }
+                return self.abort(AbortReason::NoChamberPressure);
+            }
+            _ => {}
+        }
+        if self.nav_state.get()?.accel[2] >= c.min_accel {
+            self.accel_confirmed = true;
+        } else if !self.accel_confirmed && since_fuel_open >= c.accel_window {
+            return self.abort(AbortReason::NoAcceleration);
+        }
+        if self.pressure_confirmed && self.accel_confirmed {
+            println!(
+                "[Component:EngineControl] Ignition confirmed {:.0} ms after fuel open.",
+                since_fuel_open.as_secs_f32() * 1000.0
+            );
+            self.interlock.igniter_off()?;
+            self.transition(EngineState::Running);
+        }
+        Ok(())
+    }
+
+    // The tank below the minimum feed pressure, if either is, and its pressure
+    fn low_feed_pressure(&self) -> Option<(&'static str, f32)> {
+        let p = self.pressures?;
+        [("Fuel", p.fuel_tank), ("Oxidizer", p.oxidizer_tank)]
+            .into_iter()
+            .find(|&(_, pressure)| pressure < self.ignition.min_tank_pressure)
+    }
+
+    // Close everything and record why. Other components abort through here
+    // for faults of their own; the Abort command records Commanded.
+    pub fn abort(&mut self, reason: AbortReason) -> Result<()> {
+        if reason != AbortReason::Commanded {
+            println!("[Component:EngineControl] Aborting: {:?}", reason);
+        }
+        let result = self.interlock.safe();
+        // A repeated abort keeps the original reason
+        self.abort_reason.get_or_insert(reason);
+        self.transition(EngineState::Aborted);
+        result
+    }
+
+    fn transition(&mut self, next: EngineState) {
+        println!("[Component:EngineControl] {:?} -> {:?}", self.state, next);
+        self.state = next;
+        self.state_entered = sync::get_time();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::interlock::SafetyInterlock;
+    use crate::drivers::valve::Valve;
+    use crate::hal::dummy_hal::{self, DummyPin};
+    use crate::hal::feed_sim::Transducer;
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+
+    const STEP: Duration = config::CONTROL_LOOP_RATE;
+
+    // What the engine is told about the vehicle each control loop
+    #[derive(Clone, Copy, PartialEq)]
+    enum Sensing {
+        Truth,     // True pressures and acceleration from the simulation
+        NoChamber, // As Truth, but the transducers can't be read
+        NoAccel,   // As Truth, but navigation still reads 1 g
+    }
+
+    struct Rig {
+        engine: EngineControl<DummyPin, DummyPin>,
+        nav_state: SharedNavState,
+        sensing: Sensing,
+    }
+
+    impl Rig {
+        fn new(ignition: IgnitionConfig, sensing: Sensing) -> Self {
+            sim::init(ClockMode::Simulated, 1);
+            dummy_hal::reset();
+            dummy_hal::set_arm_switch(true);
+            let hal = dummy_hal::get_dummy_hal();
+            let pin = |id| hal.get_gpio_pin(id).unwrap();
+            let interlock = SafetyInterlock::new(
+                Valve::new(pin(config::DUMMY_VALVE_PIN)).unwrap(),
+                Valve::new(pin(config::DUMMY_VALVE_PIN + 1)).unwrap(),
+                pin(config::DUMMY_IGNITER_PIN),
+                pin(config::DUMMY_ARM_SWITCH_PIN),
+                pin(config::DUMMY_VALVE_CONTINUITY_PIN),
+                pin(config::DUMMY_VALVE_CONTINUITY_PIN + 1),
+                pin(config::DUMMY_IGNITER_CONTINUITY_PIN),
+            )
+            .unwrap();
+            let nav_state = SharedNavState::new();
+            let engine = EngineControl::new(interlock, nav_state.clone(), ignition);
+            let mut rig = Rig { engine, nav_state, sensing };
+            rig.sense();
+            rig
+        }
+
+        fn sense(&mut self) {
+            let accel = match self.sensing {
+                Sensing::NoAccel => 9.81,
+                _ => dummy_hal::vehicle_state().specific_force[2] as f32,
+            };
+            self.nav_state.update(|nav| nav.accel[2] = accel).unwrap();
+            let pressures = (self.sensing != Sensing::NoChamber).then(|| EnginePressures {
+                fuel_tank: dummy_hal::feed_pressure(Transducer::FuelTank) as f32,
+                oxidizer_tank: dummy_hal::feed_pressure(Transducer::OxidizerTank) as f32,
+                chamber: dummy_hal::feed_pressure(Transducer::Chamber) as f32,
+            });
+            self.engine.set_pressures(pressures);
+        }
+
+        // Run control loops until the engine leaves the ignition sequence
+        // (or `limit` runs out); returns how long that took
+        fn run(&mut self, limit: Duration) -> Duration {
+            let start = sync::get_time();
+            while sync::get_time().duration_since(start) < limit {
+                sim::sleep(STEP);
+                self.sense();
+                // A refused step comes back as an error as well as aborting
+                let _ = self.engine.update();
+                if matches!(self.engine.state(), EngineState::Running | EngineState::Aborted) {
+                    break;
+                }
+            }
+            sync::get_time().duration_since(start)
+        }
+
+        fn ignite(&mut self) {
+            self.engine.arm().unwrap();
+            self.engine.execute_command(EngineCommand::Ignite).unwrap();
+        }
+
+        fn assert_aborted(&self, reason: AbortReason) {
+            assert_eq!(self.engine.state(), EngineState::Aborted);
+            assert_eq!(self.engine.abort_reason(), Some(reason));
+            assert!(!self.engine.fuel_valve_open() && !self.engine.oxidizer_valve_open());
+            assert!(!self.engine.igniter_lit());
+        }
+    }
+
+    #[test]
+    fn a_good_start_runs() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::Truth);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        assert_eq!(rig.engine.state(), EngineState::Running);
+        assert!(rig.engine.fuel_valve_open() && rig.engine.oxidizer_valve_open());
+        assert!(!rig.engine.igniter_lit());
+        assert_eq!(rig.engine.abort_reason(), None);
+    }
+
+    #[test]
+    fn a_dud_igniter_aborts_for_chamber_pressure() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::Truth);
+        dummy_hal::set_igniter_dud(true);
+        rig.ignite();
+        let c = IgnitionConfig::default();
+        let elapsed = rig.run(Duration::from_secs(3));
+        rig.assert_aborted(AbortReason::NoChamberPressure);
+        // Propellant flowed for the pressure window and no longer
+        let window = c.igniter_lead + c.oxidizer_lead + c.pressure_window;
+        assert!(elapsed >= window && elapsed <= window + 3 * STEP, "{:?}", elapsed);
+    }
+
+    #[test]
+    fn an_unreadable_chamber_pressure_aborts_the_start() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::NoChamber);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        rig.assert_aborted(AbortReason::NoChamberPressure);
+    }
+
+    #[test]
+    fn no_acceleration_aborts() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::NoAccel);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        rig.assert_aborted(AbortReason::NoAcceleration);
+    }
+
+    #[test]
+    fn a_stalled_sequence_times_out() {
+        let _exclusive = sim::exclusive();
+        // Times out before the oxidizer lead is over
+        let ignition = IgnitionConfig { timeout: Duration::from_millis(600), ..IgnitionConfig::default() };
+        let mut rig = Rig::new(ignition, Sensing::Truth);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        rig.assert_aborted(AbortReason::IgnitionTimeout);
+    }
+
+    #[test]
+    fn losing_feed_pressure_aborts_a_running_engine() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::Truth);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        assert_eq!(rig.engine.state(), EngineState::Running);
+        dummy_hal::set_tanks_pressurized(false);
+        for _ in 0..50 {
+            sim::sleep(STEP);
+            rig.sense();
+            rig.engine.update().unwrap();
+        }
+        rig.assert_aborted(AbortReason::LowFeedPressure);
+    }
+
+    #[test]
+    fn interlock_refusals_abort_the_sequence() {
+        let _exclusive = sim::exclusive();
+        // The fuel coil breaks after arming: the oxidizer must not run alone
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::Truth);
+        rig.ignite();
+        dummy_hal::set_open_circuit(config::DUMMY_VALVE_PIN, true);
+        rig.run(Duration::from_secs(3));
+        rig.assert_aborted(AbortReason::Interlock);
+
+        // The arm switch opens during the burn
+        let mut rig = Rig::new(IgnitionConfig::default(), Sensing::Truth);
+        rig.ignite();
+        rig.run(Duration::from_secs(3));
+        dummy_hal::set_arm_switch(false);
+        rig.run(STEP);
+        rig.assert_aborted(AbortReason::Interlock);
+    }
+}
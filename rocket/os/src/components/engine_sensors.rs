@@ -0,0 +1,175 @@
+// Engine sensors: reads the tank and chamber pressure transducers every
+// control loop and hands the readings to EngineControl, which uses them to
+// confirm ignition and to watch the feed pressure.
+use crate::hal::interface::{Adc, OutputPin, InputPin};
+use crate::drivers::pressure_transducer::PressureTransducer;
+use crate::components::engine_control::{EngineControl, EnginePressures};
+use crate::kernel::sync::Mutex;
+use crate::error::{HalError, Result};
+use std::sync::Arc;
+
+pub struct EngineSensors<ADC, P, S>
+where
+    ADC: Adc<u16, Error = HalError>,
+    P: OutputPin,
+    S: InputPin,
+{
+    fuel_tank: PressureTransducer<ADC>,
+    oxidizer_tank: PressureTransducer<ADC>,
+    chamber: PressureTransducer<ADC>,
+    engine_control: Arc<Mutex<EngineControl<P, S>>>,
+    faulted: bool, // Last read failed
+}
+
+impl<ADC, P, S> EngineSensors<ADC, P, S>
+where
+    ADC: Adc<u16, Error = HalError>,
+    P: OutputPin,
+    S: InputPin,
+{
+    pub fn new(
+        fuel_tank: PressureTransducer<ADC>,
+        oxidizer_tank: PressureTransducer<ADC>,
+        chamber: PressureTransducer<ADC>,
+        engine_control: Arc<Mutex<EngineControl<P, S>>>,
+    ) -> Self {
+        println!("[Component:EngineSensors] Created.");
+        Self { fuel_tank, oxidizer_tank, chamber, engine_control, faulted: false }
+    }
+
+    // Called every control loop, before EngineControl::update. A failed read
+    // withdraws the pressures rather than leave stale ones in use.
+    pub fn update(&mut self) -> Result<()> {
+        match self.read() {
+            Ok(pressures) => {
+                if self.faulted {
+                    println!("[Component:EngineSensors] Transducers reading again.");
+                    self.faulted = false;
+                }
+                self.engine_control.lock()?.set_pressures(Some(pressures));
+                Ok(())
+            }
+            Err(e) => {
+                if !self.faulted {
+                    println!("[Component:EngineSensors] Transducer read failed: {:?}", e);
+                    self.faulted = true;
+                }
+                self.engine_control.lock()?.set_pressures(None);
+                Err(e)
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<EnginePressures> {
+        Ok(EnginePressures {
+            fuel_tank: self.fuel_tank.read()?,
+            oxidizer_tank: self.oxidizer_tank.read()?,
+            chamber: self.chamber.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::engine_control::{AbortReason, EngineCommand, EngineState, IgnitionConfig};
+    use crate::components::interlock::SafetyInterlock;
+    use crate::components::navigation::SharedNavState;
+    use crate::config;
+    use crate::drivers::valve::Valve;
+    use crate::hal::dummy_hal::{self, DummyAdc, DummyPin};
+    use crate::hal::feed_sim::Transducer;
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::{sim::{self, ClockMode}, sync};
+
+    type Engine = Arc<Mutex<EngineControl<DummyPin, DummyPin>>>;
+
+    // The transducers on the dummy ADC feeding an engine on the dummy
+    // interlock, armed with the tanks pressurized
+    fn setup() -> (EngineSensors<DummyAdc, DummyPin, DummyPin>, Engine, SharedNavState) {
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_arm_switch(true);
+        let hal = dummy_hal::get_dummy_hal();
+        let pin = |id| hal.get_gpio_pin(id).unwrap();
+        let transducer = |channel, calibration| {
+            PressureTransducer::new(hal.get_adc(config::DUMMY_ADC_ID).unwrap(), channel, calibration).unwrap()
+        };
+        let interlock = SafetyInterlock::new(
+            Valve::new(pin(config::DUMMY_VALVE_PIN)).unwrap(),
+            Valve::new(pin(config::DUMMY_VALVE_PIN + 1)).unwrap(),
+            pin(config::DUMMY_IGNITER_PIN),
+            pin(config::DUMMY_ARM_SWITCH_PIN),
+            pin(config::DUMMY_VALVE_CONTINUITY_PIN),
+            pin(config::DUMMY_VALVE_CONTINUITY_PIN + 1),
+            pin(config::DUMMY_IGNITER_CONTINUITY_PIN),
+        )
+        .unwrap();
+        let nav_state = SharedNavState::new();
+        let engine = Arc::new(Mutex::new(EngineControl::new(interlock, nav_state.clone(), IgnitionConfig::default())));
+        engine.lock().unwrap().arm().unwrap();
+        let sensors = EngineSensors::new(
+            transducer(config::DUMMY_FUEL_TANK_PRESSURE_CHANNEL, config::FUEL_TANK_TRANSDUCER_CAL),
+            transducer(config::DUMMY_OXIDIZER_TANK_PRESSURE_CHANNEL, config::OXIDIZER_TANK_TRANSDUCER_CAL),
+            transducer(config::DUMMY_CHAMBER_PRESSURE_CHANNEL, config::CHAMBER_TRANSDUCER_CAL),
+            engine.clone(),
+        );
+        (sensors, engine, nav_state)
+    }
+
+    // Run `cycles` control loops: sensors, then the engine on the true
+    // acceleration
+    fn run(sensors: &mut EngineSensors<DummyAdc, DummyPin, DummyPin>, engine: &Engine, nav_state: &SharedNavState, cycles: usize) {
+        for _ in 0..cycles {
+            sync::sleep(config::CONTROL_LOOP_RATE);
+            sensors.update().unwrap();
+            let accel = dummy_hal::vehicle_state().specific_force[2] as f32;
+            nav_state.update(|nav| nav.accel[2] = accel).unwrap();
+            engine.lock().unwrap().update().unwrap();
+        }
+    }
+
+    #[test]
+    fn passes_the_measured_pressures_on() {
+        let _exclusive = sim::exclusive();
+        let (mut sensors, engine, _) = setup();
+        assert_eq!(engine.lock().unwrap().pressures(), None);
+        sensors.update().unwrap();
+        let pressures = engine.lock().unwrap().pressures().unwrap();
+        // Within a few counts of the truth
+        let truth = |transducer| dummy_hal::feed_pressure(transducer) as f32;
+        assert!((pressures.fuel_tank - truth(Transducer::FuelTank)).abs() < 20e3, "{:?}", pressures);
+        assert!((pressures.oxidizer_tank - truth(Transducer::OxidizerTank)).abs() < 20e3, "{:?}", pressures);
+        assert!(pressures.chamber.abs() < 20e3, "{:?}", pressures);
+    }
+
+    #[test]
+    fn vented_tanks_stop_ignition() {
+        let _exclusive = sim::exclusive();
+        let (mut sensors, engine, nav_state) = setup();
+        dummy_hal::set_tanks_pressurized(false);
+        run(&mut sensors, &engine, &nav_state, 50);
+        let mut engine = engine.lock().unwrap();
+        assert!(engine.pressures().unwrap().fuel_tank < IgnitionConfig::default().min_tank_pressure);
+        assert!(engine.execute_command(EngineCommand::Ignite).is_err());
+        assert_eq!(engine.state(), EngineState::Idle);
+        assert!(!engine.igniter_lit());
+    }
+
+    #[test]
+    fn venting_during_the_burn_aborts() {
+        let _exclusive = sim::exclusive();
+        let (mut sensors, engine, nav_state) = setup();
+        sensors.update().unwrap();
+        engine.lock().unwrap().execute_command(EngineCommand::Ignite).unwrap();
+        run(&mut sensors, &engine, &nav_state, 100);
+        assert_eq!(engine.lock().unwrap().state(), EngineState::Running);
+
+        dummy_hal::set_tanks_pressurized(false);
+        run(&mut sensors, &engine, &nav_state, 50);
+        let engine = engine.lock().unwrap();
+        assert_eq!(engine.state(), EngineState::Aborted);
+        assert_eq!(engine.abort_reason(), Some(AbortReason::LowFeedPressure));
+        assert!(!engine.fuel_valve_open() && !engine.oxidizer_valve_open());
+    }
+}
@@ -0,0 +1,305 @@
+// Vertical state estimator: a constant-acceleration Kalman filter over
+// [altitude, vertical velocity, vertical acceleration], fed by the
+// barometric altitude and the accelerometer.
+//
+// Barometer readings are innovation-gated, since pressure ports see spikes
+// (transonic shocks, ejection charges) that would otherwise drag the
+// altitude around. If the barometer keeps disagreeing, the filter assumes
+// it is the one that has diverged and restarts from the barometer, then
+// takes the next few readings ungated so its velocity can catch up.
+
+const ALTITUDE: usize = 0;
+const VELOCITY: usize = 1;
+const ACCELERATION: usize = 2;
+// Variances assumed for velocity/acceleration after re-syncing to the barometer
+const RESYNC_VELOCITY_VARIANCE: f32 = 100.0;
+const RESYNC_ACCEL_VARIANCE: f32 = 100.0;
+
+pub type Matrix3 = [[f32; 3]; 3];
+
+#[derive(Debug, Clone, Copy)]
+pub struct EstimatorConfig {
+    pub process_noise: f32,     // (m/s^3)^2/Hz, spectral density of the unmodelled jerk
+    pub baro_noise: f32,        // m, 1-sigma barometric altitude noise
+    pub accel_noise: f32,       // m/s^2, 1-sigma vertical acceleration noise
+    pub baro_gate: f32,         // Sigmas; barometer innovations beyond this are rejected
+    pub max_baro_rejections: u32, // Consecutive rejections before the filter re-syncs to the barometer
+}
+
+impl Default for EstimatorConfig {
+    fn default() -> Self {
+        EstimatorConfig {
+            process_noise: 50.0,
+            baro_noise: 0.5,
+            accel_noise: 0.5,
+            baro_gate: 5.0,
+            max_baro_rejections: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AltitudeFilter {
+    config: EstimatorConfig,
+    x: [f32; 3],
+    p: Matrix3,
+    consecutive_rejections: u32,
+    ungated_updates: u32, // Barometer readings still accepted unconditionally after a re-sync
+    baro_rejections: u32, // Total barometer readings rejected as outliers
+}
+
+impl AltitudeFilter {
+    // Starts at rest on the pad (altitude 0 is the barometer's reference)
+    pub fn new(config: EstimatorConfig) -> Self {
+        let mut p = [[0.0; 3]; 3];
+        p[ALTITUDE][ALTITUDE] = config.baro_noise * config.baro_noise;
+        p[VELOCITY][VELOCITY] = 0.1;
+        p[ACCELERATION][ACCELERATION] = config.accel_noise * config.accel_noise;
+        AltitudeFilter {
+            config,
+            x: [0.0; 3],
+            p,
+            consecutive_rejections: 0,
+            ungated_updates: 0,
+            baro_rejections: 0,
+        }
+    }
+
+    pub fn altitude(&self) -> f32 {
+        self.x[ALTITUDE]
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.x[VELOCITY]
+    }
+
+    pub fn acceleration(&self) -> f32 {
+        self.x[ACCELERATION]
+    }
+
+    // Error covariance of [altitude, velocity, acceleration]
+    pub fn covariance(&self) -> Matrix3 {
+        self.p
+    }
+
+    pub fn baro_rejections(&self) -> u32 {
+        self.baro_rejections
+    }
+
+    // Propagate the state `dt` seconds ahead
+    pub fn predict(&mut self, dt: f32) {
+        let f = [[1.0, dt, 0.5 * dt * dt], [0.0, 1.0, dt], [0.0, 0.0, 1.0]];
+        let x = self.x;
+        for (i, row) in f.iter().enumerate() {
+            self.x[i] = row[0] * x[0] + row[1] * x[1] + row[2] * x[2];
+        }
+
+        // Discrete process noise for white jerk
+        let q = self.config.process_noise;
+        let (dt2, dt3) = (dt * dt, dt * dt * dt);
+        let qm = [
+            [q * dt3 * dt2 / 20.0, q * dt2 * dt2 / 8.0, q * dt3 / 6.0],
+            [q * dt2 * dt2 / 8.0, q * dt3 / 3.0, q * dt2 / 2.0],
+            [q * dt3 / 6.0, q * dt2 / 2.0, q * dt],
+        ];
+        let fp = multiply(&f, &self.p);
+        let fpft = multiply(&fp, &transpose(&f));
+        for i in 0..3 {
+            for j in 0..3 {
+                self.p[i][j] = fpft[i][j] + qm[i][j];
+            }
+        }
+    }
+
+    // Fuse a barometric altitude (m above the pad).
+    // Returns false if the reading was rejected as an outlier.
+    pub fn update_altitude(&mut self, altitude: f32) -> bool {
+        let r = self.config.baro_noise * self.config.baro_noise;
+        let innovation = altitude - self.x[ALTITUDE];
+        let s = self.p[ALTITUDE][ALTITUDE] + r;
+        let gate = self.config.baro_gate;
+        let outlier = innovation * innovation > gate * gate * s && self.ungated_updates == 0;
+        if outlier && self.consecutive_rejections < self.config.max_baro_rejections {
+            self.consecutive_rejections += 1;
+            self.baro_rejections += 1;
+            return false;
+        }
+        if self.consecutive_rejections >= self.config.max_baro_rejections {
+            // The barometer has disagreed for too long: it is the filter that's wrong
+            println!("[Component:Estimator] Re-syncing to barometer ({:.1} m off)", innovation);
+            self.x[ALTITUDE] = altitude;
+            self.p = [[0.0; 3]; 3];
+            self.p[ALTITUDE][ALTITUDE] = r;
+            self.p[VELOCITY][VELOCITY] = RESYNC_VELOCITY_VARIANCE;
+            self.p[ACCELERATION][ACCELERATION] = RESYNC_ACCEL_VARIANCE;
+            // Re-syncing leaves the velocity as wrong as it was, and gated
+            // readings would reject it straight back into another re-sync:
+            // let the barometer pull it in first
+            self.ungated_updates = self.config.max_baro_rejections;
+        } else {
+            self.fuse(ALTITUDE, innovation, r);
+            self.ungated_updates = self.ungated_updates.saturating_sub(1);
+        }
+        self.consecutive_rejections = 0;
+        true
+    }
+
+    // Fuse a vertical acceleration (m/s^2, gravity removed)
+    pub fn update_acceleration(&mut self, acceleration: f32) {
+        let r = self.config.accel_noise * self.config.accel_noise;
+        self.fuse(ACCELERATION, acceleration - self.x[ACCELERATION], r);
+    }
+
+    // Scalar measurement update of state `index`
+    fn fuse(&mut self, index: usize, innovation: f32, r: f32) {
+        let s = self.p[index][index] + r;
+        let k = [self.p[0][index] / s, self.p[1][index] / s, self.p[2][index] / s];
+        let row = self.p[index];
+        for (i, gain) in k.iter().enumerate() {
+            self.x[i] += gain * innovation;
+            for (p, r) in self.p[i].iter_mut().zip(row) {
+                *p -= gain * r;
+            }
+        }
+        // Keep P symmetric against rounding
+        for i in 0..3 {
+            for j in i + 1..3 {
+                let mean = 0.5 * (self.p[i][j] + self.p[j][i]);
+                self.p[i][j] = mean;
+                self.p[j][i] = mean;
+            }
+        }
+    }
+}
+
+fn multiply(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn transpose(a: &Matrix3) -> Matrix3 {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in a.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            out[j][i] = *value;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::dummy_hal::gaussian;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const IMU_DT: f32 = 0.005; // 200 Hz accelerometer
+    const BARO_EVERY: usize = 10; // 20 Hz barometer
+
+    // Fly the filter through `seconds` of the trajectory `truth(t) -> (altitude,
+    // acceleration)` with noisy measurements, the way navigation feeds it,
+    // checking the covariance after every step
+    fn run<F: Fn(f32) -> (f32, f32)>(filter: &mut AltitudeFilter, seconds: f32, truth: F) {
+        let mut rng = StdRng::seed_from_u64(9);
+        let c = filter.config;
+        let steps = (seconds / IMU_DT).round() as usize;
+        for step in 1..=steps {
+            let (altitude, acceleration) = truth(step as f32 * IMU_DT);
+            filter.predict(IMU_DT);
+            filter.update_acceleration(acceleration + gaussian(&mut rng, c.accel_noise as f64) as f32);
+            if step % BARO_EVERY == 0 {
+                filter.update_altitude(altitude + gaussian(&mut rng, c.baro_noise as f64) as f32);
+            }
+            assert_valid_covariance(&filter.covariance());
+        }
+    }
+
+    // Symmetric and positive definite (all leading principal minors positive)
+    fn assert_valid_covariance(p: &Matrix3) {
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((p[i][j] - p[j][i]).abs() <= 1e-4 * p[i][i].max(p[j][j]), "asymmetric: {:?}", p);
+            }
+        }
+        let minor2 = p[0][0] * p[1][1] - p[0][1] * p[1][0];
+        let det = p[0][0] * (p[1][1] * p[2][2] - p[1][2] * p[2][1]) - p[0][1] * (p[1][0] * p[2][2] - p[1][2] * p[2][0])
+            + p[0][2] * (p[1][0] * p[2][1] - p[1][1] * p[2][0]);
+        assert!(p[0][0] > 0.0 && minor2 > 0.0 && det > 0.0, "not positive definite: {:?}", p);
+    }
+
+    #[test]
+    fn converges_to_a_constant_altitude() {
+        let mut filter = AltitudeFilter::new(EstimatorConfig::default());
+        run(&mut filter, 10.0, |_| (2.0, 0.0));
+        assert!((filter.altitude() - 2.0).abs() < 0.2, "altitude {}", filter.altitude());
+        assert!(filter.velocity().abs() < 0.2, "velocity {}", filter.velocity());
+        assert!(filter.covariance()[ALTITUDE][ALTITUDE] < 0.25 * 0.25);
+        assert_eq!(filter.baro_rejections(), 0);
+    }
+
+    #[test]
+    fn tracks_velocity_under_constant_acceleration() {
+        let mut filter = AltitudeFilter::new(EstimatorConfig::default());
+        run(&mut filter, 5.0, |t| (10.0 * t * t, 20.0));
+        assert!((filter.velocity() - 100.0).abs() < 1.0, "velocity {}", filter.velocity());
+        assert!((filter.altitude() - 250.0).abs() < 1.0, "altitude {}", filter.altitude());
+        assert!((filter.acceleration() - 20.0).abs() < 1.0, "acceleration {}", filter.acceleration());
+        assert_eq!(filter.baro_rejections(), 0);
+    }
+
+    #[test]
+    fn gate_rejects_an_outlier() {
+        let mut filter = AltitudeFilter::new(EstimatorConfig::default());
+        run(&mut filter, 2.0, |_| (0.0, 0.0));
+        let before = filter.altitude();
+        assert!(!filter.update_altitude(80.0)); // Ejection charge pressure spike
+        assert_eq!(filter.altitude(), before);
+        assert_eq!(filter.baro_rejections(), 1);
+        assert!(filter.update_altitude(0.1));
+        run(&mut filter, 2.0, |_| (0.0, 0.0));
+        assert!(filter.altitude().abs() < 0.2, "altitude {}", filter.altitude());
+        assert_eq!(filter.baro_rejections(), 1);
+    }
+
+    #[test]
+    fn resyncs_after_max_rejections() {
+        let config = EstimatorConfig::default();
+        let mut filter = AltitudeFilter::new(config);
+        run(&mut filter, 2.0, |_| (0.0, 0.0));
+        // The barometer steps away and stays there
+        for _ in 0..config.max_baro_rejections {
+            filter.predict(0.05);
+            assert!(!filter.update_altitude(100.0));
+        }
+        assert!(filter.altitude().abs() < 1.0);
+        filter.predict(0.05);
+        assert!(filter.update_altitude(100.0));
+        assert_eq!(filter.altitude(), 100.0);
+        assert_eq!(filter.baro_rejections(), config.max_baro_rejections);
+        assert_valid_covariance(&filter.covariance());
+        // And tracks it from there
+        run(&mut filter, 2.0, |_| (100.0, 0.0));
+        assert!((filter.altitude() - 100.0).abs() < 0.2, "altitude {}", filter.altitude());
+        assert_eq!(filter.baro_rejections(), config.max_baro_rejections);
+    }
+
+    #[test]
+    fn recovers_velocity_after_a_resync() {
+        // The filter has missed a climb entirely (say the accelerometer was
+        // clipped and the barometer blanked through boost): it thinks it is
+        // at rest on the pad while the vehicle coasts up at 100 m/s
+        let mut filter = AltitudeFilter::new(EstimatorConfig::default());
+        run(&mut filter, 3.0, |t| (100.0 * t, 0.0));
+        assert!((filter.velocity() - 100.0).abs() < 2.0, "velocity {}", filter.velocity());
+        assert!((filter.altitude() - 300.0).abs() < 2.0, "altitude {}", filter.altitude());
+        // Tracking again: the barometer is no longer being thrown away
+        let rejections = filter.baro_rejections();
+        run(&mut filter, 1.0, |t| (300.0 + 100.0 * t, 0.0));
+        assert_eq!(filter.baro_rejections(), rejections);
+    }
+}
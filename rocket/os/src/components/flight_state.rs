@@ -0,0 +1,315 @@
+// Flight state component: tracks the mission phase from the navigation state.
+// Other components query the current phase, or subscribe to be sent every
+// transition as it happens.
+use crate::components::navigation::{NavState, SharedNavState};
+use crate::kernel::{sim::Instant, sync::{self, Channel, ChannelReceiver, ChannelSender, OverflowPolicy}};
+use crate::error::Result;
+use std::time::Duration;
+
+const GRAVITY: f32 = 9.81; // m/s^2
+const SUBSCRIBER_QUEUE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightPhase {
+    Pad,
+    Boost,
+    Coast,
+    Apogee,
+    Descent,
+    Landed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseTransition {
+    pub from: FlightPhase,
+    pub to: FlightPhase,
+    pub time: Instant,  // Kernel time the transition was declared
+    pub reason: String, // Which criterion fired, with the measured value
+}
+
+// Thresholds for each transition. A condition must hold continuously for
+// its `*_confirm` time before the transition is declared.
+#[derive(Debug, Clone, Copy)]
+pub struct FlightCriteria {
+    pub launch_accel: f32,           // m/s^2, axial specific force indicating thrust
+    pub launch_confirm: Duration,
+    pub burnout_accel: f32,          // m/s^2, axial specific force below which thrust has ended
+    pub burnout_confirm: Duration,
+    pub apogee_velocity: f32,        // m/s, vertical velocity at or below which we're at the top
+    pub apogee_pressure_rise: f32,   // Pa above the minimum pressure seen (barometer only)
+    pub apogee_confirm: Duration,
+    pub max_coast_time: Duration,    // Backup: declare apogee this long after burnout
+    pub apogee_hold: Duration,       // Time spent in Apogee before Descent
+    pub landing_velocity: f32,       // m/s, max |vertical velocity| when landed
+    pub landing_accel_tolerance: f32, // m/s^2, max |accel magnitude - g| when landed
+    pub landing_confirm: Duration,
+}
+
+impl Default for FlightCriteria {
+    fn default() -> Self {
+        FlightCriteria {
+            launch_accel: 1.5 * GRAVITY,
+            launch_confirm: Duration::from_millis(100),
+            burnout_accel: 0.5 * GRAVITY,
+            burnout_confirm: Duration::from_millis(100),
+            apogee_velocity: 0.0,
+            apogee_pressure_rise: 30.0, // ~2.5 m below the highest point
+            apogee_confirm: Duration::from_millis(100),
+            max_coast_time: Duration::from_secs(20),
+            apogee_hold: Duration::from_secs(1),
+            landing_velocity: 2.0,
+            landing_accel_tolerance: 1.0,
+            landing_confirm: Duration::from_secs(2),
+        }
+    }
+}
+
+pub struct FlightStateMachine {
+    nav_state: SharedNavState,
+    criteria: FlightCriteria,
+    phase: FlightPhase,
+    phase_entered: Instant,
+    condition_since: Option<Instant>, // When the current phase's exit condition started holding
+    min_pressure: Option<f32>,
+    history: Vec<PhaseTransition>,
+    subscribers: Vec<ChannelSender<PhaseTransition>>,
+}
+
+impl FlightStateMachine {
+    pub fn new(nav_state: SharedNavState, criteria: FlightCriteria) -> Self {
+        println!("[Component:FlightState] Created.");
+        Self {
+            nav_state,
+            criteria,
+            phase: FlightPhase::Pad,
+            phase_entered: sync::get_time(),
+            condition_since: None,
+            min_pressure: None,
+            history: Vec::new(),
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn phase(&self) -> FlightPhase {
+        self.phase
+    }
+
+    pub fn criteria(&self) -> FlightCriteria {
+        self.criteria
+    }
+
+    // Takes effect from the next update
+    pub fn set_criteria(&mut self, criteria: FlightCriteria) {
+        self.criteria = criteria;
+    }
+
+    // Every transition so far, oldest first
+    pub fn history(&self) -> &[PhaseTransition] {
+        &self.history
+    }
+
+    // Receive every future transition. If the subscriber falls behind, the
+    // oldest undelivered transitions are dropped.
+    pub fn subscribe(&mut self) -> ChannelReceiver<PhaseTransition> {
+        let (tx, rx) = Channel::new(SUBSCRIBER_QUEUE_LEN, OverflowPolicy::OverwriteOldest).split();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    // Evaluate the exit condition of the current phase; called every nav cycle
+    pub fn update(&mut self) -> Result<()> {
+        let nav = self.nav_state.get()?;
+        if nav.last_update.is_none() {
+            return Ok(()); // No data yet
+        }
+        let now = sync::get_time();
+        let c = self.criteria;
+        let axial = nav.accel[2];
+
+        if let Some(p) = nav.pressure {
+            if self.phase != FlightPhase::Pad {
+                self.min_pressure = Some(self.min_pressure.map_or(p, |m| m.min(p)));
+            }
+        }
+
+        match self.phase {
+            FlightPhase::Pad => {
+                if self.held(axial > c.launch_accel, now, c.launch_confirm) {
+                    self.transition(FlightPhase::Boost, now, format!("axial accel {:.1} m/s^2", axial));
+                }
+            }
+            FlightPhase::Boost => {
+                if self.held(axial < c.burnout_accel, now, c.burnout_confirm) {
+                    self.transition(FlightPhase::Coast, now, format!("axial accel {:.1} m/s^2", axial));
+                }
+            }
+            FlightPhase::Coast => {
+                let pressure_rise = match (nav.pressure, self.min_pressure) {
+                    (Some(p), Some(min)) => p - min,
+                    _ => 0.0,
+                };
+                let falling = nav.vertical_velocity <= c.apogee_velocity
+                    || pressure_rise >= c.apogee_pressure_rise;
+                if self.held(falling, now, c.apogee_confirm) {
+                    self.transition(
+                        FlightPhase::Apogee,
+                        now,
+                        format!("vertical velocity {:.1} m/s, pressure rise {:.0} Pa", nav.vertical_velocity, pressure_rise),
+                    );
+                } else if now.duration_since(self.phase_entered) >= c.max_coast_time {
+                    self.transition(FlightPhase::Apogee, now, "coast timer expired".to_string());
+                }
+            }
+            FlightPhase::Apogee => {
+                if now.duration_since(self.phase_entered) >= c.apogee_hold {
+                    self.transition(FlightPhase::Descent, now, format!("altitude {:.1} m", nav.altitude));
+                }
+            }
+            FlightPhase::Descent => {
+                if self.held(Self::at_rest(&nav, &c), now, c.landing_confirm) {
+                    self.transition(FlightPhase::Landed, now, format!("at rest, altitude {:.1} m", nav.altitude));
+                }
+            }
+            FlightPhase::Landed => {}
+        }
+        Ok(())
+    }
+
+    fn at_rest(nav: &NavState, c: &FlightCriteria) -> bool {
+        let a = nav.accel;
+        let magnitude = (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+        nav.vertical_velocity.abs() <= c.landing_velocity
+            && (magnitude - GRAVITY).abs() <= c.landing_accel_tolerance
+    }
+
+    // True once `condition` has held continuously for `confirm`
+    fn held(&mut self, condition: bool, now: Instant, confirm: Duration) -> bool {
+        if !condition {
+            self.condition_since = None;
+            return false;
+        }
+        let since = *self.condition_since.get_or_insert(now);
+        now.duration_since(since) >= confirm
+    }
+
+    fn transition(&mut self, to: FlightPhase, now: Instant, reason: String) {
+        let transition = PhaseTransition { from: self.phase, to, time: now, reason };
+        println!(
+            "[Component:FlightState] t={:.2}s {:?} -> {:?} ({})",
+            now.as_secs_f64(),
+            transition.from,
+            transition.to,
+            transition.reason
+        );
+        self.phase = to;
+        self.phase_entered = now;
+        self.condition_since = None;
+        // Forget subscribers that have gone away
+        self.subscribers.retain(|tx| tx.send(transition.clone()).is_ok());
+        self.history.push(transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::sim::{self, ClockMode};
+
+    const CYCLE: Duration = Duration::from_millis(50); // Nav loop period
+
+    struct Flight {
+        nav: SharedNavState,
+        fsm: FlightStateMachine,
+    }
+
+    impl Flight {
+        fn new() -> Self {
+            sim::init(ClockMode::Simulated, 0);
+            let nav = SharedNavState::new();
+            let fsm = FlightStateMachine::new(nav.clone(), FlightCriteria::default());
+            Flight { nav, fsm }
+        }
+
+        // One nav cycle with the given axial specific force and vertical velocity
+        fn cycle(&mut self, axial: f32, vertical_velocity: f32) -> FlightPhase {
+            sim::sleep(CYCLE);
+            self.nav
+                .update(|nav| {
+                    nav.accel = [0.0, 0.0, axial];
+                    nav.vertical_velocity = vertical_velocity;
+                    nav.last_update = Some(sync::get_time());
+                })
+                .unwrap();
+            self.fsm.update().unwrap();
+            self.fsm.phase()
+        }
+
+        fn cycles(&mut self, count: usize, axial: f32, vertical_velocity: f32) -> FlightPhase {
+            (0..count).map(|_| self.cycle(axial, vertical_velocity)).last().unwrap()
+        }
+    }
+
+    #[test]
+    fn steps_through_every_phase_of_a_flight() {
+        let _exclusive = sim::exclusive();
+        let mut flight = Flight::new();
+        let transitions = flight.fsm.subscribe();
+
+        assert_eq!(flight.cycles(10, GRAVITY, 0.0), FlightPhase::Pad);
+        // Launch needs the thrust for launch_confirm (two cycles after the first)
+        assert_eq!(flight.cycles(2, 3.0 * GRAVITY, 10.0), FlightPhase::Pad);
+        assert_eq!(flight.cycle(3.0 * GRAVITY, 20.0), FlightPhase::Boost);
+        assert_eq!(flight.cycles(20, 3.0 * GRAVITY, 100.0), FlightPhase::Boost);
+        // Burnout: drag alone decelerates
+        assert_eq!(flight.cycles(3, -2.0, 150.0), FlightPhase::Coast);
+        assert_eq!(flight.cycles(100, -1.0, 50.0), FlightPhase::Coast);
+        assert_eq!(flight.cycles(3, 0.0, -0.5), FlightPhase::Apogee);
+        // Held for apogee_hold whatever the state does
+        assert_eq!(flight.cycles(19, GRAVITY, -20.0), FlightPhase::Apogee);
+        assert_eq!(flight.cycle(GRAVITY, -20.0), FlightPhase::Descent);
+        assert_eq!(flight.cycles(200, GRAVITY, -6.0), FlightPhase::Descent);
+        // Landing needs landing_confirm at rest
+        assert_eq!(flight.cycles(40, GRAVITY, 0.0), FlightPhase::Descent);
+        assert_eq!(flight.cycle(GRAVITY, 0.0), FlightPhase::Landed);
+        assert_eq!(flight.cycles(10, 3.0 * GRAVITY, 0.0), FlightPhase::Landed);
+
+        let expected = [
+            (FlightPhase::Pad, FlightPhase::Boost),
+            (FlightPhase::Boost, FlightPhase::Coast),
+            (FlightPhase::Coast, FlightPhase::Apogee),
+            (FlightPhase::Apogee, FlightPhase::Descent),
+            (FlightPhase::Descent, FlightPhase::Landed),
+        ];
+        let history: Vec<_> = flight.fsm.history().iter().map(|t| (t.from, t.to)).collect();
+        assert_eq!(history, expected);
+        let sent: Vec<_> = std::iter::from_fn(|| transitions.try_recv().unwrap()).collect();
+        assert_eq!(sent, flight.fsm.history());
+    }
+
+    #[test]
+    fn single_sample_spikes_do_not_change_phase() {
+        let _exclusive = sim::exclusive();
+        let mut flight = Flight::new();
+
+        for _ in 0..5 {
+            flight.cycle(GRAVITY, 0.0);
+            assert_eq!(flight.cycle(10.0 * GRAVITY, 0.0), FlightPhase::Pad); // Bump on the pad
+        }
+        // Two-cycle spikes don't last launch_confirm either
+        flight.cycle(GRAVITY, 0.0);
+        flight.cycles(2, 10.0 * GRAVITY, 0.0);
+        assert_eq!(flight.cycle(GRAVITY, 0.0), FlightPhase::Pad);
+
+        flight.cycles(3, 3.0 * GRAVITY, 20.0);
+        for _ in 0..5 {
+            flight.cycle(3.0 * GRAVITY, 50.0);
+            assert_eq!(flight.cycle(0.0, 50.0), FlightPhase::Boost); // Combustion instability dip
+        }
+        flight.cycles(3, -2.0, 150.0);
+        for _ in 0..5 {
+            flight.cycle(-1.0, 50.0);
+            assert_eq!(flight.cycle(-1.0, -3.0), FlightPhase::Coast); // Velocity glitch
+        }
+        assert_eq!(flight.fsm.history().len(), 2);
+    }
+}
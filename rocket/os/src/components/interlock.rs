@@ -0,0 +1,385 @@
+// Safety interlock: the only owner of the propellant valves and the
+// igniter, so every actuator command has to get past it. Opening a valve or
+// firing the igniter needs
+//   - the physical arm switch closed (it also switches actuator power),
+//   - continuity through that valve's coil or the igniter,
+//   - and software permission: armed for the engine sequence (arm first,
+//     fire as a separate second step), or checkout for manual valve tests.
+//     The igniter is never worked by hand.
+// Closing a valve or cutting the igniter is always allowed. Refusals come
+// back as InterlockError, never as a silently ignored command.
+use crate::hal::interface::{OutputPin, InputPin};
+use crate::drivers::valve::Valve;
+use crate::error::{ComponentError, Result, RocketError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValveId {
+    Fuel,
+    Oxidizer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterlockState {
+    Safe,
+    Armed,    // The engine sequence may fire the valves
+    Checkout, // Valves may be worked one at a time by hand
+}
+
+// Why the interlock refused to change state or open a valve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterlockError {
+    ArmSwitchSafe,          // Physical arm switch open
+    NoContinuity(ValveId),  // Open circuit through the valve coil
+    NoIgniterContinuity,    // Open circuit through the igniter
+    NotArmed,               // Fire without the arm step
+    NotInCheckout,          // Manual valve command outside checkout
+    Armed,                  // Checkout requested while armed
+    CheckoutActive,         // Arm requested during checkout
+}
+
+pub struct SafetyInterlock<P: OutputPin, S: InputPin> {
+    fuel_valve: Valve<P>,
+    oxidizer_valve: Valve<P>,
+    igniter: P,             // High to fire
+    arm_switch: S,          // High when closed (ARM)
+    fuel_continuity: S,     // High when the coil is intact
+    oxidizer_continuity: S,
+    igniter_continuity: S,  // High when the igniter is intact
+    igniter_lit: bool,
+    state: InterlockState,
+}
+
+impl<P: OutputPin, S: InputPin> SafetyInterlock<P, S> {
+    pub fn new(
+        fuel_valve: Valve<P>,
+        oxidizer_valve: Valve<P>,
+        mut igniter: P,
+        arm_switch: S,
+        fuel_continuity: S,
+        oxidizer_continuity: S,
+        igniter_continuity: S,
+    ) -> Result<Self> {
+        igniter.set_low()?;
+        println!("[Component:Interlock] Created, safe.");
+        Ok(Self {
+            fuel_valve,
+            oxidizer_valve,
+            igniter,
+            arm_switch,
+            fuel_continuity,
+            oxidizer_continuity,
+            igniter_continuity,
+            igniter_lit: false,
+            state: InterlockState::Safe,
+        })
+    }
+
+    pub fn state(&self) -> InterlockState {
+        self.state
+    }
+
+    pub fn arm_switch_closed(&self) -> Result<bool> {
+        Ok(self.arm_switch.is_high()?)
+    }
+
+    pub fn continuity(&self, valve: ValveId) -> Result<bool> {
+        let pin = match valve {
+            ValveId::Fuel => &self.fuel_continuity,
+            ValveId::Oxidizer => &self.oxidizer_continuity,
+        };
+        Ok(pin.is_high()?)
+    }
+
+    pub fn igniter_continuity(&self) -> Result<bool> {
+        Ok(self.igniter_continuity.is_high()?)
+    }
+
+    pub fn igniter_lit(&self) -> bool {
+        self.igniter_lit
+    }
+
+    pub fn valve_open(&self, valve: ValveId) -> bool {
+        match valve {
+            ValveId::Fuel => self.fuel_valve.is_open(),
+            ValveId::Oxidizer => self.oxidizer_valve.is_open(),
+        }
+    }
+
+    // Step one: permit the engine sequence to fire. Checks the switch, both
+    // coils and the igniter now; firing checks them again.
+    pub fn arm(&mut self) -> Result<()> {
+        match self.state {
+            InterlockState::Armed => return Ok(()),
+            InterlockState::Checkout => return Err(refused(InterlockError::CheckoutActive)),
+            InterlockState::Safe => {}
+        }
+        self.check_switch()?;
+        self.check_continuity(ValveId::Fuel)?;
+        self.check_continuity(ValveId::Oxidizer)?;
+        if !self.igniter_continuity()? {
+            return Err(refused(InterlockError::NoIgniterContinuity));
+        }
+        println!("[Component:Interlock] Armed.");
+        self.state = InterlockState::Armed;
+        Ok(())
+    }
+
+    // Withdraw permission to fire. Open valves stay as they are.
+    pub fn disarm(&mut self) -> Result<()> {
+        if self.state == InterlockState::Armed {
+            println!("[Component:Interlock] Disarmed.");
+            self.state = InterlockState::Safe;
+        }
+        Ok(())
+    }
+
+    // Step two: open a valve as part of the engine sequence
+    pub fn fire(&mut self, valve: ValveId) -> Result<()> {
+        if self.state != InterlockState::Armed {
+            return Err(refused(InterlockError::NotArmed));
+        }
+        self.open(valve)
+    }
+
+    pub fn fire_igniter(&mut self) -> Result<()> {
+        if self.state != InterlockState::Armed {
+            return Err(refused(InterlockError::NotArmed));
+        }
+        self.check_switch()?;
+        if !self.igniter_continuity()? {
+            return Err(refused(InterlockError::NoIgniterContinuity));
+        }
+        if !self.igniter_lit {
+            println!("[Component:Interlock] Igniter on.");
+            self.igniter.set_high()?;
+            self.igniter_lit = true;
+        }
+        Ok(())
+    }
+
+    pub fn igniter_off(&mut self) -> Result<()> {
+        if self.igniter_lit {
+            println!("[Component:Interlock] Igniter off.");
+            self.igniter.set_low()?;
+            self.igniter_lit = false;
+        }
+        Ok(())
+    }
+
+    pub fn enter_checkout(&mut self) -> Result<()> {
+        match self.state {
+            InterlockState::Checkout => return Ok(()),
+            InterlockState::Armed => return Err(refused(InterlockError::Armed)),
+            InterlockState::Safe => {}
+        }
+        self.check_switch()?;
+        println!("[Component:Interlock] Checkout.");
+        self.state = InterlockState::Checkout;
+        Ok(())
+    }
+
+    // Leaving checkout closes both valves
+    pub fn exit_checkout(&mut self) -> Result<()> {
+        if self.state != InterlockState::Checkout {
+            return Ok(());
+        }
+        println!("[Component:Interlock] Checkout finished.");
+        self.safe()
+    }
+
+    pub fn checkout_valve(&mut self, valve: ValveId, open: bool) -> Result<()> {
+        if self.state != InterlockState::Checkout {
+            return Err(refused(InterlockError::NotInCheckout));
+        }
+        if open { self.open(valve) } else { self.close(valve) }
+    }
+
+    // Closing is the safe direction and is never refused
+    pub fn close(&mut self, valve: ValveId) -> Result<()> {
+        match valve {
+            ValveId::Fuel => self.fuel_valve.close(),
+            ValveId::Oxidizer => self.oxidizer_valve.close(),
+        }
+    }
+
+    // Close both valves (fuel first), cut the igniter and drop back to Safe
+    pub fn safe(&mut self) -> Result<()> {
+        // Attempt every step even if an earlier one fails
+        let fuel = self.fuel_valve.close();
+        let oxidizer = self.oxidizer_valve.close();
+        let igniter = self.igniter_off();
+        if self.state != InterlockState::Safe {
+            println!("[Component:Interlock] Safe.");
+            self.state = InterlockState::Safe;
+        }
+        fuel.and(oxidizer).and(igniter)
+    }
+
+    // Watch the arm switch. Opening it cuts valve power, so follow it down
+    // to Safe. Returns true if that just happened.
+    pub fn update(&mut self) -> Result<bool> {
+        if self.state == InterlockState::Safe || self.arm_switch_closed()? {
+            return Ok(false);
+        }
+        println!("[Component:Interlock] Arm switch opened while {:?}.", self.state);
+        self.safe()?;
+        Ok(true)
+    }
+
+    fn open(&mut self, valve: ValveId) -> Result<()> {
+        self.check_switch()?;
+        self.check_continuity(valve)?;
+        match valve {
+            ValveId::Fuel => self.fuel_valve.open(),
+            ValveId::Oxidizer => self.oxidizer_valve.open(),
+        }
+    }
+
+    fn check_switch(&self) -> Result<()> {
+        if self.arm_switch_closed()? { Ok(()) } else { Err(refused(InterlockError::ArmSwitchSafe)) }
+    }
+
+    fn check_continuity(&self, valve: ValveId) -> Result<()> {
+        if self.continuity(valve)? { Ok(()) } else { Err(refused(InterlockError::NoContinuity(valve))) }
+    }
+}
+
+// The reason travels as text: error.rs sits below the components
+fn refused(e: InterlockError) -> RocketError {
+    println!("[Component:Interlock] Refused: {:?}", e);
+    ComponentError::Interlock(format!("{:?}", e)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::hal::dummy_hal::{self, DummyPin};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+
+    const FUEL_VALVE_PIN: u8 = config::DUMMY_VALVE_PIN;
+    const OXIDIZER_VALVE_PIN: u8 = config::DUMMY_VALVE_PIN + 1;
+
+    // An interlock on the dummy HAL, with the arm switch as given and every
+    // circuit intact
+    fn interlock(arm_switch: bool) -> SafetyInterlock<DummyPin, DummyPin> {
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_arm_switch(arm_switch);
+        let hal = dummy_hal::get_dummy_hal();
+        let pin = |id| hal.get_gpio_pin(id).unwrap();
+        SafetyInterlock::new(
+            Valve::new(pin(FUEL_VALVE_PIN)).unwrap(),
+            Valve::new(pin(OXIDIZER_VALVE_PIN)).unwrap(),
+            pin(config::DUMMY_IGNITER_PIN),
+            pin(config::DUMMY_ARM_SWITCH_PIN),
+            pin(config::DUMMY_VALVE_CONTINUITY_PIN),
+            pin(config::DUMMY_VALVE_CONTINUITY_PIN + 1),
+            pin(config::DUMMY_IGNITER_CONTINUITY_PIN),
+        )
+        .unwrap()
+    }
+
+    fn refusal(e: InterlockError) -> Result<()> {
+        Err(ComponentError::Interlock(format!("{:?}", e)).into())
+    }
+
+    #[test]
+    fn fires_only_after_arming() {
+        let _exclusive = sim::exclusive();
+        let mut interlock = interlock(true);
+        assert_eq!(interlock.fire(ValveId::Fuel), refusal(InterlockError::NotArmed));
+        assert_eq!(interlock.fire_igniter(), refusal(InterlockError::NotArmed));
+        assert!(!interlock.valve_open(ValveId::Fuel) && !interlock.igniter_lit());
+
+        interlock.arm().unwrap();
+        interlock.fire(ValveId::Fuel).unwrap();
+        interlock.fire_igniter().unwrap();
+        assert!(interlock.valve_open(ValveId::Fuel) && interlock.igniter_lit());
+        // Working a valve by hand still needs checkout
+        assert_eq!(interlock.checkout_valve(ValveId::Oxidizer, true), refusal(InterlockError::NotInCheckout));
+    }
+
+    #[test]
+    fn the_arm_switch_must_be_closed() {
+        let _exclusive = sim::exclusive();
+        let mut interlock = interlock(false);
+        assert_eq!(interlock.arm(), refusal(InterlockError::ArmSwitchSafe));
+        assert_eq!(interlock.enter_checkout(), refusal(InterlockError::ArmSwitchSafe));
+        assert_eq!(interlock.state(), InterlockState::Safe);
+
+        // Opened between the arm and fire steps
+        dummy_hal::set_arm_switch(true);
+        interlock.arm().unwrap();
+        dummy_hal::set_arm_switch(false);
+        assert_eq!(interlock.fire(ValveId::Oxidizer), refusal(InterlockError::ArmSwitchSafe));
+        assert_eq!(interlock.fire_igniter(), refusal(InterlockError::ArmSwitchSafe));
+        assert!(!interlock.valve_open(ValveId::Oxidizer) && !interlock.igniter_lit());
+    }
+
+    #[test]
+    fn every_circuit_needs_continuity() {
+        let _exclusive = sim::exclusive();
+        let mut interlock = interlock(true);
+        dummy_hal::set_open_circuit(OXIDIZER_VALVE_PIN, true);
+        assert_eq!(interlock.arm(), refusal(InterlockError::NoContinuity(ValveId::Oxidizer)));
+        dummy_hal::set_open_circuit(OXIDIZER_VALVE_PIN, false);
+        dummy_hal::set_open_circuit(config::DUMMY_IGNITER_PIN, true);
+        assert_eq!(interlock.arm(), refusal(InterlockError::NoIgniterContinuity));
+        assert_eq!(interlock.state(), InterlockState::Safe);
+
+        // Broken after arming: checked again on firing
+        dummy_hal::set_open_circuit(config::DUMMY_IGNITER_PIN, false);
+        interlock.arm().unwrap();
+        dummy_hal::set_open_circuit(FUEL_VALVE_PIN, true);
+        dummy_hal::set_open_circuit(config::DUMMY_IGNITER_PIN, true);
+        assert_eq!(interlock.fire(ValveId::Fuel), refusal(InterlockError::NoContinuity(ValveId::Fuel)));
+        assert_eq!(interlock.fire_igniter(), refusal(InterlockError::NoIgniterContinuity));
+        interlock.fire(ValveId::Oxidizer).unwrap();
+        assert!(!interlock.valve_open(ValveId::Fuel) && !interlock.igniter_lit());
+    }
+
+    #[test]
+    fn arming_and_checkout_exclude_each_other() {
+        let _exclusive = sim::exclusive();
+        let mut interlock = interlock(true);
+        interlock.enter_checkout().unwrap();
+        assert_eq!(interlock.arm(), refusal(InterlockError::CheckoutActive));
+        interlock.checkout_valve(ValveId::Fuel, true).unwrap();
+        assert_eq!(interlock.fire(ValveId::Oxidizer), refusal(InterlockError::NotArmed));
+        interlock.exit_checkout().unwrap();
+        assert!(!interlock.valve_open(ValveId::Fuel));
+
+        interlock.arm().unwrap();
+        assert_eq!(interlock.enter_checkout(), refusal(InterlockError::Armed));
+        assert_eq!(interlock.state(), InterlockState::Armed);
+    }
+
+    #[test]
+    fn opening_the_arm_switch_makes_everything_safe() {
+        let _exclusive = sim::exclusive();
+        let mut interlock = interlock(true);
+        interlock.arm().unwrap();
+        interlock.fire(ValveId::Fuel).unwrap();
+        interlock.fire(ValveId::Oxidizer).unwrap();
+        interlock.fire_igniter().unwrap();
+        assert!(!interlock.update().unwrap());
+
+        dummy_hal::set_arm_switch(false);
+        assert!(interlock.update().unwrap());
+        assert_eq!(interlock.state(), InterlockState::Safe);
+        assert!(!interlock.valve_open(ValveId::Fuel) && !interlock.valve_open(ValveId::Oxidizer));
+        assert!(!interlock.igniter_lit());
+        assert!(!interlock.update().unwrap()); // Already safe
+
+        // A coil broken while armed doesn't stop the valves closing
+        dummy_hal::set_arm_switch(true);
+        interlock.arm().unwrap();
+        interlock.fire(ValveId::Oxidizer).unwrap();
+        dummy_hal::set_open_circuit(OXIDIZER_VALVE_PIN, true);
+        dummy_hal::set_arm_switch(false);
+        assert!(interlock.update().unwrap());
+        assert!(!interlock.valve_open(ValveId::Oxidizer));
+    }
+}
@@ -0,0 +1,13 @@
+pub mod navigation;
+pub mod estimator;
+pub mod attitude;
+pub mod engine_control;
+pub mod engine_sensors;
+pub mod apogee_control;
+pub mod recovery;
+pub mod interlock;
+pub mod telemetry;
+pub mod telemetry_frame;
+pub mod uplink;
+pub mod uplink_frame;
+pub mod flight_state;
@@ -0,0 +1,355 @@
+// Navigation component: drains the IMU FIFO, samples the barometer and
+// publishes the latest state to the rest of the system through SharedNavState.
+use crate::hal::interface::{I2cBus, InputPin, DelayMs};
+use crate::drivers::imu::{Imu, ImuData};
+use crate::drivers::barometer::{self, Barometer};
+use crate::drivers::high_g_accel::{HighGAccel, HighGAccelData};
+use crate::components::estimator::{AltitudeFilter, EstimatorConfig, Matrix3};
+use crate::components::attitude::{Ahrs, AhrsConfig, Quaternion};
+use crate::kernel::{sim::Instant, sync::{self, Mutex}};
+use crate::error::Result;
+use std::sync::Arc;
+
+const GRAVITY: f32 = 9.81; // m/s^2, matches the IMU driver's scaling
+// Crossfade from the IMU to the high-g accelerometer as the largest IMU axis
+// goes from BLEND_START to BLEND_END of its full scale. The IMU's error grows
+// near the rails well before it pins, and a fade avoids stepping the
+// acceleration (and the filters) by the offset between the two parts.
+const BLEND_START: f32 = 0.8;
+const BLEND_END: f32 = 0.95;
+
+// Which accelerometer `NavState::accel` came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccelSource {
+    #[default]
+    LowG,    // The IMU (fine resolution)
+    Blended, // Both, the IMU being close to full scale
+    HighG,   // The high-g accelerometer, the IMU being at or beyond full scale
+}
+
+// Snapshot of the vehicle state as seen by navigation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NavState {
+    pub imu: ImuData,                 // Latest IMU sample (physical units)
+    pub accel: [f32; 3],              // m/s^2, body frame, IMU and high-g blended by range
+    pub accel_source: AccelSource,
+    pub sample_count: u32,            // Number of IMU samples processed
+    pub imu_overflows: u32,           // IMU FIFO overflows (samples lost) so far
+    pub last_update: Option<Instant>, // When `imu` was sampled (None until first read)
+    pub altitude: f32,                // m above the pad (filtered)
+    pub vertical_velocity: f32,       // m/s, positive up (filtered)
+    pub vertical_accel: f32,          // m/s^2, positive up, gravity removed (filtered)
+    pub covariance: Matrix3,          // Estimate covariance of [altitude, velocity, accel]
+    pub baro_rejections: u32,         // Barometer readings rejected as outliers so far
+    pub pressure: Option<f32>,        // Pa, static pressure (None until the barometer is read)
+    pub baro_altitude: Option<f32>,   // m above the pad, from pressure alone
+    pub attitude: Quaternion,         // Body to world (world z up, body z along the nose)
+    pub roll: f32,                    // rad, see Quaternion::euler_angles
+    pub pitch: f32,                   // rad
+    pub yaw: f32,                     // rad, spin about the long axis (drifts)
+    pub tilt: f32,                    // rad, nose angle from vertical
+    pub angular_rate: [f32; 3],       // rad/s, body frame, gyro bias removed
+}
+
+// Handle to the navigation state shared between tasks.
+// Cloning the handle shares the same underlying state.
+#[derive(Debug, Clone)]
+pub struct SharedNavState(Mutex<NavState>);
+
+impl SharedNavState {
+    pub fn new() -> Self {
+        SharedNavState(Mutex::new(NavState::default()))
+    }
+
+    // Copy out the current state (keeps the lock short)
+    pub fn get(&self) -> Result<NavState> {
+        Ok(*self.0.lock()?)
+    }
+
+    // Modify the state in place while holding the lock
+    pub fn update<F: FnOnce(&mut NavState)>(&self, f: F) -> Result<()> {
+        let mut state = self.0.lock()?;
+        f(&mut state);
+        Ok(())
+    }
+}
+
+impl Default for SharedNavState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Navigation<I2C, INT, DELAY>
+where
+    I2C: I2cBus,
+    INT: InputPin,
+    DELAY: DelayMs,
+{
+    imu: Arc<Mutex<Imu<I2C, INT, DELAY>>>,
+    high_g: Arc<Mutex<HighGAccel<I2C, DELAY>>>,
+    baro: Arc<Mutex<Barometer<I2C, DELAY>>>,
+    state: SharedNavState,
+    pad_pressure: Option<f32>, // Pa, first barometer reading (altitude reference)
+    filter: AltitudeFilter,
+    ahrs: Ahrs,
+    last_sample: Option<Instant>, // Timestamp of the last IMU sample fused
+    last_high_g: Option<HighGAccelData>, // High-g reading from the previous update
+    filter_time: Option<Instant>, // Time the altitude filter has been propagated to
+}
+
+impl<I2C, INT, DELAY> Navigation<I2C, INT, DELAY>
+where
+    I2C: I2cBus,
+    INT: InputPin,
+    DELAY: DelayMs,
+{
+    pub fn new(
+        imu: Arc<Mutex<Imu<I2C, INT, DELAY>>>,
+        high_g: Arc<Mutex<HighGAccel<I2C, DELAY>>>,
+        baro: Arc<Mutex<Barometer<I2C, DELAY>>>,
+        state: SharedNavState,
+        config: EstimatorConfig,
+        ahrs_config: AhrsConfig,
+    ) -> Self {
+        println!("[Component:Navigation] Created.");
+        Self {
+            imu,
+            high_g,
+            baro,
+            state,
+            pad_pressure: None,
+            filter: AltitudeFilter::new(config),
+            ahrs: Ahrs::new(ahrs_config),
+            last_sample: None,
+            last_high_g: None,
+            filter_time: None,
+        }
+    }
+
+    // Run every IMU sample queued since the last update through the
+    // attitude and altitude filters at its own timestamp, then fuse one
+    // barometer reading and publish the result to the shared state.
+    // Acceleration comes from the IMU, crossfading to the high-g
+    // accelerometer as the IMU nears full scale (see blend_accel); it is
+    // rotated into the world frame for the altitude filter, and left out if
+    // both sensors clip. The high-g part is read once per update and each
+    // sample gets that reading interpolated to its own timestamp (see
+    // high_g_at).
+    pub fn update(&mut self) -> Result<()> {
+        let (samples, imu_overflows, imu_full_scale) = {
+            let mut imu = self.imu.lock()?;
+            (imu.read_fifo()?, imu.fifo_overflows(), imu.config().accel_range.full_scale())
+        }; // IMU lock released before touching the shared state
+        let now = sync::get_time();
+        let high_g = self.high_g.lock()?.read_data()?;
+        let last_high_g = self.last_high_g.replace(high_g);
+
+        let mut latest = None;
+        for sample in &samples {
+            let data = sample.data;
+            let high_g = high_g_at(last_high_g.as_ref(), &high_g, sample.timestamp);
+            let (accel, accel_source) = blend_accel(&data, &high_g, imu_full_scale);
+            let dt = self.last_sample.map_or(0.0, |last| sample.timestamp.duration_since(last).as_secs_f32());
+            self.last_sample = Some(sample.timestamp);
+            self.ahrs.update(data.gyro, accel, dt);
+
+            self.predict_to(sample.timestamp);
+            // A clipped reading understates the acceleration; coast on the model instead
+            if !(data.accel_saturated && high_g.saturated) {
+                self.filter.update_acceleration(self.ahrs.attitude().rotate(accel)[2] - GRAVITY);
+            }
+            latest = Some((data, accel, accel_source, sample.timestamp));
+        }
+
+        let baro_data = self.baro.lock()?.read_data()?;
+        let pad_pressure = *self.pad_pressure.get_or_insert(baro_data.pressure);
+        let baro_altitude = barometer::pressure_to_altitude(baro_data.pressure, pad_pressure);
+        self.predict_to(now);
+        self.filter.update_altitude(baro_altitude);
+
+        let attitude = self.ahrs.attitude();
+        let (roll, pitch, yaw) = attitude.euler_angles();
+        let filter = &self.filter;
+        let ahrs = &self.ahrs;
+        self.state.update(|state| {
+            state.attitude = attitude;
+            state.roll = roll;
+            state.pitch = pitch;
+            state.yaw = yaw;
+            state.tilt = attitude.tilt();
+            state.altitude = filter.altitude();
+            state.vertical_velocity = filter.velocity();
+            state.vertical_accel = filter.acceleration();
+            state.covariance = filter.covariance();
+            state.baro_rejections = filter.baro_rejections();
+            if let Some((data, accel, accel_source, timestamp)) = latest {
+                state.imu = data;
+                state.accel = accel;
+                state.accel_source = accel_source;
+                state.angular_rate = ahrs.angular_rate(data.gyro);
+                state.last_update = Some(timestamp);
+            }
+            state.pressure = Some(baro_data.pressure);
+            state.baro_altitude = Some(baro_altitude);
+            state.sample_count = state.sample_count.wrapping_add(samples.len() as u32);
+            state.imu_overflows = imu_overflows;
+        })
+    }
+
+    // Propagate the altitude filter up to `time` (never backwards)
+    fn predict_to(&mut self, time: Instant) {
+        let dt = self.filter_time.map_or(0.0, |last| time.duration_since(last).as_secs_f32());
+        self.filter.predict(dt);
+        self.filter_time = Some(self.filter_time.map_or(time, |last| last.max(time)));
+    }
+}
+
+// High-g reading at `time`, interpolated between the previous and latest
+// reads. The IMU samples in a batch span the whole loop period before the
+// latest read (50 ms in flight); using that one read for all of them would
+// lag the oldest by up to the loop period, an error of the whole change in
+// acceleration over that time (large at ignition and burnout). Interpolating
+// leaves only the curvature of the acceleration over the loop period, and
+// the part's own 100 Hz output rate limits what it resolves in any case. A
+// reading flagged saturated at either end taints the interpolation.
+fn high_g_at(previous: Option<&HighGAccelData>, latest: &HighGAccelData, time: Instant) -> HighGAccelData {
+    let Some(previous) = previous.filter(|previous| previous.timestamp < latest.timestamp) else {
+        return *latest; // First update: nothing to interpolate from
+    };
+    let span = latest.timestamp.duration_since(previous.timestamp).as_secs_f32();
+    let fraction = (time.duration_since(previous.timestamp).as_secs_f32() / span).min(1.0);
+    HighGAccelData {
+        accel: [0, 1, 2].map(|axis| previous.accel[axis] + fraction * (latest.accel[axis] - previous.accel[axis])),
+        saturated: latest.saturated || (previous.saturated && fraction < 1.0),
+        timestamp: time,
+    }
+}
+
+// Weighted mix of the IMU and high-g readings: all IMU below BLEND_START of
+// its full scale, all high-g from BLEND_END or once the IMU has pinned
+fn blend_accel(imu: &ImuData, high_g: &HighGAccelData, imu_full_scale: f32) -> ([f32; 3], AccelSource) {
+    let peak = imu.accel.iter().fold(0.0f32, |peak, a| peak.max(a.abs())) / imu_full_scale;
+    let weight = if imu.accel_saturated { 1.0 } else { ((peak - BLEND_START) / (BLEND_END - BLEND_START)).clamp(0.0, 1.0) };
+    let source = match weight {
+        w if w <= 0.0 => AccelSource::LowG,
+        w if w >= 1.0 => AccelSource::HighG,
+        _ => AccelSource::Blended,
+    };
+    let accel = [0, 1, 2].map(|axis| imu.accel[axis] + weight * (high_g.accel[axis] - imu.accel[axis]));
+    (accel, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::drivers::imu::{AccelRange, ImuConfig};
+    use crate::hal::dummy_hal;
+    use crate::hal::interface::{FullHardwareAbstraction, OutputPin};
+    use crate::kernel::sim::{self, ClockMode};
+    use std::time::Duration;
+
+    const FULL_SCALE: f32 = 4.0 * GRAVITY;
+
+    fn imu(axial: f32) -> ImuData {
+        ImuData { accel: [0.5, -0.5, axial], accel_saturated: axial >= FULL_SCALE, ..ImuData::default() }
+    }
+
+    #[test]
+    fn crossfades_to_the_high_g_part_near_full_scale() {
+        // The high-g part reads 1 m/s^2 higher on every axis
+        let high_g = |axial: f32| HighGAccelData { accel: [1.5, 0.5, axial + 1.0], ..HighGAccelData::default() };
+
+        let axial = 0.5 * FULL_SCALE;
+        assert_eq!(blend_accel(&imu(axial), &high_g(axial), FULL_SCALE), ([0.5, -0.5, axial], AccelSource::LowG));
+
+        let axial = (BLEND_START + 0.25 * (BLEND_END - BLEND_START)) * FULL_SCALE;
+        let (accel, source) = blend_accel(&imu(axial), &high_g(axial), FULL_SCALE);
+        assert_eq!(source, AccelSource::Blended);
+        for (blended, imu) in accel.iter().zip(imu(axial).accel) {
+            assert!((blended - imu - 0.25).abs() < 1e-4, "{:?}", accel);
+        }
+
+        let axial = (BLEND_END + 0.01) * FULL_SCALE;
+        assert_eq!(blend_accel(&imu(axial), &high_g(axial), FULL_SCALE), (high_g(axial).accel, AccelSource::HighG));
+        // Pinned: the IMU reading says nothing about the true value
+        let (accel, source) = blend_accel(&imu(FULL_SCALE), &high_g(2.0 * FULL_SCALE), FULL_SCALE);
+        assert_eq!((accel, source), (high_g(2.0 * FULL_SCALE).accel, AccelSource::HighG));
+        // Either sign of the axis counts
+        assert_eq!(blend_accel(&imu(-axial), &high_g(-axial), FULL_SCALE).1, AccelSource::HighG);
+    }
+
+    #[test]
+    fn interpolates_the_high_g_part_to_each_sample_time() {
+        let at = |millis: u64, axial: f32, saturated: bool| HighGAccelData {
+            accel: [0.0, 2.0, axial],
+            saturated,
+            timestamp: Instant::from_micros(millis * 1000),
+        };
+        let previous = at(100, 100.0, false);
+        let latest = at(150, 200.0, false);
+
+        let mid = high_g_at(Some(&previous), &latest, Instant::from_micros(110_000));
+        assert!((mid.accel[2] - 120.0).abs() < 1e-3, "{:?}", mid);
+        assert_eq!(mid.accel[1], 2.0);
+        assert!(!mid.saturated);
+        assert_eq!(high_g_at(Some(&previous), &latest, latest.timestamp).accel, latest.accel);
+        // Nothing to interpolate from on the first update
+        assert_eq!(high_g_at(None, &latest, previous.timestamp).accel, latest.accel);
+        // Saturation at either end carries over
+        assert!(high_g_at(Some(&at(100, 100.0, true)), &latest, mid.timestamp).saturated);
+        assert!(high_g_at(Some(&previous), &at(150, 200.0, true), mid.timestamp).saturated);
+    }
+
+    #[test]
+    fn switches_to_the_high_g_part_when_the_imu_clips_in_boost() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_arm_switch(true);
+        let hal = dummy_hal::get_dummy_hal();
+        let i2c = hal.get_i2c_bus(0).unwrap();
+        let delay = hal.get_delay_timer();
+        // +/-2 g: clips early in the boost
+        let imu_config = ImuConfig { accel_range: AccelRange::G2, sample_rate_divider: 4, ..ImuConfig::default() };
+        let mut imu = Imu::new(i2c.clone(), hal.get_gpio_pin(config::DUMMY_IMU_INT_PIN).unwrap(), delay, config::DUMMY_IMU_ADDR, imu_config).unwrap();
+        imu.start_fifo().unwrap();
+        let high_g = HighGAccel::new(i2c.clone(), delay, config::DUMMY_HIGH_G_ADDR).unwrap();
+        let baro = Barometer::new(i2c, delay, config::DUMMY_BARO_ADDR).unwrap();
+        let state = SharedNavState::new();
+        let mut navigation = Navigation::new(
+            Arc::new(Mutex::new(imu)),
+            Arc::new(Mutex::new(high_g)),
+            Arc::new(Mutex::new(baro)),
+            state.clone(),
+            EstimatorConfig::default(),
+            AhrsConfig::default(),
+        );
+        let mut fly = |duration: Duration| {
+            let end = sim::now() + duration;
+            while sim::now() < end {
+                sim::sleep(config::NAV_LOOP_RATE);
+                navigation.update().unwrap();
+            }
+        };
+
+        fly(Duration::from_millis(500));
+        let on_pad = state.get().unwrap();
+        assert_eq!(on_pad.accel_source, AccelSource::LowG);
+        assert!(!on_pad.imu.accel_saturated);
+
+        for pin in [config::DUMMY_IGNITER_PIN, config::DUMMY_VALVE_PIN, config::DUMMY_VALVE_PIN + 1] {
+            hal.get_gpio_pin(pin).unwrap().set_high().unwrap();
+        }
+        fly(Duration::from_secs(1));
+        let boost = state.get().unwrap();
+        assert!(boost.imu.accel_saturated);
+        assert_eq!(boost.accel_source, AccelSource::HighG);
+        // The true acceleration, not the IMU's pinned reading
+        let truth = dummy_hal::vehicle_state().specific_force[2] as f32;
+        assert!(truth > 2.0 * AccelRange::G2.full_scale());
+        assert!((boost.accel[2] - truth).abs() < 2.0, "{} vs {} m/s^2", boost.accel[2], truth);
+        assert!(boost.vertical_accel > 3.0 * GRAVITY, "{} m/s^2", boost.vertical_accel);
+    }
+}
@@ -0,0 +1,343 @@
+// Recovery component: fires the drogue at apogee and the main at a set
+// altitude on the way down, each through its own pyro channel. Backup
+// timers fire them if the flight state or the altitude never gets there:
+// the drogue a fixed time after liftoff, the main a fixed time after the
+// drogue. Nothing fires before liftoff has been detected or after landing.
+use crate::hal::interface::{OutputPin, InputPin};
+use crate::drivers::pyro::PyroChannel;
+use crate::components::flight_state::{FlightPhase, FlightStateMachine};
+use crate::components::navigation::SharedNavState;
+use crate::kernel::{sim::Instant, sync::{self, ChannelSender, Mutex}};
+use crate::error::Result;
+use std::{sync::Arc, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parachute {
+    Drogue,
+    Main,
+}
+
+// What made a channel fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeployTrigger {
+    Apogee,      // Flight state reached apogee (drogue)
+    Altitude,    // Descended through the main deploy altitude (main)
+    BackupTimer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deployment {
+    pub parachute: Parachute,
+    pub trigger: DeployTrigger,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecoveryConfig {
+    pub main_deploy_altitude: f32, // m above the pad
+    pub drogue_backup: Duration,   // After liftoff
+    pub main_backup: Duration,     // After the drogue
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        RecoveryConfig {
+            main_deploy_altitude: 150.0,
+            drogue_backup: Duration::from_secs(25), // Nominal apogee ~16 s after liftoff
+            // Nominal main deploy ~46 s after the drogue; the drogue alone
+            // would land ~53 s after apogee
+            main_backup: Duration::from_secs(50),
+        }
+    }
+}
+
+pub struct Recovery<P: OutputPin, S: InputPin> {
+    drogue: PyroChannel<P, S>,
+    main: PyroChannel<P, S>,
+    nav_state: SharedNavState,
+    flight_state: Arc<Mutex<FlightStateMachine>>,
+    config: RecoveryConfig,
+    deployments: ChannelSender<Deployment>, // To telemetry
+    liftoff: Option<Instant>,
+    drogue_fired: Option<Instant>,
+}
+
+impl<P: OutputPin, S: InputPin> Recovery<P, S> {
+    pub fn new(
+        drogue: PyroChannel<P, S>,
+        main: PyroChannel<P, S>,
+        nav_state: SharedNavState,
+        flight_state: Arc<Mutex<FlightStateMachine>>,
+        config: RecoveryConfig,
+        deployments: ChannelSender<Deployment>,
+    ) -> Result<Self> {
+        // Report continuity for the pad checks; a channel without it still fires
+        for channel in [&drogue, &main] {
+            let continuity = channel.continuity()?;
+            println!(
+                "[Component:Recovery] {} continuity {}.",
+                channel.name(),
+                if continuity { "OK" } else { "OPEN" }
+            );
+        }
+        println!("[Component:Recovery] Created, main at {:.0} m.", config.main_deploy_altitude);
+        Ok(Self {
+            drogue,
+            main,
+            nav_state,
+            flight_state,
+            config,
+            deployments,
+            liftoff: None,
+            drogue_fired: None,
+        })
+    }
+
+    pub fn continuity(&self, parachute: Parachute) -> Result<bool> {
+        self.channel(parachute).continuity()
+    }
+
+    pub fn fired(&self, parachute: Parachute) -> bool {
+        self.channel(parachute).fired()
+    }
+
+    // Called every recovery loop
+    pub fn update(&mut self) -> Result<()> {
+        // Pulses end on time whatever else happens this cycle. An output that
+        // won't switch off is reported, not returned: it mustn't hold up the
+        // other channel.
+        for channel in [&mut self.drogue, &mut self.main] {
+            if let Err(e) = channel.update() {
+                println!("[Component:Recovery] Failed to end the {} pulse: {:?}", channel.name(), e);
+            }
+        }
+
+        let phase = self.flight_state.lock()?.phase();
+        if matches!(phase, FlightPhase::Pad | FlightPhase::Landed) {
+            return Ok(());
+        }
+        let now = sync::get_time();
+        let liftoff = *self.liftoff.get_or_insert(now);
+        let descending = matches!(phase, FlightPhase::Apogee | FlightPhase::Descent);
+
+        match self.drogue_fired {
+            None => {
+                if descending {
+                    self.deploy(Parachute::Drogue, DeployTrigger::Apogee)?;
+                } else if now.duration_since(liftoff) >= self.config.drogue_backup {
+                    self.deploy(Parachute::Drogue, DeployTrigger::BackupTimer)?;
+                }
+            }
+            Some(drogue_fired) if !self.main.fired() => {
+                let altitude = self.nav_state.get()?.altitude;
+                if descending && altitude <= self.config.main_deploy_altitude {
+                    self.deploy(Parachute::Main, DeployTrigger::Altitude)?;
+                } else if now.duration_since(drogue_fired) >= self.config.main_backup {
+                    self.deploy(Parachute::Main, DeployTrigger::BackupTimer)?;
+                }
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    // Let any pulse in progress run its full length; part of the shutdown
+    // sequence, after the recovery task has stopped
+    pub fn finish_pulses(&mut self) -> Result<()> {
+        let drogue = self.drogue.finish_pulse();
+        let main = self.main.finish_pulse();
+        drogue.and(main)
+    }
+
+    fn deploy(&mut self, parachute: Parachute, trigger: DeployTrigger) -> Result<()> {
+        let altitude = self.nav_state.get()?.altitude;
+        println!("[Component:Recovery] Deploying {:?} ({:?}) at {:.0} m.", parachute, trigger, altitude);
+        self.channel_mut(parachute).fire()?;
+        if parachute == Parachute::Drogue {
+            self.drogue_fired = Some(sync::get_time());
+        }
+        self.deployments.send(Deployment { parachute, trigger })?;
+        Ok(())
+    }
+
+    fn channel(&self, parachute: Parachute) -> &PyroChannel<P, S> {
+        match parachute {
+            Parachute::Drogue => &self.drogue,
+            Parachute::Main => &self.main,
+        }
+    }
+
+    fn channel_mut(&mut self, parachute: Parachute) -> &mut PyroChannel<P, S> {
+        match parachute {
+            Parachute::Drogue => &mut self.drogue,
+            Parachute::Main => &mut self.main,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::flight_state::FlightCriteria;
+    use crate::config;
+    use crate::hal::dummy_hal::{self, DummyPin};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+    use crate::kernel::sync::{Channel, ChannelReceiver, OverflowPolicy};
+
+    const GRAVITY: f32 = 9.81;
+
+    struct Rig {
+        recovery: Recovery<DummyPin, DummyPin>,
+        nav: SharedNavState,
+        flight_state: Arc<Mutex<FlightStateMachine>>,
+        deployments: ChannelReceiver<Deployment>,
+    }
+
+    impl Rig {
+        fn new(config: RecoveryConfig) -> Self {
+            sim::init(ClockMode::Simulated, 1);
+            dummy_hal::reset();
+            let hal = dummy_hal::get_dummy_hal();
+            let pin = |id| hal.get_gpio_pin(id).unwrap();
+            let channel = |name, fire, continuity| PyroChannel::new(name, pin(fire), pin(continuity), config::PYRO_FIRE_PULSE).unwrap();
+            let nav = SharedNavState::new();
+            let flight_state = Arc::new(Mutex::new(FlightStateMachine::new(nav.clone(), FlightCriteria::default())));
+            let (deployment_tx, deployments) = Channel::new(4, OverflowPolicy::Reject).split();
+            let recovery = Recovery::new(
+                channel("Drogue", config::DUMMY_DROGUE_PYRO_PIN, config::DUMMY_DROGUE_CONTINUITY_PIN),
+                channel("Main", config::DUMMY_MAIN_PYRO_PIN, config::DUMMY_MAIN_CONTINUITY_PIN),
+                nav.clone(),
+                flight_state.clone(),
+                config,
+                deployment_tx,
+            )
+            .unwrap();
+            Rig { recovery, nav, flight_state, deployments }
+        }
+
+        // Recovery loops for `duration` with the given axial specific force,
+        // vertical velocity and altitude, the flight state updated each time
+        fn fly(&mut self, duration: Duration, axial: f32, vertical_velocity: f32, altitude: f32) -> FlightPhase {
+            let end = sync::get_time() + duration;
+            while sync::get_time() < end {
+                sim::sleep(config::RECOVERY_LOOP_RATE);
+                self.nav
+                    .update(|nav| {
+                        nav.accel = [0.0, 0.0, axial];
+                        nav.vertical_velocity = vertical_velocity;
+                        nav.altitude = altitude;
+                        nav.last_update = Some(sync::get_time());
+                    })
+                    .unwrap();
+                self.flight_state.lock().unwrap().update().unwrap();
+                self.recovery.update().unwrap();
+            }
+            self.flight_state.lock().unwrap().phase()
+        }
+
+        // Off the pad and through burnout, still climbing
+        fn launch(&mut self) {
+            assert_eq!(self.fly(Duration::from_secs(1), 3.0 * GRAVITY, 50.0, 100.0), FlightPhase::Boost);
+            assert_eq!(self.fly(Duration::from_millis(500), 0.0, 100.0, 300.0), FlightPhase::Coast);
+        }
+
+        fn deployment(&mut self) -> Option<Deployment> {
+            self.deployments.try_recv().unwrap()
+        }
+    }
+
+    fn deployed(parachute: Parachute, trigger: DeployTrigger) -> Option<Deployment> {
+        Some(Deployment { parachute, trigger })
+    }
+
+    #[test]
+    fn drogue_at_apogee_and_main_at_its_altitude() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(RecoveryConfig::default());
+        rig.launch();
+        assert!(!rig.recovery.fired(Parachute::Drogue));
+
+        assert_eq!(rig.fly(Duration::from_millis(200), 0.0, -1.0, 1000.0), FlightPhase::Apogee);
+        assert_eq!(rig.deployment(), deployed(Parachute::Drogue, DeployTrigger::Apogee));
+        assert!(rig.recovery.fired(Parachute::Drogue));
+
+        let main_altitude = RecoveryConfig::default().main_deploy_altitude;
+        rig.fly(Duration::from_secs(2), GRAVITY, -20.0, main_altitude + 1.0);
+        assert_eq!(rig.deployment(), None);
+        rig.fly(config::RECOVERY_LOOP_RATE, GRAVITY, -20.0, main_altitude);
+        assert_eq!(rig.deployment(), deployed(Parachute::Main, DeployTrigger::Altitude));
+        assert!(rig.recovery.fired(Parachute::Main));
+        // Both e-matches burnt through by the end of their pulses
+        rig.fly(config::PYRO_FIRE_PULSE, GRAVITY, -20.0, main_altitude - 10.0);
+        assert!(!rig.recovery.continuity(Parachute::Drogue).unwrap());
+        assert!(!rig.recovery.continuity(Parachute::Main).unwrap());
+    }
+
+    #[test]
+    fn backup_timers_fire_both_without_apogee() {
+        let _exclusive = sim::exclusive();
+        let config = RecoveryConfig {
+            drogue_backup: Duration::from_secs(3),
+            main_backup: Duration::from_secs(2),
+            ..RecoveryConfig::default()
+        };
+        let mut rig = Rig::new(config);
+        rig.launch();
+        let liftoff = rig.flight_state.lock().unwrap().history()[0].time;
+        // Apogee never detected, and the altitude says nothing
+        while rig.deployment().is_none() {
+            assert_eq!(rig.fly(config::RECOVERY_LOOP_RATE, 0.0, 100.0, 500.0), FlightPhase::Coast);
+        }
+        let drogue_at = sync::get_time();
+        assert!(drogue_at.duration_since(liftoff) >= config.drogue_backup);
+        assert!(drogue_at.duration_since(liftoff) <= config.drogue_backup + Duration::from_millis(100));
+        assert!(rig.recovery.fired(Parachute::Drogue) && !rig.recovery.fired(Parachute::Main));
+
+        rig.fly(config.main_backup - config::RECOVERY_LOOP_RATE, 0.0, 100.0, 500.0);
+        assert_eq!(rig.deployment(), None);
+        rig.fly(config::RECOVERY_LOOP_RATE, 0.0, 100.0, 500.0);
+        assert_eq!(rig.deployment(), deployed(Parachute::Main, DeployTrigger::BackupTimer));
+    }
+
+    #[test]
+    fn nothing_fires_on_the_pad_or_after_landing() {
+        let _exclusive = sim::exclusive();
+        let config = RecoveryConfig {
+            main_deploy_altitude: -1000.0, // Only the backup timer fires the main
+            drogue_backup: Duration::from_secs(3),
+            main_backup: Duration::from_secs(10),
+        };
+        let mut rig = Rig::new(config);
+        // Well past the drogue backup, on the pad
+        assert_eq!(rig.fly(Duration::from_secs(5), GRAVITY, 0.0, 0.0), FlightPhase::Pad);
+        assert_eq!(rig.deployment(), None);
+
+        rig.launch();
+        rig.fly(Duration::from_secs(2), GRAVITY, -20.0, 500.0);
+        assert_eq!(rig.deployment(), deployed(Parachute::Drogue, DeployTrigger::Apogee));
+        assert_eq!(rig.fly(Duration::from_secs(3), GRAVITY, 0.0, 0.0), FlightPhase::Landed);
+        // Past the main backup, landed
+        rig.fly(Duration::from_secs(15), GRAVITY, 0.0, 0.0);
+        assert_eq!(rig.deployment(), None);
+        assert!(!rig.recovery.fired(Parachute::Main));
+    }
+
+    #[test]
+    fn shutdown_lets_the_pulse_run_its_full_length() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new(RecoveryConfig::default());
+        rig.launch();
+        while !rig.recovery.fired(Parachute::Drogue) {
+            rig.fly(config::RECOVERY_LOOP_RATE, 0.0, -1.0, 1000.0);
+        }
+        let drogue = dummy_hal::get_dummy_hal().get_gpio_pin(config::DUMMY_DROGUE_PYRO_PIN).unwrap();
+        assert!(drogue.is_high().unwrap());
+
+        // Fired within the last loop; shutdown comes straight after
+        let fired_by = sync::get_time();
+        rig.recovery.finish_pulses().unwrap();
+        assert!(!drogue.is_high().unwrap());
+        let waited = sync::get_time().duration_since(fired_by);
+        assert!(waited >= config::PYRO_FIRE_PULSE - config::RECOVERY_LOOP_RATE && waited <= config::PYRO_FIRE_PULSE);
+    }
+}
@@ -0,0 +1,187 @@
+// Telemetry component: sends one frame (see telemetry_frame) through the
+// radio per cycle. Command responses and events (phase and engine state
+// changes, engine aborts, parachute deployments) go out ahead of the regular
+// rotation of nav, engine and health messages.
+use crate::hal::interface::{SpiBus, OutputPin, InputPin, DelayMs};
+use crate::drivers::radio::Radio;
+use crate::components::navigation::SharedNavState;
+use crate::components::engine_control::{EngineControl, EngineState};
+use crate::components::flight_state::{FlightStateMachine, PhaseTransition};
+use crate::components::recovery::Deployment;
+use crate::components::telemetry_frame::{CommandResponse, EventMessage, EngineMessage, Frame, HealthMessage, Message, NavMessage};
+use crate::kernel::sync::{self, ChannelReceiver, Mutex};
+use crate::kernel::task;
+use crate::config;
+use crate::error::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TX_POLL_INTERVAL: Duration = Duration::from_millis(1); // Checking for TxDone
+
+pub struct Telemetry<SPI, CS, IRQ, RDELAY, P, S>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    RDELAY: DelayMs,
+    P: OutputPin,
+    S: InputPin,
+{
+    radio: Arc<Mutex<Radio<SPI, CS, IRQ, RDELAY>>>,
+    nav_state: SharedNavState,
+    flight_state: Arc<Mutex<FlightStateMachine>>,
+    phase_changes: ChannelReceiver<PhaseTransition>,
+    engine_control: Arc<Mutex<EngineControl<P, S>>>,
+    command_responses: ChannelReceiver<CommandResponse>,
+    deployments: ChannelReceiver<Deployment>,
+    last_engine_state: EngineState,
+    abort_reported: bool,
+    urgent: VecDeque<Message>, // Sent before the rotation, oldest first
+    cycle: u32,
+    sequence: u16,
+}
+
+impl<SPI, CS, IRQ, RDELAY, P, S> Telemetry<SPI, CS, IRQ, RDELAY, P, S>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    RDELAY: DelayMs,
+    P: OutputPin,
+    S: InputPin,
+{
+    pub fn new(
+        radio: Arc<Mutex<Radio<SPI, CS, IRQ, RDELAY>>>,
+        nav_state: SharedNavState,
+        flight_state: Arc<Mutex<FlightStateMachine>>,
+        engine_control: Arc<Mutex<EngineControl<P, S>>>,
+        command_responses: ChannelReceiver<CommandResponse>,
+        deployments: ChannelReceiver<Deployment>,
+    ) -> Result<Self> {
+        let phase_changes = flight_state.lock()?.subscribe();
+        let last_engine_state = engine_control.lock()?.state();
+        println!("[Component:Telemetry] Created.");
+        Ok(Self {
+            radio,
+            nav_state,
+            flight_state,
+            phase_changes,
+            engine_control,
+            command_responses,
+            deployments,
+            last_engine_state,
+            abort_reported: false,
+            urgent: VecDeque::new(),
+            cycle: 0,
+            sequence: 0,
+        })
+    }
+
+    // Pick this cycle's message and send it
+    pub fn run_cycle(&mut self) -> Result<()> {
+        self.collect_urgent()?;
+        let message = match self.urgent.pop_front() {
+            Some(message) => message,
+            None if (self.cycle + 1).is_multiple_of(config::TELEMETRY_HEALTH_INTERVAL) => Message::Health(self.health()?),
+            None if (self.cycle + 1).is_multiple_of(config::TELEMETRY_ENGINE_INTERVAL) => Message::Engine(self.engine()?),
+            None => Message::Nav(self.nav()?),
+        };
+        self.cycle = self.cycle.wrapping_add(1);
+        self.send(message)
+    }
+
+    // Send the engine state right away, e.g. the safed state at shutdown
+    pub fn send_engine_state(&mut self) -> Result<()> {
+        let engine = self.engine()?;
+        self.last_engine_state = engine.state; // Supersedes a change event
+        self.send(Message::Engine(engine))
+    }
+
+    fn send(&mut self, message: Message) -> Result<()> {
+        let frame = Frame {
+            sequence: self.sequence,
+            timestamp_ms: (sync::get_time().as_micros() / 1000) as u32,
+            message,
+        };
+        self.radio.lock()?.start_transmit(&frame.encode())?;
+        // Wait out the time on air without holding the radio, so the uplink
+        // can keep polling it
+        while !self.radio.lock()?.transmit_done()? {
+            sync::sleep(TX_POLL_INTERVAL);
+        }
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+
+    fn collect_urgent(&mut self) -> Result<()> {
+        while let Some(response) = self.command_responses.try_recv()? {
+            self.urgent.push_back(Message::CommandResponse(response));
+        }
+        while let Some(transition) = self.phase_changes.try_recv()? {
+            self.urgent.push_back(Message::Event(EventMessage::PhaseChange { from: transition.from, to: transition.to }));
+        }
+        let (engine_state, abort_reason) = {
+            let engine = self.engine_control.lock()?;
+            (engine.state(), engine.abort_reason())
+        };
+        if engine_state != self.last_engine_state {
+            let event = EventMessage::EngineStateChange { from: self.last_engine_state, to: engine_state };
+            self.urgent.push_back(Message::Event(event));
+            self.last_engine_state = engine_state;
+        }
+        while let Some(deployment) = self.deployments.try_recv()? {
+            let event = EventMessage::Deployment { parachute: deployment.parachute, trigger: deployment.trigger };
+            self.urgent.push_back(Message::Event(event));
+        }
+        // Reported once, even if a status request already showed Aborted
+        if let (Some(reason), false) = (abort_reason, self.abort_reported) {
+            self.urgent.push_back(Message::Event(EventMessage::EngineAborted(reason)));
+            self.abort_reported = true;
+        }
+        Ok(())
+    }
+
+    fn nav(&self) -> Result<NavMessage> {
+        let nav = self.nav_state.get()?;
+        Ok(NavMessage {
+            altitude: nav.altitude,
+            vertical_velocity: nav.vertical_velocity,
+            vertical_accel: nav.vertical_accel,
+            accel: nav.accel,
+            gyro: nav.angular_rate,
+            tilt: nav.tilt,
+            sample_count: nav.sample_count,
+            phase: self.flight_state.lock()?.phase(),
+            accel_source: nav.accel_source,
+        })
+    }
+
+    fn engine(&self) -> Result<EngineMessage> {
+        let engine = self.engine_control.lock()?;
+        let pressures = engine.pressures().unwrap_or_default();
+        Ok(EngineMessage {
+            state: engine.state(),
+            fuel_valve_open: engine.fuel_valve_open(),
+            oxidizer_valve_open: engine.oxidizer_valve_open(),
+            armed: engine.armed(),
+            test_mode: engine.test_mode(),
+            igniter_lit: engine.igniter_lit(),
+            fuel_tank_pressure: pressures.fuel_tank,
+            oxidizer_tank_pressure: pressures.oxidizer_tank,
+            chamber_pressure: pressures.chamber,
+        })
+    }
+
+    fn health(&self) -> Result<HealthMessage> {
+        let nav = self.nav_state.get()?;
+        let tasks = task::statistics();
+        Ok(HealthMessage {
+            imu_temp: nav.imu.temp,
+            imu_overflows: nav.imu_overflows,
+            baro_rejections: nav.baro_rejections,
+            deadline_misses: tasks.iter().map(|t| t.deadline_misses as u32).sum(),
+            task_errors: tasks.iter().map(|t| t.errors as u32).sum(),
+        })
+    }
+}
@@ -0,0 +1,609 @@
+// Telemetry frame format shared by the flight software and the ground
+// station. One frame per radio packet, little endian:
+//
+//   sync: 2 bytes (0xA5 0x7E) | version: u8 | message type: u8 | seq: u16 |
+//   timestamp: u32 (ms of vehicle time) | payload length: u8 | payload |
+//   CRC-16/CCITT-FALSE: u16 over everything from the version byte on
+//
+// The CRC catches corruption the radio's own CRC lets through (or packets
+// from a radio with CRC disabled). A decoder only accepts frames of the
+// version it was built for; bump FRAME_VERSION whenever a payload changes.
+use crate::components::engine_control::{AbortReason, EngineState};
+use crate::components::flight_state::FlightPhase;
+use crate::components::recovery::{DeployTrigger, Parachute};
+use crate::components::navigation::AccelSource;
+use crate::components::uplink_frame::CommandResult;
+use std::fmt;
+
+pub const FRAME_SYNC: [u8; 2] = [0xA5, 0x7E];
+pub const FRAME_VERSION: u8 = 7; // 7: throttle fault abort reason
+pub const FRAME_HEADER_LEN: usize = 2 + 1 + 1 + 2 + 4 + 1;
+pub const FRAME_OVERHEAD: usize = FRAME_HEADER_LEN + 2; // Header + CRC
+
+// Message type codes
+const MSG_NAV: u8 = 1;
+const MSG_ENGINE: u8 = 2;
+const MSG_HEALTH: u8 = 3;
+const MSG_EVENT: u8 = 4;
+const MSG_COMMAND_RESPONSE: u8 = 5;
+
+// Event codes
+const EVENT_PHASE_CHANGE: u8 = 1;
+const EVENT_ENGINE_STATE_CHANGE: u8 = 2;
+const EVENT_ENGINE_ABORTED: u8 = 3;
+const EVENT_DEPLOYMENT: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    Truncated,               // Shorter than its header and CRC, or than its declared payload
+    BadSync,
+    UnsupportedVersion(u8),
+    BadCrc,
+    UnknownMessageType(u8),
+    InvalidPayload,          // Wrong length for its type, or an unknown code inside
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Truncated => write!(f, "truncated frame"),
+            FrameError::BadSync => write!(f, "bad sync word"),
+            FrameError::UnsupportedVersion(v) => write!(f, "unsupported frame version {}", v),
+            FrameError::BadCrc => write!(f, "CRC mismatch"),
+            FrameError::UnknownMessageType(t) => write!(f, "unknown message type {}", t),
+            FrameError::InvalidPayload => write!(f, "invalid payload"),
+        }
+    }
+}
+
+// Navigation solution and the body-frame measurements behind it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NavMessage {
+    pub altitude: f32,          // m above the pad
+    pub vertical_velocity: f32, // m/s, positive up
+    pub vertical_accel: f32,    // m/s^2, positive up, gravity removed
+    pub accel: [f32; 3],        // m/s^2, from the accelerometer(s) in `accel_source`
+    pub gyro: [f32; 3],         // rad/s, bias removed
+    pub tilt: f32,              // rad from vertical
+    pub sample_count: u32,
+    pub phase: FlightPhase,
+    pub accel_source: AccelSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EngineMessage {
+    pub state: EngineState,
+    pub fuel_valve_open: bool,
+    pub oxidizer_valve_open: bool,
+    pub armed: bool,
+    pub test_mode: bool,
+    pub igniter_lit: bool,
+    pub fuel_tank_pressure: f32,     // Pa gauge, 0 when not measured
+    pub oxidizer_tank_pressure: f32, // Pa gauge
+    pub chamber_pressure: f32,       // Pa gauge
+}
+
+// Slow-changing housekeeping: sensor and scheduler health
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthMessage {
+    pub imu_temp: f32,        // degrees C
+    pub imu_overflows: u32,
+    pub baro_rejections: u32,
+    pub deadline_misses: u32, // Summed over all periodic tasks
+    pub task_errors: u32,     // Summed over all periodic tasks
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventMessage {
+    PhaseChange { from: FlightPhase, to: FlightPhase },
+    EngineStateChange { from: EngineState, to: EngineState },
+    EngineAborted(AbortReason),
+    Deployment { parachute: Parachute, trigger: DeployTrigger },
+}
+
+// ACK/NACK for an uplink command (see uplink_frame)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandResponse {
+    pub sequence: u32, // Of the command being answered
+    pub result: CommandResult,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    Nav(NavMessage),
+    Engine(EngineMessage),
+    Health(HealthMessage),
+    Event(EventMessage),
+    CommandResponse(CommandResponse),
+}
+
+impl Message {
+    pub fn message_type(&self) -> u8 {
+        match self {
+            Message::Nav(_) => MSG_NAV,
+            Message::Engine(_) => MSG_ENGINE,
+            Message::Health(_) => MSG_HEALTH,
+            Message::Event(_) => MSG_EVENT,
+            Message::CommandResponse(_) => MSG_COMMAND_RESPONSE,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::Nav(_) => "nav",
+            Message::Engine(_) => "engine",
+            Message::Health(_) => "health",
+            Message::Event(_) => "event",
+            Message::CommandResponse(_) => "command response",
+        }
+    }
+
+    fn encode_payload(&self, out: &mut Vec<u8>) {
+        match self {
+            Message::Nav(m) => {
+                for v in [m.altitude, m.vertical_velocity, m.vertical_accel] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                for v in m.accel.iter().chain(m.gyro.iter()) {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                out.extend_from_slice(&m.tilt.to_le_bytes());
+                out.extend_from_slice(&m.sample_count.to_le_bytes());
+                out.push(phase_code(m.phase));
+                out.push(accel_source_code(m.accel_source));
+            }
+            Message::Engine(m) => {
+                out.push(engine_state_code(m.state));
+                out.push(
+                    (m.fuel_valve_open as u8)
+                        | ((m.oxidizer_valve_open as u8) << 1)
+                        | ((m.armed as u8) << 2)
+                        | ((m.test_mode as u8) << 3)
+                        | ((m.igniter_lit as u8) << 4),
+                );
+                for v in [m.fuel_tank_pressure, m.oxidizer_tank_pressure, m.chamber_pressure] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+            Message::Health(m) => {
+                out.extend_from_slice(&m.imu_temp.to_le_bytes());
+                for v in [m.imu_overflows, m.baro_rejections, m.deadline_misses, m.task_errors] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+            Message::Event(EventMessage::PhaseChange { from, to }) => {
+                out.extend_from_slice(&[EVENT_PHASE_CHANGE, phase_code(*from), phase_code(*to)]);
+            }
+            Message::Event(EventMessage::EngineStateChange { from, to }) => {
+                out.extend_from_slice(&[EVENT_ENGINE_STATE_CHANGE, engine_state_code(*from), engine_state_code(*to)]);
+            }
+            Message::Event(EventMessage::EngineAborted(reason)) => {
+                out.extend_from_slice(&[EVENT_ENGINE_ABORTED, abort_reason_code(*reason), 0]); // Padded to the event length
+            }
+            Message::Event(EventMessage::Deployment { parachute, trigger }) => {
+                out.extend_from_slice(&[EVENT_DEPLOYMENT, parachute_code(*parachute), deploy_trigger_code(*trigger)]);
+            }
+            Message::CommandResponse(m) => {
+                out.extend_from_slice(&m.sequence.to_le_bytes());
+                out.push(m.result.code());
+            }
+        }
+    }
+
+    fn decode_payload(message_type: u8, p: &[u8]) -> Result<Self, FrameError> {
+        let f32_at = |i: usize| f32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]);
+        let u32_at = |i: usize| u32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]);
+        let expect_len = |len: usize| if p.len() == len { Ok(()) } else { Err(FrameError::InvalidPayload) };
+        let invalid = || FrameError::InvalidPayload;
+        match message_type {
+            MSG_NAV => {
+                expect_len(46)?;
+                Ok(Message::Nav(NavMessage {
+                    altitude: f32_at(0),
+                    vertical_velocity: f32_at(4),
+                    vertical_accel: f32_at(8),
+                    accel: [f32_at(12), f32_at(16), f32_at(20)],
+                    gyro: [f32_at(24), f32_at(28), f32_at(32)],
+                    tilt: f32_at(36),
+                    sample_count: u32_at(40),
+                    phase: phase_from_code(p[44]).ok_or_else(invalid)?,
+                    accel_source: accel_source_from_code(p[45]).ok_or_else(invalid)?,
+                }))
+            }
+            MSG_ENGINE => {
+                expect_len(14)?;
+                if p[1] & !0x1F != 0 {
+                    return Err(FrameError::InvalidPayload);
+                }
+                Ok(Message::Engine(EngineMessage {
+                    state: engine_state_from_code(p[0]).ok_or_else(invalid)?,
+                    fuel_valve_open: p[1] & 0x01 != 0,
+                    oxidizer_valve_open: p[1] & 0x02 != 0,
+                    armed: p[1] & 0x04 != 0,
+                    test_mode: p[1] & 0x08 != 0,
+                    igniter_lit: p[1] & 0x10 != 0,
+                    fuel_tank_pressure: f32_at(2),
+                    oxidizer_tank_pressure: f32_at(6),
+                    chamber_pressure: f32_at(10),
+                }))
+            }
+            MSG_HEALTH => {
+                expect_len(20)?;
+                Ok(Message::Health(HealthMessage {
+                    imu_temp: f32_at(0),
+                    imu_overflows: u32_at(4),
+                    baro_rejections: u32_at(8),
+                    deadline_misses: u32_at(12),
+                    task_errors: u32_at(16),
+                }))
+            }
+            MSG_EVENT => {
+                expect_len(3)?;
+                let event = match p[0] {
+                    EVENT_PHASE_CHANGE => EventMessage::PhaseChange {
+                        from: phase_from_code(p[1]).ok_or_else(invalid)?,
+                        to: phase_from_code(p[2]).ok_or_else(invalid)?,
+                    },
+                    EVENT_ENGINE_STATE_CHANGE => EventMessage::EngineStateChange {
+                        from: engine_state_from_code(p[1]).ok_or_else(invalid)?,
+                        to: engine_state_from_code(p[2]).ok_or_else(invalid)?,
+                    },
+                    EVENT_ENGINE_ABORTED if p[2] == 0 => {
+                        EventMessage::EngineAborted(abort_reason_from_code(p[1]).ok_or_else(invalid)?)
+                    }
+                    EVENT_DEPLOYMENT => EventMessage::Deployment {
+                        parachute: parachute_from_code(p[1]).ok_or_else(invalid)?,
+                        trigger: deploy_trigger_from_code(p[2]).ok_or_else(invalid)?,
+                    },
+                    _ => return Err(FrameError::InvalidPayload),
+                };
+                Ok(Message::Event(event))
+            }
+            MSG_COMMAND_RESPONSE => {
+                expect_len(5)?;
+                Ok(Message::CommandResponse(CommandResponse {
+                    sequence: u32_at(0),
+                    result: CommandResult::from_code(p[4]).ok_or_else(invalid)?,
+                }))
+            }
+            other => Err(FrameError::UnknownMessageType(other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub sequence: u16,     // Per sender, wraps; gaps mean lost frames
+    pub timestamp_ms: u32, // Vehicle time the frame was built
+    pub message: Message,
+}
+
+impl Frame {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.message.encode_payload(&mut payload);
+        let mut bytes = Vec::with_capacity(FRAME_OVERHEAD + payload.len());
+        bytes.extend_from_slice(&FRAME_SYNC);
+        bytes.push(FRAME_VERSION);
+        bytes.push(self.message.message_type());
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.extend_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes.push(payload.len() as u8); // Payloads are all far below 255 bytes
+        bytes.extend_from_slice(&payload);
+        let crc = crc16(&bytes[FRAME_SYNC.len()..]);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    // Checks run outermost first, so a corrupted frame reports the CRC
+    // rather than whatever garbage it decodes to.
+    pub fn decode(bytes: &[u8]) -> Result<Self, FrameError> {
+        if bytes.len() < FRAME_OVERHEAD {
+            return Err(FrameError::Truncated);
+        }
+        if bytes[..2] != FRAME_SYNC {
+            return Err(FrameError::BadSync);
+        }
+        let payload_len = bytes[10] as usize;
+        let total = FRAME_OVERHEAD + payload_len;
+        if bytes.len() < total {
+            return Err(FrameError::Truncated);
+        }
+        let crc = u16::from_le_bytes([bytes[total - 2], bytes[total - 1]]);
+        if crc16(&bytes[FRAME_SYNC.len()..total - 2]) != crc {
+            return Err(FrameError::BadCrc);
+        }
+        if bytes[2] != FRAME_VERSION {
+            return Err(FrameError::UnsupportedVersion(bytes[2]));
+        }
+        if bytes.len() != total {
+            return Err(FrameError::InvalidPayload); // Trailing bytes after the CRC
+        }
+        Ok(Frame {
+            sequence: u16::from_le_bytes([bytes[4], bytes[5]]),
+            timestamp_ms: u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+            message: Message::decode_payload(bytes[3], &bytes[FRAME_HEADER_LEN..total - 2])?,
+        })
+    }
+}
+
+// CRC-16/CCITT-FALSE: poly 0x1021, init 0xFFFF, no reflection, no final XOR
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &b in bytes {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn phase_code(phase: FlightPhase) -> u8 {
+    match phase {
+        FlightPhase::Pad => 0,
+        FlightPhase::Boost => 1,
+        FlightPhase::Coast => 2,
+        FlightPhase::Apogee => 3,
+        FlightPhase::Descent => 4,
+        FlightPhase::Landed => 5,
+    }
+}
+
+fn phase_from_code(code: u8) -> Option<FlightPhase> {
+    match code {
+        0 => Some(FlightPhase::Pad),
+        1 => Some(FlightPhase::Boost),
+        2 => Some(FlightPhase::Coast),
+        3 => Some(FlightPhase::Apogee),
+        4 => Some(FlightPhase::Descent),
+        5 => Some(FlightPhase::Landed),
+        _ => None,
+    }
+}
+
+fn engine_state_code(state: EngineState) -> u8 {
+    match state {
+        EngineState::Idle => 0,
+        EngineState::OxidizerLead => 1,
+        EngineState::Running => 2,
+        EngineState::Shutdown => 3,
+        EngineState::Aborted => 4,
+        EngineState::Igniting => 5,
+        EngineState::Starting => 6,
+    }
+}
+
+fn engine_state_from_code(code: u8) -> Option<EngineState> {
+    match code {
+        0 => Some(EngineState::Idle),
+        1 => Some(EngineState::OxidizerLead),
+        2 => Some(EngineState::Running),
+        3 => Some(EngineState::Shutdown),
+        4 => Some(EngineState::Aborted),
+        5 => Some(EngineState::Igniting),
+        6 => Some(EngineState::Starting),
+        _ => None,
+    }
+}
+
+fn abort_reason_code(reason: AbortReason) -> u8 {
+    match reason {
+        AbortReason::Commanded => 0,
+        AbortReason::NoChamberPressure => 1,
+        AbortReason::NoAcceleration => 2,
+        AbortReason::IgnitionTimeout => 3,
+        AbortReason::Interlock => 4,
+        AbortReason::LowFeedPressure => 5,
+        AbortReason::ThrottleFault => 6,
+    }
+}
+
+fn abort_reason_from_code(code: u8) -> Option<AbortReason> {
+    match code {
+        0 => Some(AbortReason::Commanded),
+        1 => Some(AbortReason::NoChamberPressure),
+        2 => Some(AbortReason::NoAcceleration),
+        3 => Some(AbortReason::IgnitionTimeout),
+        4 => Some(AbortReason::Interlock),
+        5 => Some(AbortReason::LowFeedPressure),
+        6 => Some(AbortReason::ThrottleFault),
+        _ => None,
+    }
+}
+
+fn parachute_code(parachute: Parachute) -> u8 {
+    match parachute {
+        Parachute::Drogue => 0,
+        Parachute::Main => 1,
+    }
+}
+
+fn parachute_from_code(code: u8) -> Option<Parachute> {
+    match code {
+        0 => Some(Parachute::Drogue),
+        1 => Some(Parachute::Main),
+        _ => None,
+    }
+}
+
+fn deploy_trigger_code(trigger: DeployTrigger) -> u8 {
+    match trigger {
+        DeployTrigger::Apogee => 0,
+        DeployTrigger::Altitude => 1,
+        DeployTrigger::BackupTimer => 2,
+    }
+}
+
+fn deploy_trigger_from_code(code: u8) -> Option<DeployTrigger> {
+    match code {
+        0 => Some(DeployTrigger::Apogee),
+        1 => Some(DeployTrigger::Altitude),
+        2 => Some(DeployTrigger::BackupTimer),
+        _ => None,
+    }
+}
+
+fn accel_source_code(source: AccelSource) -> u8 {
+    match source {
+        AccelSource::LowG => 0,
+        AccelSource::HighG => 1,
+        AccelSource::Blended => 2,
+    }
+}
+
+fn accel_source_from_code(code: u8) -> Option<AccelSource> {
+    match code {
+        0 => Some(AccelSource::LowG),
+        1 => Some(AccelSource::HighG),
+        2 => Some(AccelSource::Blended),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::uplink_frame::NackReason;
+
+    fn sample_messages() -> Vec<Message> {
+        vec![
+            Message::Nav(NavMessage {
+                altitude: 812.5,
+                vertical_velocity: -3.25,
+                vertical_accel: -9.5,
+                accel: [0.1, -0.2, 31.0],
+                gyro: [0.01, -0.02, 1.5],
+                tilt: 0.05,
+                sample_count: 123_456,
+                phase: FlightPhase::Coast,
+                accel_source: AccelSource::HighG,
+            }),
+            Message::Engine(EngineMessage {
+                state: EngineState::Running,
+                fuel_valve_open: true,
+                oxidizer_valve_open: false,
+                armed: true,
+                test_mode: false,
+                igniter_lit: true,
+                fuel_tank_pressure: 2.75e6,
+                oxidizer_tank_pressure: 2.5e6,
+                chamber_pressure: 1.9e6,
+            }),
+            Message::Health(HealthMessage {
+                imu_temp: 24.75,
+                imu_overflows: 2,
+                baro_rejections: 7,
+                deadline_misses: 1,
+                task_errors: 0,
+            }),
+            Message::Event(EventMessage::PhaseChange { from: FlightPhase::Boost, to: FlightPhase::Coast }),
+            Message::Event(EventMessage::EngineStateChange { from: EngineState::OxidizerLead, to: EngineState::Aborted }),
+            Message::Event(EventMessage::EngineStateChange { from: EngineState::Igniting, to: EngineState::Starting }),
+            Message::Event(EventMessage::EngineAborted(AbortReason::NoAcceleration)),
+            Message::Event(EventMessage::EngineAborted(AbortReason::LowFeedPressure)),
+            Message::Event(EventMessage::EngineAborted(AbortReason::ThrottleFault)),
+            Message::Event(EventMessage::Deployment { parachute: Parachute::Main, trigger: DeployTrigger::BackupTimer }),
+            Message::CommandResponse(CommandResponse { sequence: 70_000, result: CommandResult::Ack }),
+            Message::CommandResponse(CommandResponse {
+                sequence: u32::MAX,
+                result: CommandResult::Nack(NackReason::InvalidState),
+            }),
+        ]
+    }
+
+    fn sample_frames() -> Vec<Frame> {
+        sample_messages()
+            .into_iter()
+            .enumerate()
+            .map(|(i, message)| Frame { sequence: 65_534u16.wrapping_add(i as u16 * 1000), timestamp_ms: 4_000_000_000 - i as u32, message })
+            .collect()
+    }
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn every_message_type_round_trips() {
+        for frame in sample_frames() {
+            let bytes = frame.encode();
+            assert_eq!(bytes[..2], FRAME_SYNC);
+            assert_eq!(bytes[2], FRAME_VERSION);
+            assert_eq!(bytes.len(), FRAME_OVERHEAD + bytes[10] as usize);
+            assert_eq!(Frame::decode(&bytes), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn every_single_bit_flip_is_rejected() {
+        for frame in sample_frames() {
+            let bytes = frame.encode();
+            for bit in 0..bytes.len() * 8 {
+                let mut corrupted = bytes.clone();
+                corrupted[bit / 8] ^= 1 << (bit % 8);
+                assert!(Frame::decode(&corrupted).is_err(), "{} frame, bit {} flipped", frame.message.name(), bit);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_body_reports_crc() {
+        let mut bytes = sample_frames()[0].encode();
+        bytes[FRAME_HEADER_LEN + 3] ^= 0x40;
+        assert_eq!(Frame::decode(&bytes), Err(FrameError::BadCrc));
+        let mut bytes = sample_frames()[1].encode();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        assert_eq!(Frame::decode(&bytes), Err(FrameError::BadCrc));
+    }
+
+    #[test]
+    fn truncated_and_padded_frames_are_rejected() {
+        let bytes = sample_frames()[0].encode();
+        for len in 0..bytes.len() {
+            assert_eq!(Frame::decode(&bytes[..len]), Err(FrameError::Truncated), "{} bytes", len);
+        }
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(Frame::decode(&padded), Err(FrameError::InvalidPayload));
+    }
+
+    #[test]
+    fn bad_sync_is_rejected() {
+        let mut bytes = sample_frames()[2].encode();
+        bytes[0] = 0x00;
+        assert_eq!(Frame::decode(&bytes), Err(FrameError::BadSync));
+    }
+
+    // Re-seal a hand-edited frame so only the intended field is wrong
+    fn reseal(mut bytes: Vec<u8>) -> Vec<u8> {
+        let end = bytes.len() - 2;
+        let crc = crc16(&bytes[FRAME_SYNC.len()..end]);
+        bytes[end..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn other_versions_and_unknown_contents_are_rejected() {
+        let bytes = sample_frames()[1].encode();
+
+        let mut newer = bytes.clone();
+        newer[2] = FRAME_VERSION + 1;
+        assert_eq!(Frame::decode(&reseal(newer)), Err(FrameError::UnsupportedVersion(FRAME_VERSION + 1)));
+
+        let mut unknown_type = bytes.clone();
+        unknown_type[3] = 0x7F;
+        assert_eq!(Frame::decode(&reseal(unknown_type)), Err(FrameError::UnknownMessageType(0x7F)));
+
+        let mut unknown_state = bytes.clone();
+        unknown_state[FRAME_HEADER_LEN] = 0xEE;
+        assert_eq!(Frame::decode(&reseal(unknown_state)), Err(FrameError::InvalidPayload));
+
+        // An engine payload where a nav payload is expected
+        let mut wrong_type = bytes;
+        wrong_type[3] = MSG_NAV;
+        assert_eq!(Frame::decode(&reseal(wrong_type)), Err(FrameError::InvalidPayload));
+    }
+}
@@ -0,0 +1,420 @@
+// Uplink component: takes command frames (see uplink_frame) off the flight
+// radio, authenticates them, rejects replays, executes them and queues an
+// ACK/NACK for the telemetry component to send down.
+//
+// Replay protection: a command is only executed if its sequence number is
+// above the last one accepted. A repeat of the last accepted command (the
+// ground retrying because our ACK was lost) is answered again without being
+// executed again. The last accepted command is saved as a SequenceRecord
+// before it is answered, so a reboot doesn't reopen old sequence numbers;
+// without a readable record the first authentic frame sets the counter.
+use crate::hal::interface::{SpiBus, OutputPin, InputPin, DelayMs};
+use crate::drivers::radio::Radio;
+use crate::components::engine_control::{EngineCommand, EngineControl};
+use crate::components::flight_state::{FlightCriteria, FlightPhase, FlightStateMachine};
+use crate::components::telemetry_frame::CommandResponse;
+use crate::components::uplink_frame::{Command, CommandFrame, CommandResult, NackReason, Parameter, SequenceRecord};
+use crate::kernel::sync::{ChannelSender, Mutex};
+use crate::error::{ComponentError, Result, RocketError};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MAX_PACKET_LEN: usize = 255; // SX127x FIFO payload limit
+// Accepted ranges for SetParameter
+const OXIDIZER_LEAD_RANGE: (f32, f32) = (0.0, 2.0); // s
+const LAUNCH_ACCEL_RANGE: (f32, f32) = (5.0, 100.0); // m/s^2
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UplinkStats {
+    pub accepted: u32,      // Executed, whatever the outcome
+    pub duplicates: u32,    // Repeats of the last accepted command
+    pub replays: u32,       // Authentic but with an old sequence number
+    pub auth_failures: u32, // Dropped without a response
+    pub malformed: u32,     // Authentic but undecodable
+}
+
+pub struct Uplink<SPI, CS, IRQ, DELAY, P, S>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    DELAY: DelayMs,
+    P: OutputPin,
+    S: InputPin,
+{
+    radio: Arc<Mutex<Radio<SPI, CS, IRQ, DELAY>>>,
+    engine_control: Arc<Mutex<EngineControl<P, S>>>,
+    flight_state: Arc<Mutex<FlightStateMachine>>,
+    responses: ChannelSender<CommandResponse>,
+    key: Vec<u8>,
+    sequence_path: String, // Where last_accepted is persisted
+    last_accepted: Option<CommandResponse>,
+    stats: UplinkStats,
+}
+
+impl<SPI, CS, IRQ, DELAY, P, S> Uplink<SPI, CS, IRQ, DELAY, P, S>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    DELAY: DelayMs,
+    P: OutputPin,
+    S: InputPin,
+{
+    pub fn new(
+        radio: Arc<Mutex<Radio<SPI, CS, IRQ, DELAY>>>,
+        engine_control: Arc<Mutex<EngineControl<P, S>>>,
+        flight_state: Arc<Mutex<FlightStateMachine>>,
+        responses: ChannelSender<CommandResponse>,
+        key: &[u8],
+        sequence_path: &str,
+    ) -> Self {
+        let last_accepted = match SequenceRecord::load(sequence_path) {
+            Ok(SequenceRecord { sequence, result: Some(result) }) => {
+                println!("[Component:Uplink] Last accepted command #{} ({:?}).", sequence, result);
+                Some(CommandResponse { sequence, result })
+            }
+            Ok(_) => {
+                println!("[Component:Uplink] Sequence record {} has no result, ignoring it.", sequence_path);
+                None
+            }
+            Err(e) => {
+                println!("[Component:Uplink] No sequence record ({}), accepting the first authentic command.", e);
+                None
+            }
+        };
+        println!("[Component:Uplink] Created.");
+        Self {
+            radio,
+            engine_control,
+            flight_state,
+            responses,
+            key: key.to_vec(),
+            sequence_path: sequence_path.to_string(),
+            last_accepted,
+            stats: UplinkStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> UplinkStats {
+        self.stats
+    }
+
+    // Handle a command received since the last call, if any
+    pub fn poll(&mut self) -> Result<()> {
+        let mut buffer = [0u8; MAX_PACKET_LEN];
+        let len = self.radio.lock()?.receive_packet(&mut buffer)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let frame = match CommandFrame::decode(&buffer[..len], &self.key) {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("[Component:Uplink] Rejected {}-byte packet: {}", len, e);
+                return match e.authenticated_sequence() {
+                    Some(sequence) => {
+                        self.stats.malformed += 1;
+                        self.respond(sequence, CommandResult::Nack(NackReason::Malformed))
+                    }
+                    None => {
+                        self.stats.auth_failures += 1;
+                        Ok(())
+                    }
+                };
+            }
+        };
+
+        match self.last_accepted {
+            Some(last) if frame.sequence == last.sequence => {
+                self.stats.duplicates += 1;
+                println!("[Component:Uplink] Command #{} repeated, answering again", frame.sequence);
+                self.respond(last.sequence, last.result)
+            }
+            Some(last) if frame.sequence < last.sequence => {
+                self.stats.replays += 1;
+                println!(
+                    "[Component:Uplink] Command #{} {:?} rejected: replay (last accepted #{})",
+                    frame.sequence, frame.command, last.sequence
+                );
+                self.respond(frame.sequence, CommandResult::Nack(NackReason::Replay))
+            }
+            _ => {
+                self.stats.accepted += 1;
+                let result = self.execute(frame.command);
+                println!("[Component:Uplink] Command #{} {:?}: {:?}", frame.sequence, frame.command, result);
+                self.last_accepted = Some(CommandResponse { sequence: frame.sequence, result });
+                let record = SequenceRecord { sequence: frame.sequence, result: Some(result) };
+                if let Err(e) = record.save(&self.sequence_path) {
+                    eprintln!("[Component:Uplink] {}", e);
+                }
+                self.respond(frame.sequence, result)
+            }
+        }
+    }
+
+    fn execute(&mut self, command: Command) -> CommandResult {
+        let outcome = match command {
+            Command::Ping => Ok(()),
+            Command::Arm => self.engine_control.lock().and_then(|mut e| e.arm()),
+            Command::Disarm => self.engine_control.lock().and_then(|mut e| e.disarm()),
+            Command::Abort => self.engine_control.lock().and_then(|mut e| e.execute_command(EngineCommand::Abort)),
+            Command::SetTestMode(enabled) => self.engine_control.lock().and_then(|mut e| e.set_test_mode(enabled)),
+            Command::OpenValve(valve) => self.engine_control.lock().and_then(|mut e| e.set_valve(valve, true)),
+            Command::CloseValve(valve) => self.engine_control.lock().and_then(|mut e| e.set_valve(valve, false)),
+            Command::SetParameter(parameter, value) => return self.set_parameter(parameter, value),
+        };
+        match outcome {
+            Ok(()) => CommandResult::Ack,
+            // Logic errors are the component refusing in its current state
+            Err(RocketError::Component(ComponentError::LogicError(reason))) => {
+                println!("[Component:Uplink] Refused: {}", reason);
+                CommandResult::Nack(NackReason::InvalidState)
+            }
+            // The interlock has already logged why
+            Err(RocketError::Component(ComponentError::Interlock(_))) => CommandResult::Nack(NackReason::InvalidState),
+            Err(e) => {
+                eprintln!("[Component:Uplink] Command failed: {}", e);
+                CommandResult::Nack(NackReason::Failed)
+            }
+        }
+    }
+
+    // Parameters only change on the pad
+    fn set_parameter(&mut self, parameter: Parameter, value: f32) -> CommandResult {
+        let (min, max) = match parameter {
+            Parameter::OxidizerLead => OXIDIZER_LEAD_RANGE,
+            Parameter::LaunchAccel => LAUNCH_ACCEL_RANGE,
+        };
+        if !(min..=max).contains(&value) {
+            return CommandResult::Nack(NackReason::InvalidArgument);
+        }
+        let outcome = self.flight_state.lock().and_then(|mut flight_state| {
+            if flight_state.phase() != FlightPhase::Pad {
+                return Ok(false);
+            }
+            match parameter {
+                Parameter::OxidizerLead => {
+                    self.engine_control.lock()?.set_oxidizer_lead(Duration::from_secs_f32(value))?;
+                }
+                Parameter::LaunchAccel => {
+                    let criteria = flight_state.criteria();
+                    flight_state.set_criteria(FlightCriteria { launch_accel: value, ..criteria });
+                }
+            }
+            Ok(true)
+        });
+        match outcome {
+            Ok(true) => CommandResult::Ack,
+            Ok(false) | Err(RocketError::Component(ComponentError::LogicError(_))) => CommandResult::Nack(NackReason::InvalidState),
+            Err(e) => {
+                eprintln!("[Component:Uplink] Setting {:?} failed: {}", parameter, e);
+                CommandResult::Nack(NackReason::Failed)
+            }
+        }
+    }
+
+    fn respond(&self, sequence: u32, result: CommandResult) -> Result<()> {
+        self.responses.send(CommandResponse { sequence, result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::components::flight_state::FlightCriteria;
+    use crate::components::interlock::{SafetyInterlock, ValveId};
+    use crate::components::engine_control::IgnitionConfig;
+    use crate::components::navigation::SharedNavState;
+    use crate::components::uplink_frame::UPLINK_MAC_LEN;
+    use crate::drivers::radio::RadioConfig;
+    use crate::drivers::valve::Valve;
+    use crate::hal::dummy_hal::{self, DummyDelay, DummyPin, DummySpi};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::hal::radio_sim::LinkParams;
+    use crate::kernel::sim::{self, ClockMode};
+    use crate::kernel::sync::{Channel, ChannelReceiver, OverflowPolicy};
+
+    type SimRadio = Radio<DummySpi, DummyPin, DummyPin, DummyDelay>;
+
+    const KEY: &[u8] = b"test-key";
+
+    // The vehicle's uplink and engine, and a ground radio to command them with
+    struct Rig {
+        uplink: Uplink<DummySpi, DummyPin, DummyPin, DummyDelay, DummyPin, DummyPin>,
+        radio: Arc<Mutex<SimRadio>>,
+        engine: Arc<Mutex<EngineControl<DummyPin, DummyPin>>>,
+        flight_state: Arc<Mutex<FlightStateMachine>>,
+        responses: ChannelReceiver<CommandResponse>,
+        response_tx: ChannelSender<CommandResponse>,
+        ground: SimRadio,
+        sequence_path: String,
+    }
+
+    impl Rig {
+        // Starts without a sequence record
+        fn new(name: &str) -> Self {
+            sim::init(ClockMode::Simulated, 1);
+            dummy_hal::reset();
+            dummy_hal::set_link_params(LinkParams { packet_loss: 0.0, bit_error_rate: 0.0, fading: 0.0, ..LinkParams::default() });
+            dummy_hal::set_arm_switch(true);
+            let hal = dummy_hal::get_dummy_hal();
+            let pin = |id| hal.get_gpio_pin(id).unwrap();
+            let radio = |bus, cs, irq| {
+                let spi = hal.get_spi_bus(bus).unwrap();
+                Radio::new(spi, pin(cs), pin(irq), hal.get_delay_timer(), RadioConfig::default()).unwrap()
+            };
+            let interlock = SafetyInterlock::new(
+                Valve::new(pin(config::DUMMY_VALVE_PIN)).unwrap(),
+                Valve::new(pin(config::DUMMY_VALVE_PIN + 1)).unwrap(),
+                pin(config::DUMMY_IGNITER_PIN),
+                pin(config::DUMMY_ARM_SWITCH_PIN),
+                pin(config::DUMMY_VALVE_CONTINUITY_PIN),
+                pin(config::DUMMY_VALVE_CONTINUITY_PIN + 1),
+                pin(config::DUMMY_IGNITER_CONTINUITY_PIN),
+            )
+            .unwrap();
+            let nav_state = SharedNavState::new();
+            let engine = Arc::new(Mutex::new(EngineControl::new(interlock, nav_state.clone(), IgnitionConfig::default())));
+            let flight_state = Arc::new(Mutex::new(FlightStateMachine::new(nav_state, FlightCriteria::default())));
+            let ground = radio(config::DUMMY_GROUND_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_CS_PIN, config::DUMMY_GROUND_RADIO_IRQ_PIN);
+            let radio = Arc::new(Mutex::new(radio(config::DUMMY_RADIO_SPI_BUS, config::DUMMY_RADIO_CS_PIN, config::DUMMY_RADIO_IRQ_PIN)));
+            let (response_tx, responses) = Channel::new(8, OverflowPolicy::Reject).split();
+            let sequence_path = std::env::temp_dir()
+                .join(format!("uplink_sequence_{}_{}.bin", std::process::id(), name))
+                .to_string_lossy()
+                .into_owned();
+            let _ = std::fs::remove_file(&sequence_path);
+            let uplink = Uplink::new(radio.clone(), engine.clone(), flight_state.clone(), response_tx.clone(), KEY, &sequence_path);
+            Rig { uplink, radio, engine, flight_state, responses, response_tx, ground, sequence_path }
+        }
+
+        // Power-cycle the uplink: a fresh one with the same sequence record
+        fn reboot(&mut self) {
+            let (radio, engine, flight_state) = (self.radio.clone(), self.engine.clone(), self.flight_state.clone());
+            self.uplink = Uplink::new(radio, engine, flight_state, self.response_tx.clone(), KEY, &self.sequence_path);
+        }
+
+        // Put a packet on the air and let the uplink handle it; returns its answer
+        fn send(&mut self, packet: &[u8]) -> Option<CommandResponse> {
+            self.ground.send_packet(packet).unwrap();
+            sim::sleep(Duration::from_millis(5));
+            self.uplink.poll().unwrap();
+            self.responses.try_recv().unwrap()
+        }
+
+        fn command(&mut self, sequence: u32, command: Command) -> Option<CommandResponse> {
+            self.send(&CommandFrame { sequence, command }.encode(KEY))
+        }
+    }
+
+    impl Drop for Rig {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.sequence_path);
+        }
+    }
+
+    fn answer(sequence: u32, result: CommandResult) -> Option<CommandResponse> {
+        Some(CommandResponse { sequence, result })
+    }
+
+    #[test]
+    fn executes_authentic_commands_and_answers_them() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("executes");
+        assert_eq!(rig.command(1, Command::Ping), answer(1, CommandResult::Ack));
+        assert_eq!(rig.command(2, Command::SetTestMode(true)), answer(2, CommandResult::Ack));
+        assert!(rig.engine.lock().unwrap().test_mode());
+        assert_eq!(rig.command(5, Command::OpenValve(ValveId::Oxidizer)), answer(5, CommandResult::Ack));
+        assert!(rig.engine.lock().unwrap().oxidizer_valve_open());
+        // Executed, but refused by the engine
+        assert_eq!(rig.command(6, Command::Arm), answer(6, CommandResult::Nack(NackReason::InvalidState)));
+        assert_eq!(
+            rig.command(7, Command::SetParameter(Parameter::OxidizerLead, 5.0)),
+            answer(7, CommandResult::Nack(NackReason::InvalidArgument))
+        );
+        assert_eq!(rig.uplink.stats(), UplinkStats { accepted: 5, ..UplinkStats::default() });
+    }
+
+    #[test]
+    fn forged_frames_are_dropped_without_an_answer() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("forged");
+        assert_eq!(rig.send(&CommandFrame { sequence: 1, command: Command::SetTestMode(true) }.encode(b"wrong-key")), None);
+        let mut tampered = CommandFrame { sequence: 2, command: Command::SetTestMode(true) }.encode(KEY);
+        tampered[3] ^= 0x01;
+        assert_eq!(rig.send(&tampered), None);
+        assert!(!rig.engine.lock().unwrap().test_mode());
+        assert_eq!(rig.uplink.stats(), UplinkStats { auth_failures: 2, ..UplinkStats::default() });
+        // A forgery doesn't move the counter either
+        assert_eq!(rig.command(1, Command::Ping), answer(1, CommandResult::Ack));
+    }
+
+    #[test]
+    fn old_sequence_numbers_are_replays() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("replays");
+        let first = CommandFrame { sequence: 3, command: Command::SetTestMode(true) }.encode(KEY);
+        assert_eq!(rig.send(&first), answer(3, CommandResult::Ack));
+        assert_eq!(rig.command(10, Command::SetTestMode(false)), answer(10, CommandResult::Ack));
+        // The captured first frame, and a new one numbered below the counter
+        assert_eq!(rig.send(&first), answer(3, CommandResult::Nack(NackReason::Replay)));
+        assert_eq!(rig.command(9, Command::Ping), answer(9, CommandResult::Nack(NackReason::Replay)));
+        assert!(!rig.engine.lock().unwrap().test_mode());
+        assert_eq!(rig.uplink.stats(), UplinkStats { accepted: 2, replays: 2, ..UplinkStats::default() });
+    }
+
+    #[test]
+    fn a_repeat_of_the_last_command_is_answered_but_not_run_again() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("repeat");
+        assert_eq!(rig.command(1, Command::SetTestMode(true)), answer(1, CommandResult::Ack));
+        let open = CommandFrame { sequence: 2, command: Command::OpenValve(ValveId::Fuel) }.encode(KEY);
+        assert_eq!(rig.send(&open), answer(2, CommandResult::Ack));
+        rig.engine.lock().unwrap().set_valve(ValveId::Fuel, false).unwrap();
+        // The ground didn't hear the ACK and retries
+        assert_eq!(rig.send(&open), answer(2, CommandResult::Ack));
+        assert!(!rig.engine.lock().unwrap().fuel_valve_open());
+        assert_eq!(rig.uplink.stats(), UplinkStats { accepted: 2, duplicates: 1, ..UplinkStats::default() });
+    }
+
+    #[test]
+    fn authentic_but_malformed_frames_are_nacked() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("malformed");
+        // Valid MAC over a frame claiming an argument Ping doesn't take
+        let mut frame = CommandFrame { sequence: 4, command: Command::Ping }.encode(KEY);
+        frame.truncate(frame.len() - UPLINK_MAC_LEN);
+        frame[8] = 1;
+        frame.push(0);
+        let mac = {
+            use hmac::{Hmac, Mac};
+            let mut mac = Hmac::<sha2::Sha256>::new_from_slice(KEY).unwrap();
+            mac.update(&frame[2..]);
+            mac.finalize().into_bytes()
+        };
+        frame.extend_from_slice(&mac[..UPLINK_MAC_LEN]);
+        assert_eq!(rig.send(&frame), answer(4, CommandResult::Nack(NackReason::Malformed)));
+        assert_eq!(rig.uplink.stats(), UplinkStats { malformed: 1, ..UplinkStats::default() });
+    }
+
+    #[test]
+    fn the_counter_survives_a_reboot() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("reboot");
+        let arm_test = CommandFrame { sequence: 7, command: Command::SetTestMode(true) }.encode(KEY);
+        assert_eq!(rig.send(&arm_test), answer(7, CommandResult::Ack));
+        rig.engine.lock().unwrap().set_test_mode(false).unwrap();
+
+        rig.reboot();
+        assert_eq!(rig.send(&arm_test), answer(7, CommandResult::Ack)); // Answered from the record
+        assert!(!rig.engine.lock().unwrap().test_mode());
+        assert_eq!(rig.command(6, Command::Ping), answer(6, CommandResult::Nack(NackReason::Replay)));
+        assert_eq!(rig.command(8, Command::Ping), answer(8, CommandResult::Ack));
+
+        // Without a record the first authentic command sets the counter
+        std::fs::remove_file(&rig.sequence_path).unwrap();
+        rig.reboot();
+        assert_eq!(rig.command(2, Command::Ping), answer(2, CommandResult::Ack));
+    }
+}
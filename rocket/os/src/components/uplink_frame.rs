@@ -0,0 +1,422 @@
+// Uplink command frame format, ground to vehicle. One frame per radio
+// packet, little endian:
+//
+//   sync: 2 bytes (0xA5 0xC3) | version: u8 | seq: u32 | command: u8 |
+//   argument length: u8 | arguments | MAC: 16 bytes
+//
+// The MAC is HMAC-SHA256 with the pre-shared key over everything from the
+// version byte on, truncated to its first 16 bytes. The sequence number is
+// covered by the MAC and must increase from one command to the next, which
+// is what lets the vehicle reject replayed frames (see components::uplink).
+// Both ends keep their counter in a SequenceRecord so that holds across
+// restarts.
+use crate::components::interlock::ValveId;
+use crate::drivers::imu_calibration::fletcher16;
+use crate::error::RocketError;
+use crate::error::Result as RocketResult;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::fs;
+
+pub const UPLINK_SYNC: [u8; 2] = [0xA5, 0xC3];
+pub const UPLINK_VERSION: u8 = 1;
+pub const UPLINK_HEADER_LEN: usize = 2 + 1 + 4 + 1 + 1;
+pub const UPLINK_MAC_LEN: usize = 16;
+
+// Command codes
+const CMD_PING: u8 = 1;
+const CMD_ARM: u8 = 2;
+const CMD_DISARM: u8 = 3;
+const CMD_ABORT: u8 = 4;
+const CMD_SET_TEST_MODE: u8 = 5;
+const CMD_OPEN_VALVE: u8 = 6;
+const CMD_CLOSE_VALVE: u8 = 7;
+const CMD_SET_PARAMETER: u8 = 8;
+
+const RECORD_MAGIC: [u8; 4] = *b"UPSQ";
+const RECORD_NO_RESULT: u8 = 0xFF;
+pub const SEQUENCE_RECORD_LEN: usize = RECORD_MAGIC.len() + 4 + 1 + 2; // + Fletcher-16
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UplinkError {
+    Truncated,
+    BadSync,
+    BadMac, // Not sent by someone holding the key, or corrupted
+    // Authentic, but not a command this decoder understands
+    UnsupportedVersion { sequence: u32, version: u8 },
+    Malformed { sequence: u32 },
+}
+
+impl UplinkError {
+    // Sequence number of a frame that passed authentication, so the
+    // vehicle can answer it even though it can't execute it
+    pub fn authenticated_sequence(&self) -> Option<u32> {
+        match self {
+            UplinkError::UnsupportedVersion { sequence, .. } | UplinkError::Malformed { sequence } => Some(*sequence),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UplinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UplinkError::Truncated => write!(f, "truncated frame"),
+            UplinkError::BadSync => write!(f, "bad sync word"),
+            UplinkError::BadMac => write!(f, "authentication failed"),
+            UplinkError::UnsupportedVersion { sequence, version } => {
+                write!(f, "command #{}: unsupported uplink version {}", sequence, version)
+            }
+            UplinkError::Malformed { sequence } => write!(f, "command #{}: malformed", sequence),
+        }
+    }
+}
+
+// Vehicle parameters that can be changed from the ground (on the pad only)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameter {
+    OxidizerLead, // s, oxidizer-only time before the fuel valve opens
+    LaunchAccel,  // m/s^2, axial acceleration that declares liftoff
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Ping,
+    Arm,
+    Disarm,
+    Abort,
+    SetTestMode(bool),   // Valve commands are only accepted in test mode
+    OpenValve(ValveId),
+    CloseValve(ValveId),
+    SetParameter(Parameter, f32),
+}
+
+impl Command {
+    fn code(&self) -> u8 {
+        match self {
+            Command::Ping => CMD_PING,
+            Command::Arm => CMD_ARM,
+            Command::Disarm => CMD_DISARM,
+            Command::Abort => CMD_ABORT,
+            Command::SetTestMode(_) => CMD_SET_TEST_MODE,
+            Command::OpenValve(_) => CMD_OPEN_VALVE,
+            Command::CloseValve(_) => CMD_CLOSE_VALVE,
+            Command::SetParameter(..) => CMD_SET_PARAMETER,
+        }
+    }
+
+    fn encode_args(&self, out: &mut Vec<u8>) {
+        match self {
+            Command::Ping | Command::Arm | Command::Disarm | Command::Abort => {}
+            Command::SetTestMode(enabled) => out.push(*enabled as u8),
+            Command::OpenValve(valve) | Command::CloseValve(valve) => out.push(valve_code(*valve)),
+            Command::SetParameter(parameter, value) => {
+                out.push(parameter_code(*parameter));
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    fn decode(code: u8, args: &[u8]) -> Option<Self> {
+        match (code, args) {
+            (CMD_PING, []) => Some(Command::Ping),
+            (CMD_ARM, []) => Some(Command::Arm),
+            (CMD_DISARM, []) => Some(Command::Disarm),
+            (CMD_ABORT, []) => Some(Command::Abort),
+            (CMD_SET_TEST_MODE, [0]) => Some(Command::SetTestMode(false)),
+            (CMD_SET_TEST_MODE, [1]) => Some(Command::SetTestMode(true)),
+            (CMD_OPEN_VALVE, [valve]) => valve_from_code(*valve).map(Command::OpenValve),
+            (CMD_CLOSE_VALVE, [valve]) => valve_from_code(*valve).map(Command::CloseValve),
+            (CMD_SET_PARAMETER, [parameter, a, b, c, d]) => {
+                parameter_from_code(*parameter).map(|p| Command::SetParameter(p, f32::from_le_bytes([*a, *b, *c, *d])))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandFrame {
+    pub sequence: u32,
+    pub command: Command,
+}
+
+impl CommandFrame {
+    pub fn encode(&self, key: &[u8]) -> Vec<u8> {
+        let mut args = Vec::new();
+        self.command.encode_args(&mut args);
+        let mut bytes = Vec::with_capacity(UPLINK_HEADER_LEN + args.len() + UPLINK_MAC_LEN);
+        bytes.extend_from_slice(&UPLINK_SYNC);
+        bytes.push(UPLINK_VERSION);
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.push(self.command.code());
+        bytes.push(args.len() as u8);
+        bytes.extend_from_slice(&args);
+        let mac = mac(key, &bytes[UPLINK_SYNC.len()..]).finalize().into_bytes();
+        bytes.extend_from_slice(&mac[..UPLINK_MAC_LEN]);
+        bytes
+    }
+
+    // Nothing past the sync word is trusted until the MAC checks out
+    pub fn decode(bytes: &[u8], key: &[u8]) -> Result<Self, UplinkError> {
+        if bytes.len() < UPLINK_HEADER_LEN + UPLINK_MAC_LEN {
+            return Err(UplinkError::Truncated);
+        }
+        if bytes[..2] != UPLINK_SYNC {
+            return Err(UplinkError::BadSync);
+        }
+        let (body, tag) = bytes.split_at(bytes.len() - UPLINK_MAC_LEN);
+        mac(key, &body[UPLINK_SYNC.len()..])
+            .verify_truncated_left(tag)
+            .map_err(|_| UplinkError::BadMac)?;
+
+        let sequence = u32::from_le_bytes([body[3], body[4], body[5], body[6]]);
+        if body[2] != UPLINK_VERSION {
+            return Err(UplinkError::UnsupportedVersion { sequence, version: body[2] });
+        }
+        let args = &body[UPLINK_HEADER_LEN..];
+        if args.len() != body[8] as usize {
+            return Err(UplinkError::Malformed { sequence });
+        }
+        let command = Command::decode(body[7], args).ok_or(UplinkError::Malformed { sequence })?;
+        Ok(CommandFrame { sequence, command })
+    }
+}
+
+fn mac(key: &[u8], data: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac
+}
+
+// Why the vehicle refused a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NackReason {
+    Replay,          // Sequence number not above the last one accepted
+    Malformed,
+    InvalidState,    // Not allowed right now (e.g. valve command outside test mode, arm switch safe)
+    InvalidArgument, // Parameter value out of range
+    Failed,          // Accepted, but executing it failed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandResult {
+    Ack,
+    Nack(NackReason),
+}
+
+impl CommandResult {
+    pub fn code(&self) -> u8 {
+        match self {
+            CommandResult::Ack => 0,
+            CommandResult::Nack(NackReason::Replay) => 1,
+            CommandResult::Nack(NackReason::Malformed) => 2,
+            CommandResult::Nack(NackReason::InvalidState) => 3,
+            CommandResult::Nack(NackReason::InvalidArgument) => 4,
+            CommandResult::Nack(NackReason::Failed) => 5,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(CommandResult::Ack),
+            1 => Some(CommandResult::Nack(NackReason::Replay)),
+            2 => Some(CommandResult::Nack(NackReason::Malformed)),
+            3 => Some(CommandResult::Nack(NackReason::InvalidState)),
+            4 => Some(CommandResult::Nack(NackReason::InvalidArgument)),
+            5 => Some(CommandResult::Nack(NackReason::Failed)),
+            _ => None,
+        }
+    }
+}
+
+// Last sequence number an end has used, persisted across restarts: on the
+// vehicle the last command accepted and its result, on the ground the last
+// command sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceRecord {
+    pub sequence: u32,
+    pub result: Option<CommandResult>,
+}
+
+impl SequenceRecord {
+    // Fixed little-endian layout: magic, sequence, result code, Fletcher-16
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SEQUENCE_RECORD_LEN);
+        bytes.extend_from_slice(&RECORD_MAGIC);
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes.push(self.result.map_or(RECORD_NO_RESULT, |result| result.code()));
+        let checksum = fletcher16(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SEQUENCE_RECORD_LEN || bytes[..4] != RECORD_MAGIC {
+            return None;
+        }
+        let (body, checksum) = bytes.split_at(SEQUENCE_RECORD_LEN - 2);
+        if fletcher16(body) != u16::from_le_bytes([checksum[0], checksum[1]]) {
+            return None;
+        }
+        let result = match body[8] {
+            RECORD_NO_RESULT => None,
+            code => Some(CommandResult::from_code(code)?),
+        };
+        Some(SequenceRecord { sequence: u32::from_le_bytes([body[4], body[5], body[6], body[7]]), result })
+    }
+
+    pub fn save(&self, path: &str) -> RocketResult<()> {
+        fs::write(path, self.to_bytes())
+            .map_err(|e| RocketError::Configuration(format!("Failed to save uplink sequence to {}: {}", path, e)))
+    }
+
+    pub fn load(path: &str) -> RocketResult<Self> {
+        let bytes = fs::read(path)
+            .map_err(|e| RocketError::Configuration(format!("Failed to read uplink sequence {}: {}", path, e)))?;
+        Self::from_bytes(&bytes).ok_or_else(|| RocketError::Configuration(format!("Corrupt uplink sequence record {}", path)))
+    }
+}
+
+fn valve_code(valve: ValveId) -> u8 {
+    match valve {
+        ValveId::Fuel => 0,
+        ValveId::Oxidizer => 1,
+    }
+}
+
+fn valve_from_code(code: u8) -> Option<ValveId> {
+    match code {
+        0 => Some(ValveId::Fuel),
+        1 => Some(ValveId::Oxidizer),
+        _ => None,
+    }
+}
+
+fn parameter_code(parameter: Parameter) -> u8 {
+    match parameter {
+        Parameter::OxidizerLead => 1,
+        Parameter::LaunchAccel => 2,
+    }
+}
+
+fn parameter_from_code(code: u8) -> Option<Parameter> {
+    match code {
+        1 => Some(Parameter::OxidizerLead),
+        2 => Some(Parameter::LaunchAccel),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"test-key";
+
+    // A frame with a valid MAC over whatever follows the sync word
+    fn seal(body: &[u8]) -> Vec<u8> {
+        let mut bytes = UPLINK_SYNC.to_vec();
+        bytes.extend_from_slice(body);
+        let mac = mac(KEY, body).finalize().into_bytes();
+        bytes.extend_from_slice(&mac[..UPLINK_MAC_LEN]);
+        bytes
+    }
+
+    #[test]
+    fn every_command_round_trips() {
+        let commands = [
+            Command::Ping,
+            Command::Arm,
+            Command::Disarm,
+            Command::Abort,
+            Command::SetTestMode(true),
+            Command::SetTestMode(false),
+            Command::OpenValve(ValveId::Fuel),
+            Command::CloseValve(ValveId::Oxidizer),
+            Command::SetParameter(Parameter::OxidizerLead, 0.5),
+            Command::SetParameter(Parameter::LaunchAccel, 30.0),
+        ];
+        for (i, command) in commands.into_iter().enumerate() {
+            let frame = CommandFrame { sequence: 1_000_000 + i as u32, command };
+            assert_eq!(CommandFrame::decode(&frame.encode(KEY), KEY), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn forged_and_damaged_frames_fail_authentication() {
+        let bytes = CommandFrame { sequence: 7, command: Command::SetParameter(Parameter::LaunchAccel, 30.0) }.encode(KEY);
+        assert_eq!(CommandFrame::decode(&bytes, b"wrong-key"), Err(UplinkError::BadMac));
+        // Any byte after the sync word, MAC included
+        for i in UPLINK_SYNC.len()..bytes.len() {
+            let mut tampered = bytes.clone();
+            tampered[i] ^= 0x01;
+            assert_eq!(CommandFrame::decode(&tampered, KEY), Err(UplinkError::BadMac), "byte {}", i);
+        }
+        let mut bad_sync = bytes.clone();
+        bad_sync[0] ^= 0x01;
+        assert_eq!(CommandFrame::decode(&bad_sync, KEY), Err(UplinkError::BadSync));
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        // Dropping MAC bytes shifts the tag onto the arguments
+        let bytes = CommandFrame { sequence: 7, command: Command::OpenValve(ValveId::Fuel) }.encode(KEY);
+        assert_eq!(CommandFrame::decode(&bytes[..bytes.len() - 1], KEY), Err(UplinkError::BadMac));
+        // Without arguments there is nothing left to shift onto
+        let bytes = CommandFrame { sequence: 7, command: Command::Ping }.encode(KEY);
+        assert_eq!(bytes.len(), UPLINK_HEADER_LEN + UPLINK_MAC_LEN);
+        assert_eq!(CommandFrame::decode(&bytes[..bytes.len() - 1], KEY), Err(UplinkError::Truncated));
+        assert_eq!(CommandFrame::decode(&[], KEY), Err(UplinkError::Truncated));
+    }
+
+    #[test]
+    fn authentic_but_undecodable_frames_keep_their_sequence() {
+        let sequence = 42u32.to_le_bytes();
+        let frame = |version: u8, code: u8, args: &[u8], declared_len: u8| {
+            let mut body = vec![version];
+            body.extend_from_slice(&sequence);
+            body.push(code);
+            body.push(declared_len);
+            body.extend_from_slice(args);
+            seal(&body)
+        };
+        let decode = |bytes: Vec<u8>| CommandFrame::decode(&bytes, KEY);
+
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_PING, &[], 0)).map(|f| f.command), Ok(Command::Ping));
+        assert_eq!(
+            decode(frame(UPLINK_VERSION + 1, CMD_PING, &[], 0)),
+            Err(UplinkError::UnsupportedVersion { sequence: 42, version: UPLINK_VERSION + 1 })
+        );
+        let malformed = Err(UplinkError::Malformed { sequence: 42 });
+        assert_eq!(decode(frame(UPLINK_VERSION, 0xEE, &[], 0)), malformed); // Unknown command
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_PING, &[0], 0)), malformed); // Length disagrees
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_PING, &[0], 1)), malformed); // Ping takes nothing
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_OPEN_VALVE, &[9], 1)), malformed); // No such valve
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_SET_TEST_MODE, &[2], 1)), malformed);
+        assert_eq!(decode(frame(UPLINK_VERSION, CMD_SET_PARAMETER, &[1, 0, 0], 3)), malformed);
+        assert_eq!(UplinkError::Malformed { sequence: 42 }.authenticated_sequence(), Some(42));
+        assert_eq!(UplinkError::BadMac.authenticated_sequence(), None);
+    }
+
+    #[test]
+    fn sequence_record_round_trips_and_rejects_damage() {
+        for record in [
+            SequenceRecord { sequence: 0x0102_0304, result: Some(CommandResult::Nack(NackReason::Replay)) },
+            SequenceRecord { sequence: u32::MAX, result: None },
+        ] {
+            let bytes = record.to_bytes();
+            assert_eq!(bytes.len(), SEQUENCE_RECORD_LEN);
+            assert_eq!(SequenceRecord::from_bytes(&bytes), Some(record));
+            for i in 0..bytes.len() {
+                let mut damaged = bytes.clone();
+                damaged[i] ^= 0x10;
+                assert_eq!(SequenceRecord::from_bytes(&damaged), None, "byte {}", i);
+            }
+            assert_eq!(SequenceRecord::from_bytes(&bytes[..bytes.len() - 1]), None);
+        }
+    }
+}
@@ -1,17 +1,95 @@
 use std::time::Duration;
+use crate::kernel::sim::ClockMode;
+use crate::drivers::pressure_transducer::PressureCalibration;
 
 // Simulation parameters
 pub const SIM_TICK_RATE: Duration = Duration::from_millis(10); // Base tick for simulation delays
+pub const SIM_PHYSICS_STEP: Duration = Duration::from_millis(1); // Vehicle dynamics integration step
+pub const SIM_CLOCK_MODE: ClockMode = ClockMode::Simulated; // RealTime to run against the wall clock
+pub const SIM_SEED: u64 = 0x5EED; // Seed for all simulated noise; same seed => same run
 
 // Task loop rates (adjust as needed)
 pub const NAV_LOOP_RATE: Duration = Duration::from_millis(50); // 20 Hz
 pub const CONTROL_LOOP_RATE: Duration = Duration::from_millis(20); // 50 Hz
 pub const TELEMETRY_LOOP_RATE: Duration = Duration::from_millis(200); // 5 Hz
+// One frame per telemetry cycle: pending events first, otherwise nav state
+// except on every Nth cycle, which carries engine state or health instead
+pub const TELEMETRY_ENGINE_INTERVAL: u32 = 5; // cycles
+pub const TELEMETRY_HEALTH_INTERVAL: u32 = 10; // cycles
+pub const UPLINK_LOOP_RATE: Duration = Duration::from_millis(50); // Check the radio for commands
+pub const RECOVERY_LOOP_RATE: Duration = Duration::from_millis(20); // 50 Hz, also times the pyro pulses
+
+// Task priorities (higher runs first when released together)
+pub const CONTROL_TASK_PRIORITY: u8 = 3;
+pub const RECOVERY_TASK_PRIORITY: u8 = 3;
+pub const NAV_TASK_PRIORITY: u8 = 2;
+pub const TELEMETRY_TASK_PRIORITY: u8 = 1;
+pub const UPLINK_TASK_PRIORITY: u8 = 1;
+pub const SCHEDULER_REPORT_INTERVAL: Duration = Duration::from_secs(5); // Task statistics printout
+pub const SIM_RUN_DURATION: Option<Duration> = Some(Duration::from_secs(120)); // None = run until a task requests shutdown
 
 // Simulated Hardware Configuration
 pub const DUMMY_IMU_ADDR: u8 = 0x68;
+pub const DUMMY_IMU_INT_PIN: u8 = 12; // IMU INT (data ready) to this GPIO input
+pub const DUMMY_BARO_ADDR: u8 = 0x76; // BMP280 with SDO tied low
+pub const DUMMY_HIGH_G_ADDR: u8 = 0x53; // ADXL375 with ALT ADDRESS tied low
 pub const DUMMY_VALVE_PIN: u8 = 10; // Simulated GPIO pin number
+pub const DUMMY_VALVE_CONTINUITY_PIN: u8 = 16; // Fuel valve coil continuity sense; oxidizer on the next pin
+pub const DUMMY_ARM_SWITCH_PIN: u8 = 14; // Physical arm switch, high when closed; also switches valve and igniter power
+pub const DUMMY_IGNITER_PIN: u8 = 13;
+pub const DUMMY_IGNITER_CONTINUITY_PIN: u8 = 18; // Igniter continuity sense
+pub const DUMMY_DROGUE_PYRO_PIN: u8 = 24;
+pub const DUMMY_MAIN_PYRO_PIN: u8 = 25;
+pub const DUMMY_DROGUE_CONTINUITY_PIN: u8 = 26; // E-match continuity senses
+pub const DUMMY_MAIN_CONTINUITY_PIN: u8 = 27;
+pub const DUMMY_THROTTLE_PWM_CHANNEL: u8 = 0; // Throttle valve servo
+pub const DUMMY_PWM_PERIOD: Duration = Duration::from_millis(20); // 50 Hz servo frame
+pub const DUMMY_ADC_ID: u8 = 0;
+pub const DUMMY_THROTTLE_FEEDBACK_CHANNEL: u8 = 0; // ADC channel of the throttle servo's feedback pot
+pub const DUMMY_FUEL_TANK_PRESSURE_CHANNEL: u8 = 1; // ADC channels of the pressure transducers
+pub const DUMMY_OXIDIZER_TANK_PRESSURE_CHANNEL: u8 = 2;
+pub const DUMMY_CHAMBER_PRESSURE_CHANNEL: u8 = 3;
 pub const DUMMY_RADIO_SPI_BUS: u8 = 1; // Simulated SPI bus ID
+pub const DUMMY_RADIO_CS_PIN: u8 = 20;
+pub const DUMMY_RADIO_IRQ_PIN: u8 = 21; // Radio DIO0
+// Ground station side of the simulated radio link
+pub const DUMMY_GROUND_RADIO_SPI_BUS: u8 = 2;
+pub const DUMMY_GROUND_RADIO_CS_PIN: u8 = 22;
+pub const DUMMY_GROUND_RADIO_IRQ_PIN: u8 = 23;
+
+// IMU calibration
+pub const IMU_CALIBRATION_PATH: &str = "imu_calibration.bin"; // Persisted calibration record
+pub const IMU_CALIBRATION_SAMPLES: usize = 200; // Stationary samples averaged on the pad
+pub const IMU_CALIBRATION_INTERVAL_MS: u32 = 10;
+// Bias temperature coefficients from bench characterisation of the IMU part
+pub const IMU_GYRO_TEMP_COEFF: [f32; 3] = [0.0004, -0.0003, 0.0002]; // rad/s per degree C
+pub const IMU_ACCEL_TEMP_COEFF: [f32; 3] = [0.004, 0.003, -0.005]; // m/s^2 per degree C
+
+// Pressure transducer calibrations from the bench, one per fitted unit
+pub const FUEL_TANK_TRANSDUCER_CAL: PressureCalibration =
+    PressureCalibration { zero_counts: 406, full_scale_counts: 3688, full_scale_pressure: 5.0e6 };
+pub const OXIDIZER_TANK_TRANSDUCER_CAL: PressureCalibration =
+    PressureCalibration { zero_counts: 417, full_scale_counts: 3681, full_scale_pressure: 5.0e6 };
+pub const CHAMBER_TRANSDUCER_CAL: PressureCalibration =
+    PressureCalibration { zero_counts: 412, full_scale_counts: 3685, full_scale_pressure: 3.5e6 };
+
+// Uplink command authentication. Pre-shared between vehicle and ground;
+// this one is for the simulation only, flight keys are provisioned separately.
+pub const UPLINK_KEY: [u8; 32] = *b"rocket-os-simulation-uplink-key!";
+pub const UPLINK_SEQUENCE_PATH: &str = "uplink_sequence.bin"; // Last command accepted, kept across reboots
+
+// Ground station
+pub const GROUND_POLL_RATE: Duration = Duration::from_millis(10); // Drain the ground radio, catch the uplink window
+pub const GROUND_DASHBOARD_RATE: Duration = Duration::from_secs(1); // Dashboard refresh
+pub const GROUND_POLL_TASK_PRIORITY: u8 = 1;
+pub const GROUND_DASHBOARD_TASK_PRIORITY: u8 = 0;
+pub const GROUND_RECORDING_PATH: &str = "ground_recording.csv"; // Every received packet, one row each
+pub const GROUND_SEQUENCE_PATH: &str = "ground_sequence.bin"; // Last command sequence number used
+pub const GROUND_LINK_TIMEOUT: Duration = Duration::from_secs(2); // Dashboard flags the link as lost after this
+pub const GROUND_COMMAND_TIMEOUT: Duration = Duration::from_secs(1); // Wait for an ACK/NACK before resending
+pub const GROUND_COMMAND_RETRIES: u32 = 3; // Resends before a command is given up
 
 // Component Configuration
 pub const TARGET_APOGEE: f32 = 1000.0; // meters
+pub const IGNITION_OXIDIZER_LEAD: Duration = Duration::from_millis(500); // Oxidizer open before fuel
+pub const PYRO_FIRE_PULSE: Duration = Duration::from_millis(500); // Pyro output held high this long on every fire
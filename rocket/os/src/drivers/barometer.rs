@@ -0,0 +1,251 @@
+use crate::hal::interface::{I2cBus, DelayMs};
+use crate::error::{DriverError, DriverResult}; // Driver-level Result (see error.rs)
+use crate::error::Result as RocketResult; // Using top-level Result
+
+// BMP280 registers
+const REG_CALIB: u8 = 0x88; // dig_T1..dig_P9, 24 bytes little endian
+const REG_CHIP_ID: u8 = 0xD0;
+const REG_RESET: u8 = 0xE0;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_CONFIG: u8 = 0xF5;
+const REG_PRESS_MSB: u8 = 0xF7; // press_msb..temp_xlsb, 6 bytes
+
+const CHIP_ID: u8 = 0x58;
+const RESET_COMMAND: u8 = 0xB6;
+// Temperature x2, pressure x16 oversampling, normal mode ("ultra high resolution")
+const CTRL_MEAS_VALUE: u8 = (0b010 << 5) | (0b101 << 2) | 0b11;
+// 0.5 ms standby, IIR filter off (navigation does its own filtering)
+const CONFIG_VALUE: u8 = 0x00;
+const SKIPPED_SAMPLE: i32 = 0x80000; // Reported for a measurement that is switched off
+
+pub const SEA_LEVEL_PRESSURE: f32 = 101_325.0; // Pa, ISA
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BaroData {
+    pub pressure: f32,    // Pa
+    pub temperature: f32, // degrees C
+}
+
+// Factory trimming coefficients, read once at start-up
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Calibration {
+    pub dig_t1: u16,
+    pub dig_t2: i16,
+    pub dig_t3: i16,
+    pub dig_p1: u16,
+    pub dig_p: [i16; 8], // dig_P2..dig_P9
+}
+
+impl Calibration {
+    fn from_bytes(raw: &[u8; 24]) -> Self {
+        let word = |i: usize| [raw[2 * i], raw[2 * i + 1]];
+        let mut dig_p = [0i16; 8];
+        for (i, coefficient) in dig_p.iter_mut().enumerate() {
+            *coefficient = i16::from_le_bytes(word(4 + i));
+        }
+        Calibration {
+            dig_t1: u16::from_le_bytes(word(0)),
+            dig_t2: i16::from_le_bytes(word(1)),
+            dig_t3: i16::from_le_bytes(word(2)),
+            dig_p1: u16::from_le_bytes(word(3)),
+            dig_p,
+        }
+    }
+
+    // Fine temperature, shared by both compensations (datasheet 3.11.3)
+    pub(crate) fn t_fine(&self, adc_t: i32) -> i32 {
+        let (t1, t2, t3) = (self.dig_t1 as i32, self.dig_t2 as i32, self.dig_t3 as i32);
+        let var1 = (((adc_t >> 3) - (t1 << 1)) * t2) >> 11;
+        let var2 = (((((adc_t >> 4) - t1) * ((adc_t >> 4) - t1)) >> 12) * t3) >> 14;
+        var1 + var2
+    }
+
+    // Pressure in Pa as Q24.8, or None if the coefficients would divide by zero
+    pub(crate) fn pressure(&self, adc_p: i32, t_fine: i32) -> Option<i64> {
+        let p1 = self.dig_p1 as i64;
+        let [p2, p3, p4, p5, p6, p7, p8, p9] = self.dig_p.map(|c| c as i64);
+        let mut var1 = t_fine as i64 - 128000;
+        let mut var2 = var1 * var1 * p6;
+        var2 += (var1 * p5) << 17;
+        var2 += p4 << 35;
+        var1 = ((var1 * var1 * p3) >> 8) + ((var1 * p2) << 12);
+        var1 = (((1i64 << 47) + var1) * p1) >> 33;
+        if var1 == 0 {
+            return None;
+        }
+        let mut p = 1048576 - adc_p as i64;
+        p = (((p << 31) - var2) * 3125) / var1;
+        let var1 = (p9 * (p >> 13) * (p >> 13)) >> 25;
+        let var2 = (p8 * p) >> 19;
+        Some(((p + var1 + var2) >> 8) + (p7 << 4))
+    }
+}
+
+// Altitude in m of `pressure` above the level where the pressure is
+// `reference_pressure` (ISA troposphere, hypsometric form)
+pub fn pressure_to_altitude(pressure: f32, reference_pressure: f32) -> f32 {
+    44_330.0 * (1.0 - (pressure / reference_pressure).powf(1.0 / 5.255))
+}
+
+pub struct Barometer<I2C, DELAY>
+where
+    I2C: I2cBus,
+    DELAY: DelayMs,
+{
+    i2c: I2C,
+    delay: DELAY,
+    address: u8,
+    calibration: Calibration,
+}
+
+impl<I2C, DELAY> Barometer<I2C, DELAY>
+where
+    I2C: I2cBus,
+    DELAY: DelayMs,
+{
+    pub fn new(i2c: I2C, delay: DELAY, address: u8) -> RocketResult<Self> {
+        let mut baro = Self {
+            i2c,
+            delay,
+            address,
+            calibration: Calibration::default(),
+        };
+        baro.init()?;
+        Ok(baro)
+    }
+
+    fn init(&mut self) -> DriverResult<()> {
+        println!("[Driver:Baro] Initializing barometer at address 0x{:02X}", self.address);
+        self.write_register(REG_RESET, RESET_COMMAND)?;
+        self.delay.delay_ms(3); // Start-up time after reset
+
+        let chip_id = self.read_register(REG_CHIP_ID)?;
+        if chip_id != CHIP_ID {
+            return Err(DriverError::UnexpectedDevice(chip_id));
+        }
+
+        let mut raw = [0u8; 24];
+        self.i2c.write_read(self.address, &[REG_CALIB], &mut raw)?;
+        self.calibration = Calibration::from_bytes(&raw);
+        if self.calibration.dig_t1 == 0 || self.calibration.dig_p1 == 0 {
+            return Err(DriverError::InvalidData); // Blank NVM, compensation would be meaningless
+        }
+        println!("[Driver:Baro] Calibration: {:?}", self.calibration);
+
+        // CONFIG is only guaranteed to be written in sleep mode, so set it first
+        self.write_register(REG_CONFIG, CONFIG_VALUE)?;
+        self.write_register(REG_CTRL_MEAS, CTRL_MEAS_VALUE)?;
+        self.delay.delay_ms(45); // First conversion at this oversampling
+        println!("[Driver:Baro] Initialization complete.");
+        Ok(())
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> DriverResult<()> {
+        self.i2c.write(self.address, &[register, value])?;
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> DriverResult<u8> {
+        let mut buffer = [0u8; 1];
+        self.i2c.write_read(self.address, &[register], &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    // Read the latest conversion and compensate it with the trimming coefficients
+    pub fn read_data(&mut self) -> DriverResult<BaroData> {
+        let mut buffer = [0u8; 6];
+        // Burst read so pressure and temperature come from the same conversion
+        self.i2c.write_read(self.address, &[REG_PRESS_MSB], &mut buffer)?;
+
+        let adc_p = ((buffer[0] as i32) << 12) | ((buffer[1] as i32) << 4) | ((buffer[2] as i32) >> 4);
+        let adc_t = ((buffer[3] as i32) << 12) | ((buffer[4] as i32) << 4) | ((buffer[5] as i32) >> 4);
+        if adc_p == SKIPPED_SAMPLE || adc_t == SKIPPED_SAMPLE {
+            return Err(DriverError::SensorNotReady);
+        }
+
+        let t_fine = self.calibration.t_fine(adc_t);
+        let temperature = ((t_fine * 5 + 128) >> 8) as f32 / 100.0;
+        let pressure = self.calibration.pressure(adc_p, t_fine).ok_or(DriverError::InvalidData)? as f32 / 256.0;
+
+        Ok(BaroData { pressure, temperature })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::hal::dummy_hal;
+    use crate::hal::flight_sim::atmosphere;
+    use crate::hal::interface::{FullHardwareAbstraction, OutputPin};
+    use crate::kernel::sim::{self, ClockMode};
+    use std::time::Duration;
+
+    // Read the simulated BMP280 and check it against the standard atmosphere
+    // at the vehicle's true altitude
+    fn check_against_atmosphere(baro: &mut Barometer<dummy_hal::DummyI2c, dummy_hal::DummyDelay>) -> f32 {
+        let data = baro.read_data().unwrap();
+        let truth = dummy_hal::vehicle_state().position[2];
+        let (_, air_pressure, _) = atmosphere(truth);
+        // The pressure noise is 1.5 Pa rms; the vehicle moves on during the 50 us bus turnaround
+        assert!((data.pressure as f64 - air_pressure).abs() < 10.0, "{} Pa at {:.1} m", data.pressure, truth);
+        let altitude = pressure_to_altitude(data.pressure, SEA_LEVEL_PRESSURE);
+        assert!((altitude as f64 - truth).abs() < 1.5, "{:.1} m at {:.1} m", altitude, truth);
+        altitude
+    }
+
+    #[test]
+    fn pressure_to_altitude_follows_the_standard_atmosphere() {
+        assert_eq!(pressure_to_altitude(SEA_LEVEL_PRESSURE, SEA_LEVEL_PRESSURE), 0.0);
+        let (_, pressure_1000, _) = atmosphere(1000.0);
+        assert!((pressure_1000 - 89_875.0).abs() < 5.0, "{} Pa", pressure_1000);
+        let altitude = pressure_to_altitude(pressure_1000 as f32, SEA_LEVEL_PRESSURE);
+        assert!((altitude - 1000.0).abs() < 1.0, "{} m", altitude);
+        // Relative to a reference taken at 1000 m
+        assert_eq!(pressure_to_altitude(pressure_1000 as f32, pressure_1000 as f32), 0.0);
+    }
+
+    #[test]
+    fn reads_the_altitude_on_the_pad_and_in_flight() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_arm_switch(true);
+        let hal = dummy_hal::get_dummy_hal();
+        let mut baro = Barometer::new(hal.get_i2c_bus(0).unwrap(), hal.get_delay_timer(), config::DUMMY_BARO_ADDR).unwrap();
+
+        let pad = check_against_atmosphere(&mut baro);
+        assert!(pad.abs() < 1.5, "{} m", pad);
+
+        // Light the engine and read again a few seconds into the flight
+        for pin in [config::DUMMY_IGNITER_PIN, config::DUMMY_VALVE_PIN, config::DUMMY_VALVE_PIN + 1] {
+            hal.get_gpio_pin(pin).unwrap().set_high().unwrap();
+        }
+        sim::sleep(Duration::from_secs(4));
+        let raised = check_against_atmosphere(&mut baro);
+        assert!(raised > 300.0, "{} m", raised);
+    }
+
+    #[test]
+    fn compensation_matches_the_datasheet_example() {
+        // BMP280 datasheet 3.12: trimming values and readings of the worked example
+        let calibration = Calibration {
+            dig_t1: 27504,
+            dig_t2: 26435,
+            dig_t3: -1000,
+            dig_p1: 36477,
+            dig_p: [-10685, 3024, 2855, 140, -7, 15500, -14600, 6000],
+        };
+        let t_fine = calibration.t_fine(519888);
+        assert_eq!(t_fine, 128422);
+        assert_eq!((t_fine * 5 + 128) >> 8, 2508); // 25.08 degrees C
+        // The example's 100653.27 Pa is from the floating-point algorithm
+        let pressure = calibration.pressure(415148, t_fine).unwrap() as f32 / 256.0;
+        assert!((pressure - 100653.27).abs() < 0.05, "{} Pa", pressure);
+        assert_eq!(Calibration { dig_p1: 0, ..calibration }.pressure(415148, t_fine), None);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::hal::interface::{I2cBus, DelayMs};
+use crate::error::{DriverError, DriverResult}; // Driver-level Result (see error.rs)
+use crate::error::Result as RocketResult; // Using top-level Result
+use crate::kernel::sim::Instant;
+use crate::kernel::sync;
+
+// ADXL375 registers
+const DEVID: u8 = 0x00;
+const BW_RATE: u8 = 0x2C;
+const POWER_CTL: u8 = 0x2D;
+const DATA_FORMAT: u8 = 0x31;
+const DATAX0: u8 = 0x32; // X, Y, Z: 3 little-endian words
+
+const DEVICE_ID: u8 = 0xE5;
+const RATE_100HZ: u8 = 0x0A;
+const MEASURE: u8 = 0x08;
+const DATA_FORMAT_VALUE: u8 = 0x0B; // Right-justified, D1:D0 must be set on the ADXL375
+
+const GRAVITY: f32 = 9.81; // Standard gravity, m/s^2
+const SCALE: f32 = 0.049; // g per LSB
+// Output is 13 bits; the converter pins at the ends of this range
+const RAW_MAX: i16 = 4095;
+const RAW_MIN: i16 = -4096;
+
+// Largest measurable magnitude, m/s^2 (about +/-200 g)
+pub const FULL_SCALE: f32 = RAW_MAX as f32 * SCALE * GRAVITY;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HighGAccelData {
+    pub accel: [f32; 3], // m/s^2, same axes as the IMU
+    pub saturated: bool,
+    pub timestamp: Instant, // When the data registers were read
+}
+
+// Coarse (49 mg/LSB) but wide-range accelerometer, for when the IMU clips
+pub struct HighGAccel<I2C, DELAY>
+where
+    I2C: I2cBus,
+    DELAY: DelayMs,
+{
+    i2c: I2C,
+    delay: DELAY,
+    address: u8,
+}
+
+impl<I2C, DELAY> HighGAccel<I2C, DELAY>
+where
+    I2C: I2cBus,
+    DELAY: DelayMs,
+{
+    pub fn new(i2c: I2C, delay: DELAY, address: u8) -> RocketResult<Self> {
+        let mut accel = Self { i2c, delay, address };
+        accel.init()?;
+        Ok(accel)
+    }
+
+    fn init(&mut self) -> DriverResult<()> {
+        println!("[Driver:HighG] Initializing high-g accelerometer at address 0x{:02X}", self.address);
+        self.delay.delay_ms(2); // Power-up time
+
+        let device_id = self.read_register(DEVID)?;
+        if device_id != DEVICE_ID {
+            return Err(DriverError::UnexpectedDevice(device_id));
+        }
+
+        // Configure in standby, then start measuring
+        self.write_register(POWER_CTL, 0x00)?;
+        self.write_register(BW_RATE, RATE_100HZ)?;
+        self.write_register(DATA_FORMAT, DATA_FORMAT_VALUE)?;
+        self.write_register(POWER_CTL, MEASURE)?;
+        if self.read_register(POWER_CTL)? != MEASURE {
+            return Err(DriverError::ConfigurationFailed);
+        }
+        self.delay.delay_ms(11); // First sample at 100 Hz
+        println!("[Driver:HighG] Initialization complete.");
+        Ok(())
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> DriverResult<()> {
+        self.i2c.write(self.address, &[register, value])?;
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> DriverResult<u8> {
+        let mut buffer = [0u8; 1];
+        self.i2c.write_read(self.address, &[register], &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    pub fn read_data(&mut self) -> DriverResult<HighGAccelData> {
+        let mut buffer = [0u8; 6];
+        let timestamp = sync::get_time();
+        // Multi-byte read so all three axes come from the same sample
+        self.i2c.write_read(self.address, &[DATAX0], &mut buffer)?;
+
+        let raw = [
+            i16::from_le_bytes([buffer[0], buffer[1]]),
+            i16::from_le_bytes([buffer[2], buffer[3]]),
+            i16::from_le_bytes([buffer[4], buffer[5]]),
+        ];
+        let accel = raw.map(|r| r as f32 * SCALE * GRAVITY);
+        let saturated = raw.iter().any(|&r| r >= RAW_MAX || r <= RAW_MIN);
+
+        Ok(HighGAccelData { accel, saturated, timestamp })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::error::RocketError;
+    use crate::hal::dummy_hal::{self, DummyDelay, DummyI2c};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+    use std::time::Duration;
+
+    fn accel() -> RocketResult<HighGAccel<DummyI2c, DummyDelay>> {
+        let hal = dummy_hal::get_dummy_hal();
+        HighGAccel::new(hal.get_i2c_bus(0).unwrap(), hal.get_delay_timer(), config::DUMMY_HIGH_G_ADDR)
+    }
+
+    // Put the part in standby so the data registers hold `counts`
+    fn hold_counts(counts: [i16; 3]) {
+        dummy_hal::set_i2c_register(config::DUMMY_HIGH_G_ADDR, POWER_CTL, 0x00);
+        for (i, count) in counts.iter().enumerate() {
+            let [low, high] = count.to_le_bytes();
+            dummy_hal::set_i2c_register(config::DUMMY_HIGH_G_ADDR, DATAX0 + 2 * i as u8, low);
+            dummy_hal::set_i2c_register(config::DUMMY_HIGH_G_ADDR, DATAX0 + 2 * i as u8 + 1, high);
+        }
+    }
+
+    #[test]
+    fn rejects_an_unknown_device() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_i2c_register(config::DUMMY_HIGH_G_ADDR, DEVID, 0xE6);
+        assert!(matches!(accel(), Err(RocketError::Driver(DriverError::UnexpectedDevice(0xE6)))));
+    }
+
+    #[test]
+    fn converts_counts_at_49_mg_per_lsb() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let mut accel = accel().unwrap();
+
+        // 1 g on the pad, to within the part's coarse resolution
+        let mut mean = [0.0; 3];
+        for _ in 0..100 {
+            let data = accel.read_data().unwrap();
+            assert!(!data.saturated);
+            for (mean, accel) in mean.iter_mut().zip(data.accel) {
+                *mean += accel / 100.0;
+            }
+            sim::sleep(Duration::from_millis(10));
+        }
+        assert!((mean[2] - GRAVITY).abs() < 0.15, "{:?}", mean);
+        assert!(mean[0].abs() < 0.6 && mean[1].abs() < 0.15, "{:?}", mean);
+
+        hold_counts([20, -100, 1000]);
+        let data = accel.read_data().unwrap();
+        let expected = [20.0, -100.0, 1000.0].map(|counts: f32| counts * 0.049 * GRAVITY);
+        for (accel, expected) in data.accel.iter().zip(expected) {
+            assert!((accel - expected).abs() < 1e-3, "{:?}", data.accel);
+        }
+        assert!(!data.saturated);
+    }
+
+    #[test]
+    fn flags_a_reading_pinned_at_either_end() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let mut accel = accel().unwrap();
+
+        for (counts, saturated) in [
+            ([0, 0, RAW_MAX - 1], false),
+            ([0, 0, RAW_MAX], true),
+            ([RAW_MIN, 0, 0], true),
+            ([0, RAW_MIN + 1, 0], false),
+        ] {
+            hold_counts(counts);
+            assert_eq!(accel.read_data().unwrap().saturated, saturated, "{:?}", counts);
+        }
+        assert!((FULL_SCALE / GRAVITY - 200.0).abs() < 1.0); // About +/-200 g
+    }
+}
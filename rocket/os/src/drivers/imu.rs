@@ -1,42 +1,201 @@
-use crate::hal::interface::{I2cBus, DelayMs};
-use crate::error::{DriverError, DriverResult}; // Use specific Result type if defined, or top-level
+use crate::hal::interface::{I2cBus, InputPin, DelayMs};
+use crate::error::{DriverError, DriverResult}; // Driver-level Result (see error.rs)
 use crate::error::Result as RocketResult; // Using top-level Result
+use crate::drivers::imu_calibration::ImuCalibration;
+use crate::kernel::sim::Instant;
+use crate::kernel::sync;
+use std::time::Duration;
 
-const ACCEL_X_H: u8 = 0x3B; // Example register addresses
+// MPU6050/MPU9250 registers
+const SMPLRT_DIV: u8 = 0x19;
+const CONFIG: u8 = 0x1A;
+const GYRO_CONFIG: u8 = 0x1B;
+const ACCEL_CONFIG: u8 = 0x1C;
+const FIFO_EN: u8 = 0x23;
+const INT_PIN_CFG: u8 = 0x37;
+const INT_ENABLE: u8 = 0x38;
+const INT_STATUS: u8 = 0x3A;
+const ACCEL_X_H: u8 = 0x3B;
+const USER_CTRL: u8 = 0x6A;
+const PWR_MGMT_1: u8 = 0x6B;
+const FIFO_COUNT_H: u8 = 0x72;
+const FIFO_R_W: u8 = 0x74;
+const WHO_AM_I: u8 = 0x75;
 
-#[derive(Debug, Clone, Copy)]
+const DEVICE_RESET: u8 = 0x80;
+const CLKSEL_PLL_GYRO_X: u8 = 0x01; // Wake up, clocked from the X gyro PLL
+const FIFO_EN_ALL: u8 = 0xF8; // Temperature, gyro X/Y/Z and accel into the FIFO
+const USER_CTRL_FIFO_EN: u8 = 0x40;
+const USER_CTRL_FIFO_RESET: u8 = 0x04; // Only takes effect while the FIFO is disabled
+const LATCH_INT_EN: u8 = 0x20; // INT pin held high until INT_STATUS is read
+const INT_FIFO_OFLOW: u8 = 0x10; // INT_ENABLE / INT_STATUS bits
+const INT_DATA_RDY: u8 = 0x01;
+const SAMPLE_LEN: usize = 14; // Accel, temp, gyro: same layout in the FIFO as in the data registers
+// WHO_AM_I values of the parts this driver speaks to
+const KNOWN_DEVICE_IDS: [u8; 3] = [
+    0x68, // MPU6050
+    0x70, // MPU6500
+    0x71, // MPU9250
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelRange {
+    G2,
+    G4,
+    G8,
+    G16,
+}
+
+impl AccelRange {
+    fn bits(self) -> u8 {
+        self as u8 // AFS_SEL
+    }
+
+    // LSB per g
+    pub fn sensitivity(self) -> f32 {
+        16384.0 / (1 << self.bits()) as f32
+    }
+
+    // Largest measurable magnitude, m/s^2
+    pub fn full_scale(self) -> f32 {
+        (2 << self.bits()) as f32 * GRAVITY
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroRange {
+    Dps250,
+    Dps500,
+    Dps1000,
+    Dps2000,
+}
+
+impl GyroRange {
+    fn bits(self) -> u8 {
+        self as u8 // FS_SEL
+    }
+
+    // LSB per deg/s
+    pub fn sensitivity(self) -> f32 {
+        [131.0, 65.5, 32.8, 16.4][self as usize]
+    }
+
+    // Largest measurable rate, rad/s
+    pub fn full_scale(self) -> f32 {
+        ((250 << self.bits()) as f32).to_radians()
+    }
+}
+
+// Digital low-pass filter setting (DLPF_CFG), named by accelerometer bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DlpfBandwidth {
+    Hz260, // Filter off, gyro sampled at 8 kHz
+    Hz184,
+    Hz94,
+    Hz44,
+    Hz21,
+    Hz10,
+    Hz5,
+}
+
+impl DlpfBandwidth {
+    // Rate the sample-rate divider divides down from
+    fn gyro_output_rate(self) -> f32 {
+        if self == DlpfBandwidth::Hz260 { 8000.0 } else { 1000.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImuConfig {
+    pub accel_range: AccelRange,
+    pub gyro_range: GyroRange,
+    pub dlpf: DlpfBandwidth,
+    pub sample_rate_divider: u8, // Sample rate = gyro output rate / (1 + divider)
+}
+
+impl ImuConfig {
+    // Hz at which new samples appear in the data registers
+    pub fn sample_rate(&self) -> f32 {
+        self.dlpf.gyro_output_rate() / (1.0 + self.sample_rate_divider as f32)
+    }
+
+    // Time between samples
+    pub fn sample_period(&self) -> Duration {
+        Duration::from_secs_f64((1.0 + self.sample_rate_divider as f64) / self.dlpf.gyro_output_rate() as f64)
+    }
+}
+
+impl Default for ImuConfig {
+    // Wide ranges for boost, 44 Hz filtering, 100 Hz output
+    fn default() -> Self {
+        ImuConfig {
+            accel_range: AccelRange::G16,
+            gyro_range: GyroRange::Dps2000,
+            dlpf: DlpfBandwidth::Hz44,
+            sample_rate_divider: 9,
+        }
+    }
+}
+
+const GRAVITY: f32 = 9.81; // Standard gravity, m/s^2
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct ImuData {
     pub accel: [f32; 3], // m/s^2
     pub gyro: [f32; 3],  // rad/s
     pub temp: f32,       // degrees C
+    pub accel_saturated: bool, // Some accel axis is pinned at full scale (reading understates it)
+    pub gyro_saturated: bool,  // Some gyro axis is pinned at full scale
+}
+
+// One FIFO sample, stamped with when the sensor latched it
+#[derive(Debug, Clone, Copy)]
+pub struct ImuSample {
+    pub timestamp: Instant,
+    pub data: ImuData, // Calibrated
 }
 
-pub struct Imu<I2C, DELAY>
+pub struct Imu<I2C, INT, DELAY>
 where
     I2C: I2cBus,
+    INT: InputPin,
     DELAY: DelayMs,
 {
     i2c: I2C,
+    int_pin: INT, // Data-ready interrupt line
     delay: DELAY,
     address: u8,
-    // Add sensitivity/scaling factors based on datasheet/configuration
-    accel_scale: f32,
-    gyro_scale: f32,
+    config: ImuConfig,
+    // Sensitivities of the configured ranges
+    accel_scale: f32, // LSB/g
+    gyro_scale: f32,  // LSB/deg/s
+    calibration: ImuCalibration, // Applied to every sample by read_data/read_fifo
+    // FIFO sample clock: sample n after a FIFO reset was latched n + 1
+    // sample periods after the reset
+    fifo_epoch: Option<Instant>, // Time of the last FIFO reset (None while the FIFO is off)
+    fifo_samples: u64,           // Samples drained since then
+    fifo_overflows: u32,
 }
 
-impl<I2C, DELAY> Imu<I2C, DELAY>
+impl<I2C, INT, DELAY> Imu<I2C, INT, DELAY>
 where
     I2C: I2cBus,
+    INT: InputPin,
     DELAY: DelayMs,
 {
-    pub fn new(i2c: I2C, delay: DELAY, address: u8) -> RocketResult<Self> {
+    pub fn new(i2c: I2C, int_pin: INT, delay: DELAY, address: u8, config: ImuConfig) -> RocketResult<Self> {
         let mut imu = Self {
             i2c,
+            int_pin,
             delay,
             address,
-            // Example scales (replace with actual values for a specific IMU like MPU6050/9250)
-            accel_scale: 16384.0, // LSB/g for +/- 2g range
-            gyro_scale: 131.0,    // LSB/deg/s for +/- 250 deg/s range
+            config,
+            accel_scale: config.accel_range.sensitivity(),
+            gyro_scale: config.gyro_range.sensitivity(),
+            calibration: ImuCalibration::default(),
+            fifo_epoch: None,
+            fifo_samples: 0,
+            fifo_overflows: 0,
         };
         imu.init()?;
         Ok(imu)
@@ -46,24 +205,93 @@ where
         println!("[Driver:IMU] Initializing IMU at address 0x{:02X}", self.address);
         self.delay.delay_ms(100); // Wait for sensor startup
 
-        // Example: Wake up sensor (specific to IMU model)
-        // self.write_register(0x6B, 0x00)?; // PWR_MGMT_1: Wake up
+        // Start from a known state: reset every register, wait for it to finish
+        self.write_register(PWR_MGMT_1, DEVICE_RESET)?;
+        self.delay.delay_ms(100);
 
-        // Example: Read WHO_AM_I register to verify connection
-        let who_am_i = self.read_register(0x75)?; // WHO_AM_I register
+        let who_am_i = self.read_register(WHO_AM_I)?;
         println!("[Driver:IMU] WHO_AM_I = 0x{:02X}", who_am_i);
-        // Check if who_am_i matches expected value for the sensor
-        // if who_am_i != 0x68 { /* return Err(DriverError::UnexpectedDevice) */ } // Example check
+        if !KNOWN_DEVICE_IDS.contains(&who_am_i) {
+            return Err(DriverError::UnexpectedDevice(who_am_i));
+        }
 
-        // Configure accelerometer and gyroscope ranges/settings here...
-        // self.write_register(0x1B, 0x00)?; // Gyro Config: +/- 250 deg/s
-        // self.write_register(0x1C, 0x00)?; // Accel Config: +/- 2g
+        // Wake up (the part resets into sleep mode) on the more stable gyro clock
+        self.write_register(PWR_MGMT_1, CLKSEL_PLL_GYRO_X)?;
+        self.delay.delay_ms(10);
 
+        self.configure(self.config)?;
         self.delay.delay_ms(50);
         println!("[Driver:IMU] Initialization complete.");
         Ok(())
     }
 
+    pub fn config(&self) -> ImuConfig {
+        self.config
+    }
+
+    pub fn calibration(&self) -> &ImuCalibration {
+        &self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: ImuCalibration) {
+        self.calibration = calibration;
+    }
+
+    // Times the FIFO overflowed and samples were lost
+    pub fn fifo_overflows(&self) -> u32 {
+        self.fifo_overflows
+    }
+
+    // On-pad calibration: average `samples` readings `interval_ms` apart
+    // while the vehicle is stationary, update the biases of the current
+    // calibration and start applying the result.
+    pub fn calibrate(&mut self, samples: usize, interval_ms: u32) -> DriverResult<ImuCalibration> {
+        println!("[Driver:IMU] Calibrating from {} stationary samples...", samples);
+        let mut raw = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            raw.push(self.read_uncompensated()?);
+            self.delay.delay_ms(interval_ms);
+        }
+        let calibration = self.calibration.estimate(&raw)?;
+        println!(
+            "[Driver:IMU] Calibrated at {:.1} C: gyro bias {:.4?} rad/s, accel offset {:.3?} m/s^2",
+            calibration.reference_temp, calibration.gyro_bias, calibration.accel_offset
+        );
+        self.calibration = calibration;
+        Ok(calibration)
+    }
+
+    // Apply ranges, filter and sample rate; every register is read back to
+    // catch a device that silently ignored the write.
+    pub fn configure(&mut self, config: ImuConfig) -> DriverResult<()> {
+        let settings = [
+            (SMPLRT_DIV, config.sample_rate_divider),
+            (CONFIG, config.dlpf as u8),
+            (GYRO_CONFIG, config.gyro_range.bits() << 3),
+            (ACCEL_CONFIG, config.accel_range.bits() << 3),
+        ];
+        for (register, value) in settings {
+            self.write_register(register, value)?;
+            if self.read_register(register)? != value {
+                return Err(DriverError::ConfigurationFailed);
+            }
+        }
+        self.config = config;
+        if self.fifo_epoch.is_some() {
+            self.reset_fifo()?; // Queued samples were taken at the old rate and scale
+        }
+        self.accel_scale = config.accel_range.sensitivity();
+        self.gyro_scale = config.gyro_range.sensitivity();
+        println!(
+            "[Driver:IMU] Configured: {:?}, {:?}, DLPF {:?}, {:.0} Hz",
+            config.accel_range,
+            config.gyro_range,
+            config.dlpf,
+            config.sample_rate()
+        );
+        Ok(())
+    }
+
     fn write_register(&mut self, register: u8, value: u8) -> DriverResult<()> {
         self.i2c.write(self.address, &[register, value])?;
         Ok(())
@@ -75,11 +303,85 @@ where
         Ok(buffer[0])
     }
 
+    // Queue every sample into the FIFO and raise the interrupt line when
+    // one is ready (or the FIFO overflowed). Samples are then collected in
+    // batches with read_fifo.
+    pub fn start_fifo(&mut self) -> DriverResult<()> {
+        self.write_register(INT_PIN_CFG, LATCH_INT_EN)?;
+        self.write_register(INT_ENABLE, INT_FIFO_OFLOW | INT_DATA_RDY)?;
+        self.write_register(FIFO_EN, FIFO_EN_ALL)?;
+        self.reset_fifo()?;
+        println!("[Driver:IMU] FIFO started at {:.0} Hz", self.config.sample_rate());
+        Ok(())
+    }
+
+    // Empty the FIFO and restart the sample count from now
+    fn reset_fifo(&mut self) -> DriverResult<()> {
+        self.write_register(USER_CTRL, USER_CTRL_FIFO_RESET)?;
+        self.fifo_epoch = Some(sync::get_time());
+        self.fifo_samples = 0;
+        self.write_register(USER_CTRL, USER_CTRL_FIFO_EN)?;
+        self.read_register(INT_STATUS)?; // Release the latched interrupt
+        Ok(())
+    }
+
+    // Drain every complete sample queued since the last call, oldest first.
+    // Returns nothing if the data-ready line is low. After an overflow the
+    // FIFO no longer starts on a sample boundary, so it is reset and the
+    // queued samples are dropped (counted in fifo_overflows).
+    pub fn read_fifo(&mut self) -> DriverResult<Vec<ImuSample>> {
+        let Some(epoch) = self.fifo_epoch else {
+            return Err(DriverError::SensorNotReady); // start_fifo not called
+        };
+        if self.int_pin.is_low()? {
+            return Ok(Vec::new());
+        }
+        let status = self.read_register(INT_STATUS)?;
+        if status & INT_FIFO_OFLOW != 0 {
+            self.fifo_overflows += 1;
+            println!("[Driver:IMU] FIFO overflow, dropping queued samples (overflow #{})", self.fifo_overflows);
+            self.reset_fifo()?;
+            return Ok(Vec::new());
+        }
+
+        let mut count = [0u8; 2];
+        self.i2c.write_read(self.address, &[FIFO_COUNT_H], &mut count)?;
+        let available = u16::from_be_bytes(count) as usize / SAMPLE_LEN;
+        if available == 0 {
+            return Ok(Vec::new());
+        }
+        // One burst for the whole batch; FIFO_R_W does not auto-increment
+        let mut buffer = vec![0u8; available * SAMPLE_LEN];
+        self.i2c.write_read(self.address, &[FIFO_R_W], &mut buffer)?;
+
+        let period = self.config.sample_period();
+        let mut samples = Vec::with_capacity(available);
+        for chunk in buffer.chunks_exact(SAMPLE_LEN) {
+            let mut data = self.parse_sample(chunk);
+            (data.accel, data.gyro) = self.calibration.apply(data.accel, data.gyro, data.temp);
+            self.fifo_samples += 1;
+            let timestamp = epoch + period * self.fifo_samples as u32;
+            samples.push(ImuSample { timestamp, data });
+        }
+        Ok(samples)
+    }
+
+    // Calibrated sample, with temperature-dependent biases removed
     pub fn read_data(&mut self) -> DriverResult<ImuData> {
-        let mut buffer = [0u8; 14]; // 6 accel bytes, 2 temp bytes, 6 gyro bytes
+        let mut data = self.read_uncompensated()?;
+        (data.accel, data.gyro) = self.calibration.apply(data.accel, data.gyro, data.temp);
+        Ok(data)
+    }
+
+    // Sample converted with the nominal scale factors only
+    fn read_uncompensated(&mut self) -> DriverResult<ImuData> {
+        let mut buffer = [0u8; SAMPLE_LEN]; // 6 accel bytes, 2 temp bytes, 6 gyro bytes
         // Read all sensor data registers starting from ACCEL_X_H
         self.i2c.write_read(self.address, &[ACCEL_X_H], &mut buffer)?;
+        Ok(self.parse_sample(&buffer))
+    }
 
+    fn parse_sample(&self, buffer: &[u8]) -> ImuData {
         // Parse data (assuming Big Endian format common in IMUs)
         let accel_raw = [
             i16::from_be_bytes([buffer[0], buffer[1]]),
@@ -94,7 +396,7 @@ where
         ];
 
         // Convert raw data to physical units
-        let g = 9.81; // Standard gravity
+        let g = GRAVITY;
         let accel = [
             accel_raw[0] as f32 / self.accel_scale * g,
             accel_raw[1] as f32 / self.accel_scale * g,
@@ -108,9 +410,205 @@ where
             (gyro_raw[2] as f32 / self.gyro_scale).to_radians(),
         ];
 
-        Ok(ImuData { accel, gyro, temp })
+        ImuData {
+            accel,
+            gyro,
+            temp,
+            accel_saturated: accel_raw.iter().any(|&raw| is_saturated(raw)),
+            gyro_saturated: gyro_raw.iter().any(|&raw| is_saturated(raw)),
+        }
     }
 }
 
-// Define DriverResult if you want more specific driver errors
-type DriverResult<T> = std::result::Result<T, DriverError>;
+// The converters clamp to the ends of the i16 range when out of range
+fn is_saturated(raw: i16) -> bool {
+    raw == i16::MAX || raw == i16::MIN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::error::RocketError;
+    use crate::hal::dummy_hal;
+    use crate::hal::interface::{FullHardwareAbstraction, OutputPin};
+    use crate::kernel::sim::{self, ClockMode};
+
+    fn imu(config: ImuConfig) -> RocketResult<Imu<dummy_hal::DummyI2c, dummy_hal::DummyPin, dummy_hal::DummyDelay>> {
+        let hal = dummy_hal::get_dummy_hal();
+        Imu::new(
+            hal.get_i2c_bus(0).unwrap(),
+            hal.get_gpio_pin(config::DUMMY_IMU_INT_PIN).unwrap(),
+            hal.get_delay_timer(),
+            config::DUMMY_IMU_ADDR,
+            config,
+        )
+    }
+
+    // Mean of the next `n` samples latched into the data registers (the
+    // registers still hold a sample taken before any reconfiguration)
+    fn average(imu: &mut Imu<dummy_hal::DummyI2c, dummy_hal::DummyPin, dummy_hal::DummyDelay>, n: usize) -> ImuData {
+        let mut mean = ImuData::default();
+        for _ in 0..n {
+            sim::sleep(imu.config().sample_period());
+            let data = imu.read_data().unwrap();
+            for axis in 0..3 {
+                mean.accel[axis] += data.accel[axis] / n as f32;
+                mean.gyro[axis] += data.gyro[axis] / n as f32;
+            }
+        }
+        mean
+    }
+
+    fn norm(v: [f32; 3]) -> f32 {
+        v.iter().map(|x| x * x).sum::<f32>().sqrt()
+    }
+
+    #[test]
+    fn rejects_an_unknown_device() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_i2c_register(config::DUMMY_IMU_ADDR, WHO_AM_I, 0x12);
+        let result = imu(ImuConfig::default());
+        assert!(matches!(result, Err(RocketError::Driver(DriverError::UnexpectedDevice(0x12)))));
+
+        // Each supported part is accepted
+        for id in KNOWN_DEVICE_IDS {
+            dummy_hal::set_i2c_register(config::DUMMY_IMU_ADDR, WHO_AM_I, id);
+            assert!(imu(ImuConfig::default()).is_ok());
+        }
+    }
+
+    #[test]
+    fn reading_at_rest_is_one_g_in_every_range() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let mut imu = imu(ImuConfig { accel_range: AccelRange::G2, gyro_range: GyroRange::Dps250, ..ImuConfig::default() }).unwrap();
+        imu.calibrate(50, 10).unwrap();
+
+        // The calibration is in physical units, so it carries over to any range
+        let ranges = [
+            (AccelRange::G2, GyroRange::Dps250, 16384.0, 131.0),
+            (AccelRange::G4, GyroRange::Dps500, 8192.0, 65.5),
+            (AccelRange::G8, GyroRange::Dps1000, 4096.0, 32.8),
+            (AccelRange::G16, GyroRange::Dps2000, 2048.0, 16.4),
+        ];
+        for (accel_range, gyro_range, accel_scale, gyro_scale) in ranges {
+            imu.configure(ImuConfig { accel_range, gyro_range, ..imu.config() }).unwrap();
+            assert_eq!((imu.accel_scale, imu.gyro_scale), (accel_scale, gyro_scale));
+            let rest = average(&mut imu, 20);
+            assert!((norm(rest.accel) - GRAVITY).abs() < 0.05, "{:?}: {:?}", accel_range, rest.accel);
+            assert!(norm(rest.gyro) < 0.005, "{:?}: {:?}", gyro_range, rest.gyro);
+        }
+    }
+
+    #[test]
+    fn device_follows_the_filter_and_sample_rate_divider() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let mut imu = imu(ImuConfig::default()).unwrap();
+        imu.start_fifo().unwrap();
+
+        // (filter, divider, Hz): the filter sets the rate the divider divides down from
+        let settings = [
+            (DlpfBandwidth::Hz44, 9, 100.0),
+            (DlpfBandwidth::Hz44, 4, 200.0),
+            (DlpfBandwidth::Hz260, 39, 200.0),
+            (DlpfBandwidth::Hz260, 15, 500.0),
+        ];
+        for (dlpf, sample_rate_divider, rate) in settings {
+            let config = ImuConfig { dlpf, sample_rate_divider, ..ImuConfig::default() };
+            assert_eq!(config.sample_rate(), rate);
+            imu.configure(config).unwrap(); // Resets the FIFO
+            let window = Duration::from_millis(100);
+            sim::sleep(window);
+            let samples = imu.read_fifo().unwrap();
+            // As many samples as the device latched at this rate, none early
+            let expected = (window.as_secs_f32() * rate) as usize;
+            assert!((expected - 1..=expected).contains(&samples.len()), "{:?}/{}: {} samples", dlpf, sample_rate_divider, samples.len());
+            assert_contiguous(&samples, config.sample_period());
+            assert!(samples.last().unwrap().timestamp <= sim::now());
+        }
+    }
+
+    #[test]
+    fn flags_an_axis_pinned_at_full_scale() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_arm_switch(true);
+        let mut imu = imu(ImuConfig { accel_range: AccelRange::G2, ..ImuConfig::default() }).unwrap();
+        let on_pad = average(&mut imu, 1);
+        assert!(!on_pad.accel_saturated && !imu.read_data().unwrap().gyro_saturated);
+
+        // Boost is over 5 g along the long axis
+        let hal = dummy_hal::get_dummy_hal();
+        for pin in [config::DUMMY_IGNITER_PIN, config::DUMMY_VALVE_PIN, config::DUMMY_VALVE_PIN + 1] {
+            hal.get_gpio_pin(pin).unwrap().set_high().unwrap();
+        }
+        sim::sleep(Duration::from_secs(1));
+        let boost = imu.read_data().unwrap();
+        assert!(boost.accel_saturated, "{:?}", boost.accel);
+        assert!((boost.accel[2] - AccelRange::G2.full_scale()).abs() < 0.01, "{:?}", boost.accel);
+        assert!(!boost.gyro_saturated);
+
+        // The wider range takes it
+        imu.configure(ImuConfig { accel_range: AccelRange::G16, ..imu.config() }).unwrap();
+        let boost = average(&mut imu, 1);
+        assert!(!boost.accel_saturated && boost.accel[2] > 5.0 * GRAVITY, "{:?}", boost.accel);
+    }
+
+    // Timestamps one sample period apart, oldest first
+    fn assert_contiguous(samples: &[ImuSample], period: Duration) {
+        for pair in samples.windows(2) {
+            assert_eq!(pair[1].timestamp - pair[0].timestamp, period);
+        }
+    }
+
+    #[test]
+    fn fifo_overflow_drops_the_queue_and_restarts_the_sample_clock() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let hal = dummy_hal::get_dummy_hal();
+        // 100 Hz: the 1024-byte FIFO holds 73 samples, 730 ms
+        let config = ImuConfig::default();
+        let period = config.sample_period();
+        let mut imu = Imu::new(
+            hal.get_i2c_bus(0).unwrap(),
+            hal.get_gpio_pin(config::DUMMY_IMU_INT_PIN).unwrap(),
+            hal.get_delay_timer(),
+            config::DUMMY_IMU_ADDR,
+            config,
+        )
+        .unwrap();
+        imu.start_fifo().unwrap();
+
+        // Keeping up
+        sim::sleep(Duration::from_millis(200));
+        let samples = imu.read_fifo().unwrap();
+        assert!((19..=20).contains(&samples.len()), "{} samples", samples.len());
+        assert_contiguous(&samples, period);
+        assert_eq!(imu.fifo_overflows(), 0);
+
+        // Stall for 100 samples
+        sim::sleep(Duration::from_secs(1));
+        let stalled_at = sim::now();
+        assert!(imu.read_fifo().unwrap().is_empty());
+        let reset_at = sim::now();
+        assert_eq!(imu.fifo_overflows(), 1);
+
+        // The sample clock restarts from the reset, not from the dropped samples
+        sim::sleep(Duration::from_millis(100));
+        let samples = imu.read_fifo().unwrap();
+        assert!((9..=10).contains(&samples.len()), "{} samples", samples.len());
+        assert_contiguous(&samples, period);
+        let first = samples[0].timestamp;
+        assert!(first >= stalled_at + period && first <= reset_at + period, "{:?}", first);
+        assert!(samples.last().unwrap().timestamp <= sim::now());
+        assert_eq!(imu.fifo_overflows(), 1);
+    }
+}
@@ -0,0 +1,312 @@
+// IMU calibration record: gyro bias, accelerometer offset and correction
+// transform, with linear temperature dependence of the biases.
+//
+// The pad routine (`estimate`) can only observe what a stationary vehicle
+// reveals: gyro bias, the accelerometer offset along gravity and the
+// direction of gravity. The correction transform (scale and axis
+// misalignment) and, unless the temperature moved during sampling, the
+// temperature coefficients come from a bench calibration and are carried
+// over from the previous record.
+use crate::drivers::imu::ImuData;
+use crate::error::{DriverError, DriverResult, Result, RocketError};
+use std::fs;
+
+const GRAVITY: f32 = 9.81; // m/s^2, matches the IMU driver's scaling
+const MAGIC: [u8; 4] = *b"IMUC";
+const VERSION: u8 = 1;
+const FIELD_COUNT: usize = 25; // f32 fields in the serialized record
+pub const RECORD_LEN: usize = MAGIC.len() + 1 + 4 * FIELD_COUNT + 2; // + Fletcher-16
+
+// Limits for accepting the samples as stationary
+const MAX_GYRO_STD_DEV: f32 = 0.02; // rad/s
+const MAX_ACCEL_STD_DEV: f32 = 0.3; // m/s^2, of the magnitude
+const MAX_GRAVITY_ERROR: f32 = 1.5; // m/s^2, |mean magnitude - g|
+// Temperature span needed before fitting temperature coefficients
+const MIN_TEMP_SPAN: f32 = 2.0; // degrees C
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuCalibration {
+    pub gyro_bias: [f32; 3],           // rad/s at `reference_temp`
+    pub gyro_temp_coeff: [f32; 3],     // rad/s per degree C
+    pub accel_offset: [f32; 3],        // m/s^2 at `reference_temp`
+    pub accel_temp_coeff: [f32; 3],    // m/s^2 per degree C
+    pub accel_transform: [[f32; 3]; 3], // Scale/misalignment, applied after the offset
+    pub reference_temp: f32,           // degrees C
+    pub gravity_direction: [f32; 3],   // Unit "up" in the body frame, measured on the pad
+}
+
+impl Default for ImuCalibration {
+    // No correction at all
+    fn default() -> Self {
+        ImuCalibration {
+            gyro_bias: [0.0; 3],
+            gyro_temp_coeff: [0.0; 3],
+            accel_offset: [0.0; 3],
+            accel_temp_coeff: [0.0; 3],
+            accel_transform: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            reference_temp: 25.0,
+            gravity_direction: [0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl ImuCalibration {
+    // Start from temperature coefficients characterised on the bench
+    pub fn with_temp_coeffs(gyro_temp_coeff: [f32; 3], accel_temp_coeff: [f32; 3]) -> Self {
+        ImuCalibration { gyro_temp_coeff, accel_temp_coeff, ..Self::default() }
+    }
+
+    // Correct one raw sample (physical units) taken at `temp`
+    pub fn apply(&self, accel: [f32; 3], gyro: [f32; 3], temp: f32) -> ([f32; 3], [f32; 3]) {
+        let dt = temp - self.reference_temp;
+        let mut gyro_out = [0.0; 3];
+        let mut offset_removed = [0.0; 3];
+        for axis in 0..3 {
+            gyro_out[axis] = gyro[axis] - (self.gyro_bias[axis] + self.gyro_temp_coeff[axis] * dt);
+            offset_removed[axis] = accel[axis] - (self.accel_offset[axis] + self.accel_temp_coeff[axis] * dt);
+        }
+        let m = &self.accel_transform;
+        let accel_out = [0, 1, 2].map(|i| m[i][0] * offset_removed[0] + m[i][1] * offset_removed[1] + m[i][2] * offset_removed[2]);
+        (accel_out, gyro_out)
+    }
+
+    // Re-estimate the biases from uncompensated samples of a stationary IMU,
+    // keeping whatever this record knows that the samples can't tell.
+    pub fn estimate(&self, samples: &[ImuData]) -> DriverResult<ImuCalibration> {
+        if samples.len() < 2 {
+            return Err(DriverError::CalibrationFailed("too few samples".to_string()));
+        }
+        let n = samples.len() as f32;
+        let mean = |f: &dyn Fn(&ImuData) -> f32| samples.iter().map(f).sum::<f32>() / n;
+        let std_dev = |f: &dyn Fn(&ImuData) -> f32, m: f32| (samples.iter().map(|s| (f(s) - m).powi(2)).sum::<f32>() / n).sqrt();
+
+        let magnitude = |s: &ImuData| norm(s.accel);
+        let mean_magnitude = mean(&magnitude);
+        let magnitude_std = std_dev(&magnitude, mean_magnitude);
+        if magnitude_std > MAX_ACCEL_STD_DEV || (mean_magnitude - GRAVITY).abs() > MAX_GRAVITY_ERROR {
+            return Err(DriverError::CalibrationFailed(format!(
+                "not stationary: |accel| {:.2} +/- {:.2} m/s^2",
+                mean_magnitude, magnitude_std
+            )));
+        }
+
+        let temp = |s: &ImuData| s.temp;
+        let mean_temp = mean(&temp);
+        let (min_temp, max_temp) = samples
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), s| (lo.min(s.temp), hi.max(s.temp)));
+        let fit_temperature = max_temp - min_temp >= MIN_TEMP_SPAN;
+        let temp_variance = std_dev(&temp, mean_temp).powi(2);
+
+        let mut result = *self;
+        result.reference_temp = mean_temp;
+        let mut accel_mean = [0.0; 3];
+        for (axis, accel_mean) in accel_mean.iter_mut().enumerate() {
+            let gyro = |s: &ImuData| s.gyro[axis];
+            let gyro_mean = mean(&gyro);
+            let gyro_std = std_dev(&gyro, gyro_mean);
+            if gyro_std > MAX_GYRO_STD_DEV {
+                return Err(DriverError::CalibrationFailed(format!(
+                    "not stationary: gyro axis {} +/- {:.3} rad/s",
+                    axis, gyro_std
+                )));
+            }
+            let accel = |s: &ImuData| s.accel[axis];
+            *accel_mean = mean(&accel);
+            result.gyro_bias[axis] = gyro_mean;
+
+            if fit_temperature {
+                // Least-squares slope against temperature
+                let covariance = |f: &dyn Fn(&ImuData) -> f32, m: f32| {
+                    samples.iter().map(|s| (f(s) - m) * (s.temp - mean_temp)).sum::<f32>() / n
+                };
+                result.gyro_temp_coeff[axis] = covariance(&gyro, gyro_mean) / temp_variance;
+                result.accel_temp_coeff[axis] = covariance(&accel, *accel_mean) / temp_variance;
+            }
+        }
+
+        // Only the error along gravity is observable: whatever the mean
+        // reading has beyond 1 g in its own direction is offset.
+        let up = scale(accel_mean, 1.0 / norm(accel_mean));
+        for axis in 0..3 {
+            result.accel_offset[axis] = accel_mean[axis] - GRAVITY * up[axis];
+        }
+        let (corrected, _) = result.apply(accel_mean, [0.0; 3], mean_temp);
+        result.gravity_direction = scale(corrected, 1.0 / norm(corrected));
+        Ok(result)
+    }
+
+    // Fixed little-endian layout: magic, version, fields, Fletcher-16
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RECORD_LEN);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        for value in self.fields() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let checksum = fletcher16(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> DriverResult<Self> {
+        if bytes.len() != RECORD_LEN || bytes[..4] != MAGIC || bytes[4] != VERSION {
+            return Err(DriverError::InvalidData);
+        }
+        let (body, checksum) = bytes.split_at(RECORD_LEN - 2);
+        if fletcher16(body) != u16::from_le_bytes([checksum[0], checksum[1]]) {
+            return Err(DriverError::InvalidData);
+        }
+        let v: Vec<f32> = body[5..].chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        let vec3 = |i: usize| [v[i], v[i + 1], v[i + 2]];
+        Ok(ImuCalibration {
+            gyro_bias: vec3(0),
+            gyro_temp_coeff: vec3(3),
+            accel_offset: vec3(6),
+            accel_temp_coeff: vec3(9),
+            accel_transform: [vec3(12), vec3(15), vec3(18)],
+            reference_temp: v[21],
+            gravity_direction: vec3(22),
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, self.to_bytes())
+            .map_err(|e| RocketError::Configuration(format!("Failed to save IMU calibration to {}: {}", path, e)))
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let bytes = fs::read(path)
+            .map_err(|e| RocketError::Configuration(format!("Failed to read IMU calibration {}: {}", path, e)))?;
+        Ok(Self::from_bytes(&bytes)?)
+    }
+
+    fn fields(&self) -> Vec<f32> {
+        let mut fields = Vec::with_capacity(FIELD_COUNT);
+        fields.extend_from_slice(&self.gyro_bias);
+        fields.extend_from_slice(&self.gyro_temp_coeff);
+        fields.extend_from_slice(&self.accel_offset);
+        fields.extend_from_slice(&self.accel_temp_coeff);
+        for row in &self.accel_transform {
+            fields.extend_from_slice(row);
+        }
+        fields.push(self.reference_temp);
+        fields.extend_from_slice(&self.gravity_direction);
+        fields
+    }
+}
+
+pub(crate) fn fletcher16(bytes: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for &b in bytes {
+        sum1 = (sum1 + b as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+fn norm(v: [f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+fn scale(v: [f32; 3], s: f32) -> [f32; 3] {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_record() -> ImuCalibration {
+        ImuCalibration {
+            gyro_bias: [0.01, -0.008, 0.005],
+            gyro_temp_coeff: [0.0004, -0.0003, 0.0002],
+            accel_offset: [0.15, -0.1, 0.2],
+            accel_temp_coeff: [0.004, 0.003, -0.005],
+            accel_transform: [[1.01, 0.002, 0.0], [-0.001, 0.99, 0.003], [0.0, 0.004, 1.0]],
+            reference_temp: 21.5,
+            gravity_direction: [0.01, -0.02, 0.9997],
+        }
+    }
+
+    // Replace the checksum after editing a record, so only the edit is wrong
+    fn reseal(bytes: &mut [u8]) {
+        let checksum = fletcher16(&bytes[..RECORD_LEN - 2]);
+        bytes[RECORD_LEN - 2..].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn fletcher16_check_value() {
+        assert_eq!(fletcher16(b"abcde"), 0xC8F0);
+        assert_eq!(fletcher16(b"abcdef"), 0x2057);
+    }
+
+    #[test]
+    fn record_round_trips() {
+        let bytes = sample_record().to_bytes();
+        assert_eq!(bytes.len(), RECORD_LEN);
+        assert_eq!(ImuCalibration::from_bytes(&bytes).unwrap(), sample_record());
+    }
+
+    #[test]
+    fn damaged_records_are_rejected() {
+        let bytes = sample_record().to_bytes();
+        assert_eq!(ImuCalibration::from_bytes(&bytes[..RECORD_LEN - 1]), Err(DriverError::InvalidData));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(ImuCalibration::from_bytes(&longer), Err(DriverError::InvalidData));
+
+        let mut other_version = bytes.clone();
+        other_version[4] = VERSION + 1;
+        reseal(&mut other_version);
+        assert_eq!(ImuCalibration::from_bytes(&other_version), Err(DriverError::InvalidData));
+        let mut other_magic = bytes.clone();
+        other_magic[0] = b'X';
+        reseal(&mut other_magic);
+        assert_eq!(ImuCalibration::from_bytes(&other_magic), Err(DriverError::InvalidData));
+
+        // Any corrupted byte in the fields or the checksum itself
+        for i in 5..RECORD_LEN {
+            let mut corrupt = bytes.clone();
+            corrupt[i] ^= 0x10;
+            assert_eq!(ImuCalibration::from_bytes(&corrupt), Err(DriverError::InvalidData), "byte {}", i);
+        }
+    }
+
+    #[test]
+    fn estimate_fits_biases_and_temperature_coefficients() {
+        let truth = sample_record();
+        let up = [0.0, 0.0, GRAVITY];
+        // Stationary, level, warming by 5 degrees while sampling
+        let samples: Vec<ImuData> = (0..200)
+            .map(|i| {
+                let temp = 20.0 + 5.0 * i as f32 / 199.0;
+                let dt = temp - truth.reference_temp;
+                ImuData {
+                    accel: [0, 1, 2].map(|a| up[a] + truth.accel_offset[a] + truth.accel_temp_coeff[a] * dt),
+                    gyro: [0, 1, 2].map(|a| truth.gyro_bias[a] + truth.gyro_temp_coeff[a] * dt),
+                    temp,
+                    ..ImuData::default()
+                }
+            })
+            .collect();
+        let estimated = ImuCalibration::default().estimate(&samples).unwrap();
+        for axis in 0..3 {
+            assert!((estimated.gyro_temp_coeff[axis] - truth.gyro_temp_coeff[axis]).abs() < 1e-5);
+            assert!((estimated.accel_temp_coeff[axis] - truth.accel_temp_coeff[axis]).abs() < 1e-4);
+        }
+        // Corrects a sample at a temperature it never saw
+        let dt = 35.0 - truth.reference_temp;
+        let accel = [0, 1, 2].map(|a| up[a] + truth.accel_offset[a] + truth.accel_temp_coeff[a] * dt);
+        let gyro = [0, 1, 2].map(|a| truth.gyro_bias[a] + truth.gyro_temp_coeff[a] * dt);
+        let (accel, gyro) = estimated.apply(accel, gyro, 35.0);
+        // Sideways offsets look the same as a tilted pad: they end up in the
+        // gravity direction rather than the offset
+        assert!((norm(accel) - GRAVITY).abs() < 0.01, "{:?}", accel);
+        for axis in 0..3 {
+            assert!((accel[axis] / norm(accel) - estimated.gravity_direction[axis]).abs() < 1e-3, "{:?}", accel);
+            assert!(gyro[axis].abs() < 1e-4, "{:?}", gyro);
+        }
+        assert!(ImuCalibration::default().estimate(&samples[..1]).is_err());
+    }
+}
@@ -1,3 +1,9 @@
 pub mod imu;
+pub mod imu_calibration;
 pub mod valve;
+pub mod pyro;
+pub mod throttle_valve;
+pub mod pressure_transducer;
 pub mod radio;
+pub mod barometer;
+pub mod high_g_accel;
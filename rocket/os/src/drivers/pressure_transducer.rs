@@ -0,0 +1,99 @@
+// Ratiometric pressure transducer on an ADC channel, read as Pa gauge.
+//
+// Each unit comes with its own two-point calibration from the bench: ADC
+// counts at zero pressure and at full scale. The output never reaches the
+// ends of the converter's range, so readings well outside the calibrated
+// span mean a broken wire or a failed sensor rather than a pressure.
+use crate::hal::interface::Adc;
+use crate::error::{DriverError, HalError};
+use crate::error::Result as RocketResult;
+
+// Readings this far (as a fraction of the calibrated span) beyond either
+// end of the span are faults
+const RANGE_MARGIN: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PressureCalibration {
+    pub zero_counts: u16,         // ADC counts at 0 Pa gauge
+    pub full_scale_counts: u16,   // ADC counts at full_scale_pressure
+    pub full_scale_pressure: f32, // Pa gauge
+}
+
+pub struct PressureTransducer<ADC>
+where
+    ADC: Adc<u16, Error = HalError>,
+{
+    adc: ADC,
+    channel: u8,
+    calibration: PressureCalibration,
+}
+
+impl<ADC> PressureTransducer<ADC>
+where
+    ADC: Adc<u16, Error = HalError>,
+{
+    pub fn new(adc: ADC, channel: u8, calibration: PressureCalibration) -> RocketResult<Self> {
+        println!("[Driver:PressureTransducer] Initializing transducer on ADC channel {}.", channel);
+        if calibration.full_scale_counts <= calibration.zero_counts || calibration.full_scale_pressure <= 0.0 {
+            return Err(DriverError::ConfigurationFailed.into());
+        }
+        let mut transducer = Self { adc, channel, calibration };
+        // Make sure there is a sensor on the channel at all
+        let pressure = transducer.read().map_err(|_| {
+            DriverError::CalibrationFailed(format!("ADC channel {} reads outside the calibrated span", channel))
+        })?;
+        println!("[Driver:PressureTransducer] Channel {} reads {:.0} kPa.", channel, pressure / 1000.0);
+        Ok(transducer)
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    // Pressure in Pa gauge
+    pub fn read(&mut self) -> RocketResult<f32> {
+        let counts = self.adc.read(self.channel).map_err(DriverError::from)?;
+        let c = &self.calibration;
+        let fraction = (counts as f32 - c.zero_counts as f32) / (c.full_scale_counts - c.zero_counts) as f32;
+        if !(-RANGE_MARGIN..=1.0 + RANGE_MARGIN).contains(&fraction) {
+            return Err(DriverError::InvalidData.into());
+        }
+        Ok(fraction * c.full_scale_pressure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads back whatever count it was last given
+    struct FixedAdc(u16);
+
+    impl Adc<u16> for FixedAdc {
+        type Error = HalError;
+        fn read(&mut self, _channel: u8) -> Result<u16, HalError> {
+            Ok(self.0)
+        }
+    }
+
+    const CAL: PressureCalibration = PressureCalibration { zero_counts: 400, full_scale_counts: 3600, full_scale_pressure: 4.0e6 };
+
+    #[test]
+    fn converts_counts_with_the_channel_calibration() {
+        let mut transducer = PressureTransducer::new(FixedAdc(400), 1, CAL).unwrap();
+        assert_eq!(transducer.read().unwrap(), 0.0);
+        transducer.adc.0 = 2000;
+        assert_eq!(transducer.read().unwrap(), 2.0e6);
+        transducer.adc.0 = 3600;
+        assert_eq!(transducer.read().unwrap(), 4.0e6);
+    }
+
+    #[test]
+    fn rejects_readings_outside_the_span() {
+        // Broken wire: the output sits at the bottom rail
+        assert!(PressureTransducer::new(FixedAdc(0), 1, CAL).is_err());
+        let mut transducer = PressureTransducer::new(FixedAdc(400), 1, CAL).unwrap();
+        transducer.adc.0 = 4095;
+        assert_eq!(transducer.read(), Err(DriverError::InvalidData.into()));
+    }
+}
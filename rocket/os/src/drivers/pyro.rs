@@ -0,0 +1,154 @@
+// Pyro channel: fires an e-match through a switched output, with a sense
+// line reading continuity through the e-match.
+//
+// Firing is one-shot and always gives the full pulse: update() ends it no
+// earlier than the configured duration, and nothing else ends it at all.
+// A burnt-out e-match reads open afterwards.
+use crate::hal::interface::{OutputPin, InputPin};
+use crate::kernel::sim::Instant;
+use crate::kernel::sync::{self, sleep};
+use crate::error::Result as RocketResult;
+use std::time::Duration;
+
+pub struct PyroChannel<P: OutputPin, S: InputPin> {
+    name: &'static str,
+    fire_pin: P,      // High to fire
+    continuity: S,    // High while the e-match circuit is intact
+    fire_pulse: Duration,
+    fired_at: Option<Instant>,
+    firing: bool,     // Output still high
+}
+
+impl<P: OutputPin, S: InputPin> PyroChannel<P, S> {
+    pub fn new(name: &'static str, mut fire_pin: P, continuity: S, fire_pulse: Duration) -> RocketResult<Self> {
+        println!("[Driver:Pyro] Initializing {} channel.", name);
+        fire_pin.set_low()?;
+        Ok(Self { name, fire_pin, continuity, fire_pulse, fired_at: None, firing: false })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn continuity(&self) -> RocketResult<bool> {
+        Ok(self.continuity.is_high()?)
+    }
+
+    pub fn fired(&self) -> bool {
+        self.fired_at.is_some()
+    }
+
+    pub fn is_firing(&self) -> bool {
+        self.firing
+    }
+
+    // Start the pulse. Fires whatever the continuity reads: a bad reading
+    // is no reason to keep the parachute in. Later calls do nothing.
+    pub fn fire(&mut self) -> RocketResult<()> {
+        if self.fired() {
+            return Ok(());
+        }
+        if !self.continuity()? {
+            println!("[Driver:Pyro] {} channel has no continuity, firing anyway.", self.name);
+        }
+        println!("[Driver:Pyro] Firing {} channel.", self.name);
+        self.fire_pin.set_high()?;
+        self.fired_at = Some(sync::get_time());
+        self.firing = true;
+        Ok(())
+    }
+
+    // End the pulse once it has run its full length; called every loop
+    pub fn update(&mut self) -> RocketResult<()> {
+        if let (true, Some(fired_at)) = (self.firing, self.fired_at) {
+            if fired_at.elapsed() >= self.fire_pulse {
+                self.fire_pin.set_low()?;
+                self.firing = false;
+                println!(
+                    "[Driver:Pyro] {} pulse complete, continuity {}.",
+                    self.name,
+                    if self.continuity()? { "intact" } else { "open" }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Block until a pulse in progress has run its full length, then end it
+    pub fn finish_pulse(&mut self) -> RocketResult<()> {
+        if let (true, Some(fired_at)) = (self.firing, self.fired_at) {
+            sleep(self.fire_pulse.saturating_sub(fired_at.elapsed()));
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::hal::dummy_hal::{self, DummyPin};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+
+    const PULSE: Duration = config::PYRO_FIRE_PULSE;
+
+    fn channel() -> PyroChannel<DummyPin, DummyPin> {
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let hal = dummy_hal::get_dummy_hal();
+        let pin = |id| hal.get_gpio_pin(id).unwrap();
+        PyroChannel::new("Drogue", pin(config::DUMMY_DROGUE_PYRO_PIN), pin(config::DUMMY_DROGUE_CONTINUITY_PIN), PULSE).unwrap()
+    }
+
+    // The fire output as driven, read back from the pin
+    fn output_high() -> bool {
+        dummy_hal::get_dummy_hal().get_gpio_pin(config::DUMMY_DROGUE_PYRO_PIN).unwrap().is_high().unwrap()
+    }
+
+    #[test]
+    fn holds_the_output_for_the_whole_pulse() {
+        let _exclusive = sim::exclusive();
+        let mut pyro = channel();
+        assert!(pyro.continuity().unwrap());
+        pyro.fire().unwrap();
+        let fired_at = sync::get_time();
+        while sync::get_time().duration_since(fired_at) < PULSE {
+            assert!(output_high() && pyro.is_firing());
+            pyro.update().unwrap();
+            sleep(config::RECOVERY_LOOP_RATE);
+        }
+        pyro.update().unwrap();
+        assert!(!output_high() && !pyro.is_firing());
+        assert!(!pyro.continuity().unwrap()); // The e-match burnt through
+
+        // One shot only
+        pyro.fire().unwrap();
+        assert!(!output_high());
+    }
+
+    #[test]
+    fn finishing_early_still_gives_the_full_pulse() {
+        let _exclusive = sim::exclusive();
+        let mut pyro = channel();
+        pyro.fire().unwrap();
+        let fired_at = sync::get_time();
+        sleep(Duration::from_millis(100));
+        pyro.finish_pulse().unwrap();
+        assert_eq!(sync::get_time().duration_since(fired_at), PULSE);
+        assert!(!output_high() && !pyro.is_firing());
+        pyro.finish_pulse().unwrap(); // Nothing left to finish
+        assert_eq!(sync::get_time().duration_since(fired_at), PULSE);
+    }
+
+    #[test]
+    fn fires_without_continuity() {
+        let _exclusive = sim::exclusive();
+        let mut pyro = channel();
+        dummy_hal::set_open_circuit(config::DUMMY_DROGUE_PYRO_PIN, true);
+        assert!(!pyro.continuity().unwrap());
+        pyro.fire().unwrap();
+        assert!(pyro.fired() && pyro.is_firing() && output_high());
+    }
+}
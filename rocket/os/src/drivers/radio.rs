@@ -1,19 +1,174 @@
-// Placeholder for a radio driver (e.g., LoRa, RFM9x) using SPI
+// SX1276/77/78/79 LoRa transceiver driver using SPI
 use crate::hal::interface::{SpiBus, OutputPin, InputPin, DelayMs};
+use crate::error::{DriverError, RocketError};
 use crate::error::Result as RocketResult;
+use crate::kernel::{sim::Instant, sync};
+use std::time::Duration;
+
+// SX127x registers (LoRa mode)
+const REG_FIFO: u8 = 0x00;
+const REG_OP_MODE: u8 = 0x01;
+const REG_FRF_MSB: u8 = 0x06; // Carrier frequency, 3 bytes
+const REG_PA_CONFIG: u8 = 0x09;
+const REG_OCP: u8 = 0x0B;
+const REG_LNA: u8 = 0x0C;
+const REG_FIFO_ADDR_PTR: u8 = 0x0D;
+const REG_FIFO_TX_BASE_ADDR: u8 = 0x0E;
+const REG_FIFO_RX_BASE_ADDR: u8 = 0x0F;
+const REG_FIFO_RX_CURRENT_ADDR: u8 = 0x10;
+const REG_IRQ_FLAGS: u8 = 0x12;
+const REG_RX_NB_BYTES: u8 = 0x13;
+const REG_PKT_SNR_VALUE: u8 = 0x19;
+const REG_PKT_RSSI_VALUE: u8 = 0x1A;
+const REG_RSSI_VALUE: u8 = 0x1B;
+const REG_MODEM_CONFIG_1: u8 = 0x1D;
+const REG_MODEM_CONFIG_2: u8 = 0x1E;
+const REG_PREAMBLE_MSB: u8 = 0x20;
+const REG_PAYLOAD_LENGTH: u8 = 0x22;
+const REG_MODEM_CONFIG_3: u8 = 0x26;
+const REG_SYNC_WORD: u8 = 0x39;
+const REG_DIO_MAPPING_1: u8 = 0x40;
+const REG_VERSION: u8 = 0x42;
+const REG_PA_DAC: u8 = 0x4D;
+
+const CHIP_VERSION: u8 = 0x12;
+const LONG_RANGE_MODE: u8 = 0x80; // LoRa rather than FSK/OOK; only switchable in sleep
+const MODE_SLEEP: u8 = 0x00;
+const MODE_STDBY: u8 = 0x01;
+const MODE_TX: u8 = 0x03;
+const MODE_RX_CONTINUOUS: u8 = 0x05;
+const IRQ_RX_DONE: u8 = 0x40;
+const IRQ_PAYLOAD_CRC_ERROR: u8 = 0x20;
+const IRQ_TX_DONE: u8 = 0x08;
+const DIO0_RX_DONE: u8 = 0x00; // DIO0 mapping, bits 7:6
+const DIO0_TX_DONE: u8 = 0x40;
+const PA_BOOST: u8 = 0x80;
+const PA_MAX_POWER: u8 = 0x70;
+const PA_DAC_DEFAULT: u8 = 0x84;
+const PA_DAC_HIGH_POWER: u8 = 0x87; // +20 dBm on PA_BOOST
+const OCP_100_MA: u8 = 0x2B;
+const OCP_140_MA: u8 = 0x31;
+const LNA_BOOST_HF: u8 = 0x03;
+const AGC_AUTO_ON: u8 = 0x04;
+const LOW_DATA_RATE_OPTIMIZE: u8 = 0x08;
+const RX_PAYLOAD_CRC_ON: u8 = 0x04;
+
+const CRYSTAL_HZ: u64 = 32_000_000;
+const HF_PORT_MIN_HZ: u32 = 779_000_000; // Band 1 (HF port) starts here; RSSI offset differs
+const MAX_PAYLOAD: usize = 255;
+const TX_TIMEOUT_MARGIN: Duration = Duration::from_millis(100); // Beyond the computed time on air
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bandwidth {
+    Khz7_8,
+    Khz10_4,
+    Khz15_6,
+    Khz20_8,
+    Khz31_25,
+    Khz41_7,
+    Khz62_5,
+    Khz125,
+    Khz250,
+    Khz500,
+}
+
+impl Bandwidth {
+    pub fn hz(self) -> f32 {
+        [7_800.0, 10_400.0, 15_600.0, 20_800.0, 31_250.0, 41_700.0, 62_500.0, 125_000.0, 250_000.0, 500_000.0]
+            [self as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodingRate {
+    Cr4_5 = 1,
+    Cr4_6 = 2,
+    Cr4_7 = 3,
+    Cr4_8 = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RadioConfig {
+    pub frequency: u32,        // Hz
+    pub spreading_factor: u8,  // 7..=12 (SF6 needs implicit headers, not supported)
+    pub bandwidth: Bandwidth,
+    pub coding_rate: CodingRate,
+    pub tx_power: i8,          // dBm on PA_BOOST, 2..=17 or 20
+    pub preamble_length: u16,  // Symbols
+    pub sync_word: u8,         // 0x12 private networks, 0x34 LoRaWAN
+    pub crc: bool,             // Append/check the payload CRC
+}
+
+impl RadioConfig {
+    // Symbol duration, s
+    pub fn symbol_time(&self) -> f32 {
+        (1u32 << self.spreading_factor) as f32 / self.bandwidth.hz()
+    }
+
+    // Long symbols need the low data rate optimisation
+    fn low_data_rate_optimize(&self) -> bool {
+        self.symbol_time() > 0.016
+    }
+
+    // Air time of a packet with `payload_len` bytes, explicit header
+    // (Semtech AN1200.13)
+    pub fn time_on_air(&self, payload_len: usize) -> Duration {
+        let sf = self.spreading_factor as f32;
+        let de = if self.low_data_rate_optimize() { 1.0 } else { 0.0 };
+        let crc = if self.crc { 1.0 } else { 0.0 };
+        let preamble = self.preamble_length as f32 + 4.25;
+        let bits = 8.0 * payload_len as f32 - 4.0 * sf + 28.0 + 16.0 * crc;
+        let payload = 8.0 + ((bits / (4.0 * (sf - 2.0 * de))).ceil() * (self.coding_rate as u8 as f32 + 4.0)).max(0.0);
+        Duration::from_secs_f32((preamble + payload) * self.symbol_time())
+    }
+}
+
+impl Default for RadioConfig {
+    // 915 MHz ISM, SF7/125 kHz: about 70 ms for a telemetry packet
+    fn default() -> Self {
+        RadioConfig {
+            frequency: 915_000_000,
+            spreading_factor: 7,
+            bandwidth: Bandwidth::Khz125,
+            coding_rate: CodingRate::Cr4_5,
+            tx_power: 17,
+            preamble_length: 8,
+            sync_word: 0x12,
+            crc: true,
+        }
+    }
+}
+
+// Signal quality of the last packet received
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketStatus {
+    pub rssi: f32, // dBm
+    pub snr: f32,  // dB
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    pub packets_sent: u32,
+    pub packets_received: u32,
+    pub crc_errors: u32, // Packets dropped for a bad payload CRC
+}
 
 pub struct Radio<SPI, CS, IRQ, DELAY>
 where
     SPI: SpiBus,
     CS: OutputPin,
-    IRQ: InputPin, // Interrupt pin (optional, for non-polling)
+    IRQ: InputPin, // DIO0: TxDone while transmitting, RxDone while receiving
     DELAY: DelayMs,
 {
     spi: SPI,
     cs: CS,
-    _irq: IRQ, // Mark as unused if not polling
+    irq: IRQ,
     delay: DELAY,
-    // Add radio configuration state
+    config: RadioConfig,
+    stats: LinkStats,
+    last_packet: Option<PacketStatus>,
+    held_packet: Option<Vec<u8>>, // Received just before a transmit, not yet collected
+    tx_deadline: Option<Instant>, // Transmitting; gives up on TxDone at this time
 }
 
 impl<SPI, CS, IRQ, DELAY> Radio<SPI, CS, IRQ, DELAY>
@@ -23,8 +178,8 @@ where
     IRQ: InputPin,
     DELAY: DelayMs,
 {
-    pub fn new(spi: SPI, cs: CS, irq: IRQ, delay: DELAY) -> RocketResult<Self> {
-        let mut radio = Self { spi, cs, _irq: irq, delay };
+    pub fn new(spi: SPI, cs: CS, irq: IRQ, delay: DELAY, config: RadioConfig) -> RocketResult<Self> {
+        let mut radio = Self { spi, cs, irq, delay, config, stats: LinkStats::default(), last_packet: None, held_packet: None, tx_deadline: None };
         radio.init()?;
         Ok(radio)
     }
@@ -32,19 +187,110 @@ where
     fn init(&mut self) -> RocketResult<()> {
         println!("[Driver:Radio] Initializing radio.");
         self.cs.set_high()?; // Deselect chip initially
-        self.delay.delay_ms(10);
-        // Radio-specific initialization commands via SPI
-        // Example: self.write_reg(0x01, 0b10000000)?; // Set LoRa mode, sleep
-        //          self.write_reg(0x0D, 0x00)?; // Set FIFO pointer
-        //          ... configure frequency, power, etc. ...
-        //          self.write_reg(0x01, 0b10000101)?; // Set LoRa mode, standby
-        self.delay.delay_ms(10);
-        println!("[Driver:Radio] Initialization complete (simulated).");
+        self.delay.delay_ms(10); // Power-on reset
+
+        let version = self.read_reg(REG_VERSION)?;
+        if version != CHIP_VERSION {
+            return Err(DriverError::UnexpectedDevice(version).into());
+        }
+
+        // LoRa mode can only be selected from sleep
+        self.write_reg(REG_OP_MODE, MODE_SLEEP)?;
+        self.write_reg(REG_OP_MODE, LONG_RANGE_MODE | MODE_SLEEP)?;
+        if self.read_reg(REG_OP_MODE)? & LONG_RANGE_MODE == 0 {
+            return Err(DriverError::ConfigurationFailed.into());
+        }
+        // Whole 256-byte FIFO for each direction (half duplex, never both at once)
+        self.write_reg(REG_FIFO_TX_BASE_ADDR, 0x00)?;
+        self.write_reg(REG_FIFO_RX_BASE_ADDR, 0x00)?;
+        let lna = self.read_reg(REG_LNA)?;
+        self.write_reg(REG_LNA, lna | LNA_BOOST_HF)?;
+        self.set_mode(MODE_STDBY)?;
+
+        self.configure(self.config)?;
+        self.start_receive()?;
+        println!("[Driver:Radio] Initialization complete.");
+        Ok(())
+    }
+
+    pub fn config(&self) -> RadioConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    // RSSI/SNR of the last packet received (None until one arrives)
+    pub fn last_packet_status(&self) -> Option<PacketStatus> {
+        self.last_packet
+    }
+
+    // Apply modem and RF settings, reading each back to catch a chip that
+    // ignored the write. Leaves the radio in standby.
+    pub fn configure(&mut self, config: RadioConfig) -> RocketResult<()> {
+        if !(7..=12).contains(&config.spreading_factor) {
+            return Err(RocketError::Configuration(format!("Unsupported spreading factor SF{}", config.spreading_factor)));
+        }
+        if !(2..=17).contains(&config.tx_power) && config.tx_power != 20 {
+            return Err(RocketError::Configuration(format!("Unsupported TX power {} dBm", config.tx_power)));
+        }
+        self.set_mode(MODE_STDBY)?;
+        self.tx_deadline = None; // Standby abandons a transmission
+
+        let frf = ((config.frequency as u64) << 19) / CRYSTAL_HZ;
+        let (pa_dac, output_power, ocp) = if config.tx_power == 20 {
+            (PA_DAC_HIGH_POWER, 15, OCP_140_MA)
+        } else {
+            (PA_DAC_DEFAULT, (config.tx_power - 2) as u8, OCP_100_MA)
+        };
+        let modem_config_1 = ((config.bandwidth as u8) << 4) | ((config.coding_rate as u8) << 1); // Explicit header
+        let modem_config_2 = (config.spreading_factor << 4) | if config.crc { RX_PAYLOAD_CRC_ON } else { 0 };
+        let modem_config_3 = AGC_AUTO_ON | if config.low_data_rate_optimize() { LOW_DATA_RATE_OPTIMIZE } else { 0 };
+        let settings = [
+            (REG_FRF_MSB, (frf >> 16) as u8),
+            (REG_FRF_MSB + 1, (frf >> 8) as u8),
+            (REG_FRF_MSB + 2, frf as u8),
+            (REG_PA_DAC, pa_dac),
+            (REG_PA_CONFIG, PA_BOOST | PA_MAX_POWER | output_power),
+            (REG_OCP, ocp),
+            (REG_MODEM_CONFIG_1, modem_config_1),
+            (REG_MODEM_CONFIG_2, modem_config_2),
+            (REG_MODEM_CONFIG_3, modem_config_3),
+            (REG_PREAMBLE_MSB, (config.preamble_length >> 8) as u8),
+            (REG_PREAMBLE_MSB + 1, config.preamble_length as u8),
+            (REG_SYNC_WORD, config.sync_word),
+        ];
+        for (register, value) in settings {
+            self.write_reg(register, value)?;
+            if self.read_reg(register)? != value {
+                return Err(DriverError::ConfigurationFailed.into());
+            }
+        }
+        self.config = config;
+        println!(
+            "[Driver:Radio] Configured: {:.3} MHz, SF{}, {:?}, {:?}, {} dBm",
+            config.frequency as f32 / 1e6,
+            config.spreading_factor,
+            config.bandwidth,
+            config.coding_rate,
+            config.tx_power
+        );
         Ok(())
     }
 
+    fn set_mode(&mut self, mode: u8) -> RocketResult<()> {
+        self.write_reg(REG_OP_MODE, LONG_RANGE_MODE | mode)
+    }
+
+    // Listen continuously; packets are collected with receive_packet
+    fn start_receive(&mut self) -> RocketResult<()> {
+        self.write_reg(REG_DIO_MAPPING_1, DIO0_RX_DONE)?;
+        self.write_reg(REG_FIFO_ADDR_PTR, 0x00)?;
+        self.set_mode(MODE_RX_CONTINUOUS)
+    }
+
     // Helper for SPI register access
-    #[allow(dead_code)] // May not be used in basic example
     fn read_reg(&mut self, reg: u8) -> RocketResult<u8> {
         self.cs.set_low()?;
         let mut buffer = [reg & 0x7F, 0]; // MSB=0 for read
@@ -53,7 +299,6 @@ where
         Ok(buffer[1])
     }
 
-    #[allow(dead_code)] // May not be used in basic example
     fn write_reg(&mut self, reg: u8, value: u8) -> RocketResult<()> {
         self.cs.set_low()?;
         let buffer = [reg | 0x80, value]; // MSB=1 for write
@@ -62,43 +307,336 @@ where
         Ok(())
     }
 
+    // Burst access; on REG_FIFO every byte goes through the FIFO pointer
+    fn read_burst(&mut self, reg: u8, data: &mut [u8]) -> RocketResult<()> {
+        self.cs.set_low()?;
+        let mut buffer = vec![0u8; data.len() + 1];
+        buffer[0] = reg & 0x7F;
+        self.spi.transfer(&mut buffer)?;
+        self.cs.set_high()?;
+        data.copy_from_slice(&buffer[1..]);
+        Ok(())
+    }
+
+    fn write_burst(&mut self, reg: u8, data: &[u8]) -> RocketResult<()> {
+        self.cs.set_low()?;
+        let mut buffer = Vec::with_capacity(data.len() + 1);
+        buffer.push(reg | 0x80);
+        buffer.extend_from_slice(data);
+        self.spi.write(&buffer)?;
+        self.cs.set_high()?;
+        Ok(())
+    }
+
+    // Current channel RSSI, dBm
+    pub fn rssi(&mut self) -> RocketResult<f32> {
+        Ok(self.rssi_offset() + self.read_reg(REG_RSSI_VALUE)? as f32)
+    }
+
+    fn rssi_offset(&self) -> f32 {
+        if self.config.frequency >= HF_PORT_MIN_HZ { -157.0 } else { -164.0 }
+    }
+
+    // Transmit one packet and wait for TxDone, then go back to listening
     pub fn send_packet(&mut self, packet: &[u8]) -> RocketResult<()> {
+        self.start_transmit(packet)?;
+        while !self.transmit_done()? {
+            self.delay.delay_ms(1);
+        }
+        Ok(())
+    }
+
+    // Load one packet and start sending it, without waiting for it to go
+    // out; poll transmit_done until it has. Lets a shared radio be released
+    // for the time on air.
+    pub fn start_transmit(&mut self, packet: &[u8]) -> RocketResult<()> {
+        if packet.is_empty() || packet.len() > MAX_PAYLOAD {
+            return Err(RocketError::Configuration(format!("Packet length {} outside 1..={}", packet.len(), MAX_PAYLOAD)));
+        }
+        if self.tx_deadline.is_some() {
+            return Err(DriverError::CommunicationError("transmission already in progress".to_string()).into());
+        }
         println!("[Driver:Radio] Sending packet ({} bytes): {:02X?}", packet.len(), packet);
-        // Radio-specific send sequence:
-        // 1. Set mode to Standby
-        // 2. Set FIFO pointer to start of TX buffer
-        // 3. Write packet data to FIFO via SPI
-        // 4. Set mode to TX
-        // 5. Wait for TX done interrupt/flag (or timeout)
-        self.delay.delay_ms(50); // Simulate transmission time
-        println!("[Driver:Radio] Packet sent.");
+        // Switching to TX clears RxDone and reuses the FIFO: collect a packet
+        // that arrived since the last receive_packet so it isn't lost
+        if let Some(received) = self.read_received()? {
+            self.held_packet = Some(received);
+        }
+        self.set_mode(MODE_STDBY)?;
+        self.write_reg(REG_FIFO_ADDR_PTR, 0x00)?; // TX base address
+        self.write_burst(REG_FIFO, packet)?;
+        self.write_reg(REG_PAYLOAD_LENGTH, packet.len() as u8)?;
+        self.write_reg(REG_IRQ_FLAGS, 0xFF)?; // Clear stale flags
+        self.write_reg(REG_DIO_MAPPING_1, DIO0_TX_DONE)?;
+        self.set_mode(MODE_TX)?;
+        self.tx_deadline = Some(sync::get_time() + self.config.time_on_air(packet.len()) + TX_TIMEOUT_MARGIN);
         Ok(())
     }
 
-     pub fn receive_packet(&mut self, buffer: &mut [u8]) -> RocketResult<usize> {
-         println!("[Driver:Radio] Checking for incoming packet...");
-         // Radio-specific receive sequence:
-         // 1. Check IRQ pin or status register for RX_DONE flag
-         // 2. If packet received:
-         //    a. Get packet length from radio registers
-         //    b. Get start address of received packet in FIFO
-         //    c. Read packet data from FIFO via SPI
-         //    d. Clear IRQ flags
-         //    e. Return number of bytes read
-         // 3. If no packet, return 0 bytes read
-
-         // Simulate occasionally receiving a packet
-         let mut rng = rand::thread_rng();
-         if rng.gen_bool(0.1) { // 10% chance of receiving something
-            let len = rng.gen_range(5..=20.min(buffer.len()));
-            for i in 0..len {
-                buffer[i] = rng.gen();
+    // Whether the packet from start_transmit has gone out (true when nothing
+    // is being sent). Once it has, or it timed out, the radio is listening again.
+    pub fn transmit_done(&mut self) -> RocketResult<bool> {
+        let Some(deadline) = self.tx_deadline else {
+            return Ok(true);
+        };
+        // DIO0 rises on TxDone; the chip drops back to standby by itself
+        if !self.irq.is_high()? {
+            let now = sync::get_time();
+            if now < deadline {
+                return Ok(false);
             }
-            println!("[Driver:Radio] Received {} bytes: {:02X?}", len, &buffer[..len]);
-            self.delay.delay_ms(10); // Simulate read time
-            Ok(len)
-         } else {
-            Ok(0) // No packet received
-         }
-     }
+            self.tx_deadline = None;
+            self.start_receive()?;
+            return Err(DriverError::CommunicationError(format!("TX timeout at {:.3} s", now.as_secs_f64())).into());
+        }
+        self.tx_deadline = None;
+        self.write_reg(REG_IRQ_FLAGS, IRQ_TX_DONE)?;
+        self.stats.packets_sent += 1;
+        println!("[Driver:Radio] Packet sent.");
+        self.start_receive()?;
+        Ok(true)
+    }
+
+    // Copy out a packet received since the last call. Returns 0 if none
+    // arrived or it failed its CRC; a packet longer than `buffer` is an error.
+    pub fn receive_packet(&mut self, buffer: &mut [u8]) -> RocketResult<usize> {
+        let packet = match self.held_packet.take() {
+            Some(packet) => packet,
+            None => match self.read_received()? {
+                Some(packet) => packet,
+                None => return Ok(0),
+            },
+        };
+        if packet.len() > buffer.len() {
+            return Err(DriverError::CommunicationError(format!("{}-byte packet, {}-byte buffer", packet.len(), buffer.len())).into());
+        }
+        buffer[..packet.len()].copy_from_slice(&packet);
+        Ok(packet.len())
+    }
+
+    // Take a packet out of the chip if RxDone is up. Nothing can arrive
+    // while transmitting (DIO0 is mapped to TxDone then).
+    fn read_received(&mut self) -> RocketResult<Option<Vec<u8>>> {
+        if self.tx_deadline.is_some() || self.irq.is_low()? {
+            return Ok(None);
+        }
+        let flags = self.read_reg(REG_IRQ_FLAGS)?;
+        if flags & IRQ_RX_DONE == 0 {
+            return Ok(None);
+        }
+        self.write_reg(REG_IRQ_FLAGS, 0xFF)?;
+        if flags & IRQ_PAYLOAD_CRC_ERROR != 0 {
+            self.stats.crc_errors += 1;
+            println!("[Driver:Radio] Dropped packet with bad CRC ({} so far)", self.stats.crc_errors);
+            return Ok(None);
+        }
+
+        let len = self.read_reg(REG_RX_NB_BYTES)? as usize;
+        let mut packet = vec![0u8; len];
+        let start = self.read_reg(REG_FIFO_RX_CURRENT_ADDR)?;
+        self.write_reg(REG_FIFO_ADDR_PTR, start)?;
+        self.read_burst(REG_FIFO, &mut packet)?;
+
+        // Packet RSSI is only linear above the noise floor (datasheet 5.5.5)
+        let snr = self.read_reg(REG_PKT_SNR_VALUE)? as i8 as f32 / 4.0;
+        let pkt_rssi = self.read_reg(REG_PKT_RSSI_VALUE)? as f32;
+        let rssi = if snr < 0.0 {
+            self.rssi_offset() + pkt_rssi + snr
+        } else {
+            self.rssi_offset() + pkt_rssi * 16.0 / 15.0
+        };
+        self.last_packet = Some(PacketStatus { rssi, snr });
+        self.stats.packets_received += 1;
+        println!("[Driver:Radio] Received {} bytes (RSSI {:.0} dBm, SNR {:.1} dB): {:02X?}", len, rssi, snr, packet);
+        Ok(Some(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::hal::dummy_hal::{self, DummyDelay, DummyPin, DummySpi};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::hal::radio_sim::LinkParams;
+    use crate::kernel::sim::{self, ClockMode};
+
+    type SimRadio = Radio<DummySpi, DummyPin, DummyPin, DummyDelay>;
+
+    fn radio(bus: u8, cs: u8, irq: u8) -> SimRadio {
+        let hal = dummy_hal::get_dummy_hal();
+        Radio::new(
+            hal.get_spi_bus(bus).unwrap(),
+            hal.get_gpio_pin(cs).unwrap(),
+            hal.get_gpio_pin(irq).unwrap(),
+            hal.get_delay_timer(),
+            RadioConfig::default(),
+        )
+        .unwrap()
+    }
+
+    // Flight and ground radios over a link with no random impairments
+    fn setup(ground_station: [f64; 3]) -> (SimRadio, SimRadio) {
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        dummy_hal::set_link_params(LinkParams {
+            packet_loss: 0.0,
+            bit_error_rate: 0.0,
+            fading: 0.0,
+            ground_station,
+            ..LinkParams::default()
+        });
+        let flight = radio(config::DUMMY_RADIO_SPI_BUS, config::DUMMY_RADIO_CS_PIN, config::DUMMY_RADIO_IRQ_PIN);
+        let ground = radio(config::DUMMY_GROUND_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_CS_PIN, config::DUMMY_GROUND_RADIO_IRQ_PIN);
+        (flight, ground)
+    }
+
+    // Received signal strength the link budget gives at `ground_station`
+    fn expected_rssi(ground_station: [f64; 3]) -> f32 {
+        let vehicle = dummy_hal::vehicle_state().position;
+        let distance = (0..3).map(|i| (ground_station[i] - vehicle[i]).powi(2)).sum::<f64>().sqrt();
+        let path_loss = 20.0 * 915e6f64.log10() - 147.55 + 20.0 * distance.log10();
+        (17.0 + 2.0 * LinkParams::default().antenna_gain - path_loss) as f32
+    }
+
+    #[test]
+    fn init_and_configure_write_the_modem_registers() {
+        let _exclusive = sim::exclusive();
+        let (mut radio, _) = setup(LinkParams::default().ground_station);
+        // Listening in LoRa mode, 915 MHz, SF7/125 kHz/4:5, CRC on, 17 dBm on PA_BOOST
+        let expected = [
+            (REG_OP_MODE, LONG_RANGE_MODE | MODE_RX_CONTINUOUS),
+            (REG_FRF_MSB, 0xE4),
+            (REG_FRF_MSB + 1, 0xC0),
+            (REG_FRF_MSB + 2, 0x00),
+            (REG_PA_CONFIG, 0xFF),
+            (REG_PA_DAC, PA_DAC_DEFAULT),
+            (REG_OCP, OCP_100_MA),
+            (REG_LNA, 0x20 | LNA_BOOST_HF),
+            (REG_MODEM_CONFIG_1, 0x72),
+            (REG_MODEM_CONFIG_2, 0x74),
+            (REG_MODEM_CONFIG_3, AGC_AUTO_ON),
+            (REG_PREAMBLE_MSB, 0x00),
+            (REG_PREAMBLE_MSB + 1, 0x08),
+            (REG_SYNC_WORD, 0x12),
+            (REG_DIO_MAPPING_1, DIO0_RX_DONE),
+        ];
+        for (register, value) in expected {
+            assert_eq!(radio.read_reg(register).unwrap(), value, "register 0x{:02X}", register);
+        }
+
+        // Long symbols and full power: low data rate optimisation, high-power DAC and current limit
+        let long_range = RadioConfig {
+            frequency: 868_000_000,
+            spreading_factor: 12,
+            coding_rate: CodingRate::Cr4_8,
+            tx_power: 20,
+            crc: false,
+            ..RadioConfig::default()
+        };
+        radio.configure(long_range).unwrap();
+        let expected = [
+            (REG_OP_MODE, LONG_RANGE_MODE | MODE_STDBY),
+            (REG_FRF_MSB, 0xD9),
+            (REG_FRF_MSB + 1, 0x00),
+            (REG_FRF_MSB + 2, 0x00),
+            (REG_PA_CONFIG, 0xFF),
+            (REG_PA_DAC, PA_DAC_HIGH_POWER),
+            (REG_OCP, OCP_140_MA),
+            (REG_MODEM_CONFIG_1, 0x78),
+            (REG_MODEM_CONFIG_2, 0xC0),
+            (REG_MODEM_CONFIG_3, AGC_AUTO_ON | LOW_DATA_RATE_OPTIMIZE),
+        ];
+        for (register, value) in expected {
+            assert_eq!(radio.read_reg(register).unwrap(), value, "register 0x{:02X}", register);
+        }
+        assert_eq!(radio.config(), long_range);
+
+        // Rejected before touching the chip
+        assert!(radio.configure(RadioConfig { spreading_factor: 6, ..RadioConfig::default() }).is_err());
+        assert!(radio.configure(RadioConfig { tx_power: 18, ..RadioConfig::default() }).is_err());
+        assert_eq!(radio.config(), long_range);
+    }
+
+    #[test]
+    fn send_waits_for_tx_done_and_goes_back_to_listening() {
+        let _exclusive = sim::exclusive();
+        let (mut flight, mut ground) = setup(LinkParams::default().ground_station);
+        let packet = [0xA5u8; 40];
+        let started = sim::now();
+        flight.send_packet(&packet).unwrap();
+        let elapsed = sim::now() - started;
+        let time_on_air = flight.config().time_on_air(packet.len());
+        assert!(elapsed >= time_on_air && elapsed <= time_on_air + Duration::from_millis(2), "{:?}", elapsed);
+        assert_eq!(flight.stats().packets_sent, 1);
+        assert_eq!(flight.read_reg(REG_IRQ_FLAGS).unwrap(), 0);
+        assert_eq!(flight.read_reg(REG_OP_MODE).unwrap(), LONG_RANGE_MODE | MODE_RX_CONTINUOUS);
+        assert_eq!(flight.read_reg(REG_DIO_MAPPING_1).unwrap(), DIO0_RX_DONE);
+
+        sim::sleep(Duration::from_millis(5)); // Link latency
+        let mut buffer = [0u8; MAX_PAYLOAD];
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), packet.len());
+        assert_eq!(buffer[..packet.len()], packet);
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), 0);
+        assert_eq!(ground.stats().packets_received, 1);
+
+        // Nothing to send, or too much
+        assert!(flight.send_packet(&[]).is_err());
+        assert!(flight.send_packet(&[0; MAX_PAYLOAD + 1]).is_err());
+        // A packet too long for the caller's buffer is an error, not truncated
+        ground.send_packet(&packet).unwrap();
+        sim::sleep(Duration::from_millis(5));
+        assert!(flight.receive_packet(&mut buffer[..10]).is_err());
+    }
+
+    #[test]
+    fn drops_packets_with_a_bad_crc() {
+        let _exclusive = sim::exclusive();
+        let (mut flight, mut ground) = setup(LinkParams::default().ground_station);
+        dummy_hal::set_link_params(LinkParams { packet_loss: 0.0, bit_error_rate: 1.0, fading: 0.0, ..LinkParams::default() });
+        flight.send_packet(b"corrupted").unwrap();
+        sim::sleep(Duration::from_millis(5));
+        let mut buffer = [0u8; MAX_PAYLOAD];
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), 0);
+        assert_eq!(ground.stats(), LinkStats { packets_sent: 0, packets_received: 0, crc_errors: 1 });
+        // The flags were cleared: the next good packet comes through
+        dummy_hal::set_link_params(LinkParams { packet_loss: 0.0, bit_error_rate: 0.0, fading: 0.0, ..LinkParams::default() });
+        flight.send_packet(b"intact").unwrap();
+        sim::sleep(Duration::from_millis(5));
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), 6);
+        assert_eq!(&buffer[..6], b"intact");
+        assert_eq!(ground.stats(), LinkStats { packets_sent: 0, packets_received: 1, crc_errors: 1 });
+    }
+
+    #[test]
+    fn converts_packet_rssi_and_snr() {
+        let _exclusive = sim::exclusive();
+        let mut buffer = [0u8; MAX_PAYLOAD];
+        // Register quantisation: 0.25 dB of SNR, 16/15 dB of packet RSSI
+        let tolerance = 0.6;
+
+        // Strong signal: SNR reads at the chip's ceiling and RSSI scales by 16/15
+        let nearby = [300.0, -150.0, 2.0];
+        let (mut flight, mut ground) = setup(nearby);
+        ground.send_packet(b"near").unwrap();
+        sim::sleep(Duration::from_millis(5));
+        assert_eq!(flight.receive_packet(&mut buffer).unwrap(), 4);
+        let status = flight.last_packet_status().unwrap();
+        assert_eq!(status.snr, 10.0);
+        assert!((status.rssi - expected_rssi(nearby)).abs() < tolerance, "{:?} vs {}", status, expected_rssi(nearby));
+
+        // Below the noise floor: RSSI is corrected by the (negative) SNR
+        let distant = [0.0, 0.0, 300_000.0];
+        let (mut flight, mut ground) = setup(distant);
+        ground.send_packet(b"far").unwrap();
+        sim::sleep(Duration::from_millis(5));
+        assert_eq!(flight.receive_packet(&mut buffer).unwrap(), 3);
+        let status = flight.last_packet_status().unwrap();
+        let noise_floor = -174.0 + 10.0 * 125_000f32.log10() + 6.0;
+        assert!(status.snr < 0.0, "{:?}", status);
+        assert!((status.snr - (expected_rssi(distant) - noise_floor)).abs() < tolerance, "{:?}", status);
+        assert!((status.rssi - expected_rssi(distant)).abs() < tolerance, "{:?} vs {}", status, expected_rssi(distant));
+        // Channel RSSI with nothing on the air is the noise floor
+        assert!((flight.rssi().unwrap() - noise_floor).abs() < 1.0);
+    }
 }
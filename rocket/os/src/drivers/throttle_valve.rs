@@ -0,0 +1,233 @@
+// Servo-actuated throttle valve: position commanded by pulse width on a PWM
+// channel, measured by a feedback potentiometer on an ADC channel.
+//
+// Requests go through a slew-rate limit so the valve never moves faster
+// than the feed system tolerates; update() advances the commanded position
+// towards the request and must be called regularly. close() skips the limit
+// for aborts.
+use crate::hal::interface::{PwmOutput, Adc};
+use crate::kernel::sim::Instant;
+use crate::kernel::sync;
+use crate::error::{DriverError, HalError, DriverResult};
+use crate::error::Result as RocketResult;
+
+// Feedback readings this far (as a fraction of the calibrated span) beyond
+// either end of travel mean the pot is disconnected or shorted
+const FEEDBACK_RANGE_MARGIN: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleValveConfig {
+    pub closed_pulse_us: f32,  // Servo pulse width for the closed end of travel
+    pub open_pulse_us: f32,    // ... and for fully open
+    pub max_slew_rate: f32,    // Fraction of full travel per second
+    pub feedback_closed: u16,  // ADC counts with the valve closed
+    pub feedback_open: u16,    // ADC counts with the valve fully open
+    pub tracking_tolerance: f32, // Allowed |measured - commanded|, fraction of travel
+}
+
+impl Default for ThrottleValveConfig {
+    fn default() -> Self {
+        ThrottleValveConfig {
+            closed_pulse_us: 1000.0,
+            open_pulse_us: 2000.0,
+            max_slew_rate: 2.0,
+            feedback_closed: 410, // Pot spans 10%..90% of a 12-bit reference
+            feedback_open: 3686,
+            tracking_tolerance: 0.1,
+        }
+    }
+}
+
+pub struct ThrottleValve<PWM, ADC>
+where
+    PWM: PwmOutput,
+    ADC: Adc<u16, Error = HalError>,
+{
+    pwm: PWM,
+    adc: ADC,
+    feedback_channel: u8,
+    config: ThrottleValveConfig,
+    target: f32,    // Requested position, 0 (closed) to 1 (fully open)
+    commanded: f32, // Slew-limited position sent to the servo
+    last_update: Instant,
+}
+
+impl<PWM, ADC> ThrottleValve<PWM, ADC>
+where
+    PWM: PwmOutput,
+    ADC: Adc<u16, Error = HalError>,
+{
+    pub fn new(pwm: PWM, adc: ADC, feedback_channel: u8, config: ThrottleValveConfig) -> RocketResult<Self> {
+        println!("[Driver:ThrottleValve] Initializing throttle valve (feedback on ADC channel {}).", feedback_channel);
+        let mut valve = Self {
+            pwm,
+            adc,
+            feedback_channel,
+            config,
+            target: 0.0,
+            commanded: 0.0,
+            last_update: sync::get_time(),
+        };
+        // Start closed, and make sure the feedback pot is there at all
+        valve.write_position(0.0)?;
+        let position = valve.position()?;
+        if !(-FEEDBACK_RANGE_MARGIN..=1.0 + FEEDBACK_RANGE_MARGIN).contains(&position) {
+            return Err(DriverError::CalibrationFailed(format!("feedback reads {:.2} of travel", position)).into());
+        }
+        println!("[Driver:ThrottleValve] Initialization complete.");
+        Ok(valve)
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn commanded(&self) -> f32 {
+        self.commanded
+    }
+
+    // Request a position, 0 (closed) to 1 (fully open). The valve gets there
+    // at the slew limit over the following update() calls.
+    pub fn set_target(&mut self, position: f32) -> RocketResult<()> {
+        if !position.is_finite() {
+            return Err(DriverError::InvalidData.into());
+        }
+        self.target = position.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    // Step the commanded position towards the target at the slew limit
+    pub fn update(&mut self) -> RocketResult<()> {
+        let now = sync::get_time();
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        let step = self.config.max_slew_rate * dt;
+        let next = self.commanded + (self.target - self.commanded).clamp(-step, step);
+        self.write_position(next)?;
+        Ok(())
+    }
+
+    // Command closed immediately, ignoring the slew limit
+    pub fn close(&mut self) -> RocketResult<()> {
+        println!("[Driver:ThrottleValve] Closing.");
+        self.target = 0.0;
+        self.last_update = sync::get_time();
+        self.write_position(0.0)?;
+        Ok(())
+    }
+
+    // Measured position from the feedback pot, 0 (closed) to 1 (fully open)
+    pub fn position(&mut self) -> RocketResult<f32> {
+        let counts = self.adc.read(self.feedback_channel).map_err(DriverError::from)?;
+        let closed = self.config.feedback_closed as f32;
+        let open = self.config.feedback_open as f32;
+        Ok((counts as f32 - closed) / (open - closed))
+    }
+
+    // Measured minus commanded position
+    pub fn tracking_error(&mut self) -> RocketResult<f32> {
+        Ok(self.position()? - self.commanded)
+    }
+
+    // Fail if the valve isn't where it was told to be (stalled, slipped or
+    // lost feedback)
+    pub fn check_tracking(&mut self) -> RocketResult<()> {
+        let error = self.tracking_error()?;
+        if error.abs() > self.config.tracking_tolerance {
+            return Err(DriverError::ActuatorFault(format!(
+                "throttle valve {:.2} off its commanded position {:.2}",
+                error, self.commanded
+            )).into());
+        }
+        Ok(())
+    }
+
+    fn write_position(&mut self, position: f32) -> DriverResult<()> {
+        let pulse_us = self.config.closed_pulse_us + (self.config.open_pulse_us - self.config.closed_pulse_us) * position;
+        let duty = pulse_us / self.pwm.period().as_micros() as f32;
+        self.pwm.set_duty_cycle(duty)?;
+        self.commanded = position;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::error::RocketError;
+    use crate::hal::dummy_hal::{self, DummyAdc, DummyPwm};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::{self, ClockMode};
+    use std::time::Duration;
+
+    const STEP: Duration = Duration::from_millis(20);
+
+    fn valve() -> ThrottleValve<DummyPwm, DummyAdc> {
+        sim::init(ClockMode::Simulated, 1);
+        dummy_hal::reset();
+        let hal = dummy_hal::get_dummy_hal();
+        let pwm = hal.get_pwm_channel(config::DUMMY_THROTTLE_PWM_CHANNEL).unwrap();
+        let adc = hal.get_adc(config::DUMMY_ADC_ID).unwrap();
+        ThrottleValve::new(pwm, adc, config::DUMMY_THROTTLE_FEEDBACK_CHANNEL, ThrottleValveConfig::default()).unwrap()
+    }
+
+    // Every position sent to the servo so far, from the pulse widths
+    fn servo_commands() -> Vec<(Instant, f32)> {
+        let c = ThrottleValveConfig::default();
+        let period_us = config::DUMMY_PWM_PERIOD.as_micros() as f32;
+        dummy_hal::pwm_history(config::DUMMY_THROTTLE_PWM_CHANNEL)
+            .into_iter()
+            .map(|(time, duty)| (time, (duty * period_us - c.closed_pulse_us) / (c.open_pulse_us - c.closed_pulse_us)))
+            .collect()
+    }
+
+    #[test]
+    fn moves_at_the_slew_limit_and_closes_at_once() {
+        let _exclusive = sim::exclusive();
+        let mut valve = valve();
+        // The servo starts open; give it time to close
+        for _ in 0..20 {
+            sim::sleep(STEP);
+            valve.update().unwrap();
+        }
+        valve.check_tracking().unwrap();
+
+        valve.set_target(1.0).unwrap();
+        for _ in 0..40 {
+            sim::sleep(STEP);
+            valve.update().unwrap();
+            valve.check_tracking().unwrap(); // The servo keeps up with the limit
+        }
+        let commands = servo_commands();
+        let max_slew = ThrottleValveConfig::default().max_slew_rate;
+        for pair in commands.windows(2) {
+            let dt = (pair[1].0 - pair[0].0).as_secs_f32();
+            assert!(pair[1].1 - pair[0].1 <= max_slew * dt + 1e-4, "{:?}", pair);
+        }
+        // Full travel takes 1 / max_slew
+        let (start, _) = commands[0];
+        let (opened, position) = *commands.last().unwrap();
+        assert!((position - 1.0).abs() < 1e-4);
+        assert!(opened - start >= Duration::from_secs_f32(1.0 / max_slew) + Duration::from_millis(400));
+
+        let count = commands.len();
+        valve.close().unwrap();
+        let commands = servo_commands();
+        assert_eq!(commands.len(), count + 1);
+        assert!(commands[count].1.abs() < 1e-4);
+        assert_eq!(valve.commanded(), 0.0);
+    }
+
+    #[test]
+    fn a_valve_off_its_command_is_an_actuator_fault() {
+        let _exclusive = sim::exclusive();
+        let mut valve = valve();
+        // Still open from before power-up, commanded closed
+        assert!(matches!(valve.check_tracking(), Err(RocketError::Driver(DriverError::ActuatorFault(_)))));
+        assert!(valve.tracking_error().unwrap() > 0.9);
+        sim::sleep(Duration::from_millis(400));
+        valve.check_tracking().unwrap();
+        assert!(valve.position().unwrap().abs() < 0.01);
+    }
+}
@@ -18,6 +18,9 @@ pub enum DriverError {
     CommunicationError(String),
     InvalidData,
     ConfigurationFailed,
+    UnexpectedDevice(u8), // Chip/WHO_AM_I id read back from the bus
+    CalibrationFailed(String),
+    ActuatorFault(String), // Actuator not where it was commanded
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +28,7 @@ pub enum ComponentError {
     DriverError(DriverError),
     LogicError(String),
     NotInitialized,
+    Interlock(String), // Refused by the safety interlock (the InterlockError, as text)
 }
 
 // Top-level error combining others
@@ -85,3 +89,5 @@ impl From<DriverError> for ComponentError {
 }
 
 pub type Result<T> = std::result::Result<T, RocketError>;
+pub type HalResult<T> = std::result::Result<T, HalError>;
+pub type DriverResult<T> = std::result::Result<T, DriverError>;
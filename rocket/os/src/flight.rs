@@ -0,0 +1,466 @@
+// Flight software bring-up on the simulated board: drivers, components and
+// tasks, plus the ordered shutdown. Shared by the flight binary and the
+// ground station, which flies the vehicle in-process.
+use crate::config;
+use crate::error::{self, Result};
+use crate::kernel::{task::{self, TaskConfig}, sync::{Channel, Mutex, OverflowPolicy, sleep}};
+use crate::hal::dummy_hal::{self, DummyPin, DummySpi, DummyDelay, DummyPwm, DummyAdc};
+use crate::hal::interface::{FullHardwareAbstraction, I2cBus, InputPin, DelayMs};
+use crate::drivers::{imu::{Imu, ImuConfig, AccelRange}, imu_calibration::ImuCalibration, high_g_accel::HighGAccel, barometer::Barometer, valve::Valve, pyro::PyroChannel, throttle_valve::{ThrottleValve, ThrottleValveConfig}, pressure_transducer::PressureTransducer, radio::{Radio, RadioConfig}};
+use crate::components::{
+    navigation::{Navigation, SharedNavState},
+    estimator::EstimatorConfig,
+    attitude::AhrsConfig,
+    engine_control::{EngineControl, EngineCommand, IgnitionConfig},
+    engine_sensors::EngineSensors,
+    apogee_control::{ApogeeControl, ApogeeControlConfig},
+    recovery::{Recovery, RecoveryConfig},
+    interlock::SafetyInterlock,
+    telemetry::Telemetry,
+    uplink::Uplink,
+    flight_state::{FlightStateMachine, FlightCriteria},
+};
+use std::{sync::Arc, time::Duration};
+
+type FlightTelemetry = Telemetry<DummySpi, DummyPin, DummyPin, DummyDelay, DummyPin, DummyPin>;
+
+const COMMAND_RESPONSE_QUEUE_LEN: usize = 8;
+const DEPLOYMENT_QUEUE_LEN: usize = 4;
+const ARM_HOLD_POLL: Duration = Duration::from_millis(100); // Sequencer re-check while holding
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlightConfig {
+    pub countdown: Duration, // From start to ignition
+    // Arm at the start of the countdown. Off when a ground station is in the
+    // loop: the countdown then holds at zero until the ground arms.
+    pub auto_arm: bool,
+}
+
+impl Default for FlightConfig {
+    fn default() -> Self {
+        FlightConfig { countdown: Duration::from_secs(5), auto_arm: true }
+    }
+}
+
+// The running flight software: its tasks and the components the shutdown
+// sequence needs to reach.
+pub struct FlightSoftware {
+    pub nav_state: SharedNavState,
+    pub flight_state: Arc<Mutex<FlightStateMachine>>,
+    pub engine_control: Arc<Mutex<EngineControl<DummyPin, DummyPin>>>,
+    pub recovery: Arc<Mutex<Recovery<DummyPin, DummyPin>>>,
+    apogee_control: Arc<Mutex<ApogeeControl<DummyPwm, DummyAdc, DummyPin, DummyPin>>>,
+    telemetry: Arc<Mutex<FlightTelemetry>>,
+    nav_handle: task::TaskHandle,
+    control_handle: task::TaskHandle,
+    recovery_handle: task::TaskHandle,
+    sequence_handle: task::TaskHandle,
+    telemetry_handle: task::TaskHandle,
+    uplink_handle: task::TaskHandle,
+}
+
+// Bring up the hardware, drivers and components, and spawn the flight tasks
+pub fn start(flight_config: FlightConfig) -> Result<FlightSoftware> {
+    // --- Initialization ---
+    println!("[Flight] Initializing HAL...");
+    let board_hal = dummy_hal::get_dummy_hal(); // Get the singleton HAL instance
+
+    // Get peripheral instances from the HAL
+    let i2c_bus = board_hal.get_i2c_bus(0).ok_or(error::RocketError::Configuration("Failed to get I2C bus 0".into()))?;
+    let spi_bus = board_hal.get_spi_bus(config::DUMMY_RADIO_SPI_BUS).ok_or(error::RocketError::Configuration("Failed to get SPI bus".into()))?;
+    let delay_timer = board_hal.get_delay_timer();
+    let throttle_pwm = board_hal.get_pwm_channel(config::DUMMY_THROTTLE_PWM_CHANNEL).ok_or(error::RocketError::Configuration("Failed to get throttle PWM channel".into()))?;
+    let adc = board_hal.get_adc(config::DUMMY_ADC_ID).ok_or(error::RocketError::Configuration("Failed to get ADC".into()))?;
+
+    // Setup GPIO pins (using unwrap for simplicity in example, prefer proper error handling)
+    let fuel_valve_pin = board_hal.get_gpio_pin(config::DUMMY_VALVE_PIN).unwrap();
+    let oxidizer_valve_pin = board_hal.get_gpio_pin(config::DUMMY_VALVE_PIN + 1).unwrap(); // Use next pin
+    let fuel_continuity_pin = board_hal.get_gpio_pin(config::DUMMY_VALVE_CONTINUITY_PIN).unwrap();
+    let oxidizer_continuity_pin = board_hal.get_gpio_pin(config::DUMMY_VALVE_CONTINUITY_PIN + 1).unwrap();
+    let arm_switch_pin = board_hal.get_gpio_pin(config::DUMMY_ARM_SWITCH_PIN).unwrap();
+    let igniter_pin = board_hal.get_gpio_pin(config::DUMMY_IGNITER_PIN).unwrap();
+    let igniter_continuity_pin = board_hal.get_gpio_pin(config::DUMMY_IGNITER_CONTINUITY_PIN).unwrap();
+    let drogue_pyro_pin = board_hal.get_gpio_pin(config::DUMMY_DROGUE_PYRO_PIN).unwrap();
+    let main_pyro_pin = board_hal.get_gpio_pin(config::DUMMY_MAIN_PYRO_PIN).unwrap();
+    let drogue_continuity_pin = board_hal.get_gpio_pin(config::DUMMY_DROGUE_CONTINUITY_PIN).unwrap();
+    let main_continuity_pin = board_hal.get_gpio_pin(config::DUMMY_MAIN_CONTINUITY_PIN).unwrap();
+    let radio_cs_pin = board_hal.get_gpio_pin(config::DUMMY_RADIO_CS_PIN).unwrap();
+    let radio_irq_pin = board_hal.get_gpio_pin(config::DUMMY_RADIO_IRQ_PIN).unwrap();
+    let imu_int_pin = board_hal.get_gpio_pin(config::DUMMY_IMU_INT_PIN).unwrap();
+
+
+    println!("[Flight] Initializing Drivers...");
+    // Create driver instances, wrapped in Arc<Mutex> for sharing across tasks (threads)
+    // Clone peripherals if they need to be used by multiple drivers independently (like Delay)
+    // Low-g IMU range for resolution on the pad and in descent; the high-g part covers boost.
+    // 200 Hz into the FIFO, drained in batches by the navigation loop.
+    let imu_config = ImuConfig { accel_range: AccelRange::G4, sample_rate_divider: 4, ..ImuConfig::default() };
+    let mut imu = Imu::new(i2c_bus.clone(), imu_int_pin, delay_timer, config::DUMMY_IMU_ADDR, imu_config)?;
+    calibrate_imu(&mut imu);
+    imu.start_fifo()?;
+    let imu_driver = Arc::new(Mutex::new(imu));
+    let high_g_driver = Arc::new(Mutex::new(HighGAccel::new(i2c_bus.clone(), delay_timer, config::DUMMY_HIGH_G_ADDR)?));
+    let baro_driver = Arc::new(Mutex::new(Barometer::new(i2c_bus.clone(), delay_timer, config::DUMMY_BARO_ADDR)?));
+    let fuel_valve_driver = Valve::new(fuel_valve_pin)?;
+    let oxidizer_valve_driver = Valve::new(oxidizer_valve_pin)?;
+    let drogue_pyro_driver = PyroChannel::new("Drogue", drogue_pyro_pin, drogue_continuity_pin, config::PYRO_FIRE_PULSE)?;
+    let main_pyro_driver = PyroChannel::new("Main", main_pyro_pin, main_continuity_pin, config::PYRO_FIRE_PULSE)?;
+    let throttle_valve_driver = ThrottleValve::new(throttle_pwm, adc.clone(), config::DUMMY_THROTTLE_FEEDBACK_CHANNEL, ThrottleValveConfig::default())?;
+    let fuel_tank_transducer = PressureTransducer::new(adc.clone(), config::DUMMY_FUEL_TANK_PRESSURE_CHANNEL, config::FUEL_TANK_TRANSDUCER_CAL)?;
+    let oxidizer_tank_transducer = PressureTransducer::new(adc.clone(), config::DUMMY_OXIDIZER_TANK_PRESSURE_CHANNEL, config::OXIDIZER_TANK_TRANSDUCER_CAL)?;
+    let chamber_transducer = PressureTransducer::new(adc, config::DUMMY_CHAMBER_PRESSURE_CHANNEL, config::CHAMBER_TRANSDUCER_CAL)?;
+    let radio_driver = Arc::new(Mutex::new(Radio::new(spi_bus, radio_cs_pin, radio_irq_pin, delay_timer, RadioConfig::default())?));
+
+
+    println!("[Flight] Initializing Components...");
+    // Create shared state objects
+    let shared_nav_state = SharedNavState::new();
+
+    // Create component instances
+    let navigation_component = Navigation::new(
+        imu_driver.clone(),
+        high_g_driver.clone(),
+        baro_driver.clone(),
+        shared_nav_state.clone(),
+        EstimatorConfig::default(),
+        AhrsConfig::default(),
+    );
+    let flight_state_component = Arc::new(Mutex::new(FlightStateMachine::new(
+        shared_nav_state.clone(),
+        FlightCriteria::default(),
+    )));
+    // The valves and igniter go to the interlock and nowhere else; EngineControl commands them through it
+    let interlock = SafetyInterlock::new(
+        fuel_valve_driver,
+        oxidizer_valve_driver,
+        igniter_pin,
+        arm_switch_pin,
+        fuel_continuity_pin,
+        oxidizer_continuity_pin,
+        igniter_continuity_pin,
+    )?;
+    let engine_control_component = Arc::new(Mutex::new(EngineControl::new(
+        interlock,
+        shared_nav_state.clone(),
+        IgnitionConfig::default(),
+    )));
+    let engine_sensors_component = Arc::new(Mutex::new(EngineSensors::new(
+        fuel_tank_transducer,
+        oxidizer_tank_transducer,
+        chamber_transducer,
+        engine_control_component.clone(),
+    )));
+    let apogee_control_component = Arc::new(Mutex::new(ApogeeControl::new(
+        throttle_valve_driver,
+        engine_control_component.clone(),
+        shared_nav_state.clone(),
+        ApogeeControlConfig::default(),
+    )));
+    // Share radio driver, nav state, flight phase and engine state with Telemetry
+    // Uplink hands its ACK/NACKs, and Recovery its deployments, to Telemetry to send down
+    let (response_tx, response_rx) = Channel::new(COMMAND_RESPONSE_QUEUE_LEN, OverflowPolicy::OverwriteOldest).split();
+    let (deployment_tx, deployment_rx) = Channel::new(DEPLOYMENT_QUEUE_LEN, OverflowPolicy::OverwriteOldest).split();
+    let recovery_component = Arc::new(Mutex::new(Recovery::new(
+        drogue_pyro_driver,
+        main_pyro_driver,
+        shared_nav_state.clone(),
+        flight_state_component.clone(),
+        RecoveryConfig::default(),
+        deployment_tx,
+    )?));
+    let telemetry_component = Telemetry::new(
+        radio_driver.clone(),
+        shared_nav_state.clone(),
+        flight_state_component.clone(),
+        engine_control_component.clone(),
+        response_rx,
+        deployment_rx,
+    )?;
+    let uplink_component = Uplink::new(
+        radio_driver.clone(),
+        engine_control_component.clone(),
+        flight_state_component.clone(),
+        response_tx,
+        &config::UPLINK_KEY,
+        config::UPLINK_SEQUENCE_PATH,
+    );
+
+    // Wrap mutable components in Mutex for task access
+    let navigation_component = Arc::new(Mutex::new(navigation_component));
+    let telemetry_component = Arc::new(Mutex::new(telemetry_component));
+    let uplink_component = Arc::new(Mutex::new(uplink_component));
+
+
+    // --- Task Definitions ---
+    println!("[Flight] Spawning Tasks...");
+
+    // Navigation Task (also advances the flight phase from the fresh state)
+    let nav_handle = {
+        let nav_comp = Arc::clone(&navigation_component);
+        let flight_comp = Arc::clone(&flight_state_component);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "Navigation",
+                period: config::NAV_LOOP_RATE,
+                deadline: config::NAV_LOOP_RATE,
+                priority: config::NAV_TASK_PRIORITY,
+            },
+            move || {
+                nav_comp.lock()?.update()?;
+                flight_comp.lock()?.update()
+            },
+        )
+    };
+
+    // Control Task (pressures in, Engine Control, then throttle and cutoff for the target apogee)
+    let control_handle = {
+        let sensors_comp = Arc::clone(&engine_sensors_component);
+        let engine_ctrl_comp = Arc::clone(&engine_control_component);
+        let apogee_comp = Arc::clone(&apogee_control_component);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "Control",
+                period: config::CONTROL_LOOP_RATE,
+                deadline: config::CONTROL_LOOP_RATE,
+                priority: config::CONTROL_TASK_PRIORITY,
+            },
+            move || {
+                // A fault in one step is reported, but must not hold up the
+                // next: a transducer fault the engine sequence, and an engine
+                // fault the throttle (the apogee update closes it after an abort)
+                let sensors = sensors_comp.lock()?.update();
+                let engine = engine_ctrl_comp.lock()?.update();
+                let apogee = apogee_comp.lock()?.update();
+                engine.and(apogee).and(sensors)
+            },
+        )
+    };
+
+    // Recovery Task: parachutes, in a task of its own so no engine fault can hold it up
+    let recovery_handle = {
+        let recovery_comp = Arc::clone(&recovery_component);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "Recovery",
+                period: config::RECOVERY_LOOP_RATE,
+                deadline: config::RECOVERY_LOOP_RATE,
+                priority: config::RECOVERY_TASK_PRIORITY,
+            },
+            move || recovery_comp.lock()?.update(),
+        )
+    };
+
+    // Launch Sequence (one-shot): countdown, hold until armed, then hand
+    // over to engine control
+    let sequence_handle = {
+        let engine_ctrl_comp = Arc::clone(&engine_control_component);
+        task::spawn("Sequencer", move || -> Result<()> {
+            if flight_config.auto_arm {
+                engine_ctrl_comp.lock()?.arm()?;
+            }
+            println!("[Sequencer] Waiting {:?} before ignition attempt...", flight_config.countdown);
+            sleep(flight_config.countdown);
+            let mut holding = false;
+            while !task::should_stop() && !engine_ctrl_comp.lock()?.armed() {
+                if !holding {
+                    println!("[Sequencer] Holding: waiting for the engine to be armed.");
+                    holding = true;
+                }
+                sleep(ARM_HOLD_POLL);
+            }
+            if task::should_stop() {
+                println!("[Sequencer] Shutdown during countdown, ignition cancelled.");
+                return Ok(());
+            }
+            engine_ctrl_comp.lock()?.execute_command(EngineCommand::Ignite)
+        })
+    };
+
+    // Telemetry Task
+    let telemetry_handle = {
+        let telem_comp = Arc::clone(&telemetry_component);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "Telemetry",
+                period: config::TELEMETRY_LOOP_RATE,
+                deadline: config::TELEMETRY_LOOP_RATE,
+                priority: config::TELEMETRY_TASK_PRIORITY,
+            },
+            move || telem_comp.lock()?.run_cycle(),
+        )
+    };
+
+    // Uplink Task: ground commands
+    let uplink_handle = {
+        let uplink_comp = Arc::clone(&uplink_component);
+        task::spawn_periodic(
+            TaskConfig {
+                name: "Uplink",
+                period: config::UPLINK_LOOP_RATE,
+                deadline: config::UPLINK_LOOP_RATE,
+                priority: config::UPLINK_TASK_PRIORITY,
+            },
+            move || uplink_comp.lock()?.poll(),
+        )
+    };
+
+    Ok(FlightSoftware {
+        nav_state: shared_nav_state,
+        flight_state: flight_state_component,
+        engine_control: engine_control_component,
+        recovery: recovery_component,
+        apogee_control: apogee_control_component,
+        telemetry: telemetry_component,
+        nav_handle,
+        control_handle,
+        recovery_handle,
+        sequence_handle,
+        telemetry_handle,
+        uplink_handle,
+    })
+}
+
+impl FlightSoftware {
+    // Every task has been asked to stop; wind down in dependency order.
+    pub fn shutdown(self) -> Result<()> {
+        println!("[Flight] Shutting down...");
+        // 1. Nothing may command the engine any more
+        report_join(self.uplink_handle);
+        report_join(self.sequence_handle);
+        report_join(self.control_handle);
+        // 2. Safe the propulsion system (valves and throttle), and let a pyro pulse in progress run its full length
+        if let Err(e) = self.engine_control.lock()?.safe() {
+            eprintln!("[Flight] Error safing valves: {}", e);
+        }
+        if let Err(e) = self.apogee_control.lock()?.safe() {
+            eprintln!("[Flight] Error closing the throttle: {}", e);
+        }
+        report_join(self.recovery_handle);
+        if let Err(e) = self.recovery.lock()?.finish_pulses() {
+            eprintln!("[Flight] Error ending pyro pulses: {}", e);
+        }
+        // 3. Stop sensing, then flush one last telemetry frame showing the safed state
+        report_join(self.nav_handle);
+        report_join(self.telemetry_handle);
+        if let Err(e) = self.telemetry.lock()?.send_engine_state() {
+            eprintln!("[Flight] Error sending final telemetry: {}", e);
+        }
+        Ok(())
+    }
+}
+
+// Start from the persisted calibration (or the bench temperature
+// coefficients), re-estimate the biases on the pad and persist the result.
+// A failure leaves the IMU on the starting calibration.
+fn calibrate_imu<I2C: I2cBus, INT: InputPin, DELAY: DelayMs>(imu: &mut Imu<I2C, INT, DELAY>) {
+    let stored = ImuCalibration::load(config::IMU_CALIBRATION_PATH).unwrap_or_else(|e| {
+        println!("[Flight] No stored IMU calibration ({}), using bench coefficients.", e);
+        ImuCalibration::with_temp_coeffs(config::IMU_GYRO_TEMP_COEFF, config::IMU_ACCEL_TEMP_COEFF)
+    });
+    imu.set_calibration(stored);
+    match imu.calibrate(config::IMU_CALIBRATION_SAMPLES, config::IMU_CALIBRATION_INTERVAL_MS) {
+        Ok(calibration) => {
+            if let Err(e) = calibration.save(config::IMU_CALIBRATION_PATH) {
+                eprintln!("[Flight] {}", e);
+            }
+        }
+        Err(e) => eprintln!("[Flight] IMU calibration failed, keeping the stored one: {:?}", e),
+    }
+}
+
+// Join a task during shutdown; a failed task must not stop the sequence
+fn report_join(handle: task::TaskHandle) {
+    let name = handle.name();
+    if let Err(e) = handle.join() {
+        eprintln!("[Flight] Task {} ended with error: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{engine_control::EngineState, recovery::Parachute};
+    use crate::kernel::sim::{self, ClockMode};
+
+    const COUNTDOWN: Duration = Duration::from_secs(1);
+
+    fn launch() -> FlightSoftware {
+        sim::init(ClockMode::Simulated, config::SIM_SEED);
+        dummy_hal::reset();
+        task::reset_shutdown();
+        dummy_hal::set_arm_switch(true);
+        start(FlightConfig { countdown: COUNTDOWN, auto_arm: true }).unwrap()
+    }
+
+    fn pin_high(pin: u8) -> bool {
+        dummy_hal::get_dummy_hal().get_gpio_pin(pin).unwrap().is_high().unwrap()
+    }
+
+    #[test]
+    fn shutdown_mid_burn_closes_the_valves_and_the_throttle() {
+        let _exclusive = sim::exclusive();
+        let flight = launch();
+
+        task::run_for(COUNTDOWN + Duration::from_secs(2));
+        assert_eq!(flight.engine_control.lock().unwrap().state(), EngineState::Running);
+        assert!(pin_high(config::DUMMY_VALVE_PIN) && pin_high(config::DUMMY_VALVE_PIN + 1));
+        assert!(dummy_hal::throttle_servo_position() > 0.5);
+
+        let engine_control = flight.engine_control.clone();
+        flight.shutdown().unwrap();
+
+        assert_eq!(engine_control.lock().unwrap().state(), EngineState::Shutdown);
+        assert!(!pin_high(config::DUMMY_VALVE_PIN) && !pin_high(config::DUMMY_VALVE_PIN + 1));
+        sim::sleep(Duration::from_secs(1));
+        assert!(dummy_hal::throttle_servo_position() < 0.01);
+        task::reset_shutdown();
+    }
+
+    #[test]
+    fn engine_fault_closes_the_throttle_in_the_same_control_cycle() {
+        let _exclusive = sim::exclusive();
+        let flight = launch();
+
+        // The fuel valve coil fails while the igniter is burning: the
+        // sequence can't open it, aborts, and reports the refusal
+        sim::sleep(COUNTDOWN + Duration::from_millis(100));
+        dummy_hal::set_open_circuit(config::DUMMY_VALVE_PIN, true);
+        while flight.engine_control.lock().unwrap().state() != EngineState::Aborted {
+            assert!(sim::now().as_secs_f64() < 5.0, "never aborted");
+            sim::sleep(Duration::from_millis(1));
+        }
+        let aborted_at = sim::now();
+
+        // Closed by the same job, not the next one
+        let history = dummy_hal::pwm_history(config::DUMMY_THROTTLE_PWM_CHANNEL);
+        let closed = history[0].1;
+        let (commanded_at, duty) = *history.iter().rfind(|(time, _)| *time <= aborted_at).unwrap();
+        assert_eq!(duty, closed);
+        assert!(aborted_at.duration_since(commanded_at) < Duration::from_millis(1));
+
+        task::request_shutdown("test");
+        flight.shutdown().unwrap();
+        task::reset_shutdown();
+    }
+
+    #[test]
+    fn shutdown_mid_deployment_finishes_the_pyro_pulse() {
+        let _exclusive = sim::exclusive();
+        let flight = launch();
+
+        // Fly to apogee and stop while the drogue e-match is still being fired
+        while !pin_high(config::DUMMY_DROGUE_PYRO_PIN) {
+            assert!(sim::now().as_secs_f64() < 60.0, "drogue never fired");
+            sim::sleep(config::RECOVERY_LOOP_RATE);
+        }
+        task::request_shutdown("test");
+        let fired_at = sim::now();
+        let recovery = flight.recovery.clone();
+        flight.shutdown().unwrap();
+
+        assert!(recovery.lock().unwrap().fired(Parachute::Drogue));
+        assert!(!pin_high(config::DUMMY_DROGUE_PYRO_PIN) && !pin_high(config::DUMMY_MAIN_PYRO_PIN));
+        assert!(sim::now().duration_since(fired_at) >= config::PYRO_FIRE_PULSE - config::RECOVERY_LOOP_RATE);
+        task::reset_shutdown();
+    }
+}
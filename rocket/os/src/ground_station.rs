@@ -0,0 +1,606 @@
+// Ground station: the ground end of the radio link. Drains the ground radio,
+// decodes telemetry frames, keeps link statistics, prints a text dashboard
+// and records every packet to a CSV file. Commands go up one at a time and
+// are resent until the vehicle answers them or the retries run out.
+//
+// The link is half duplex and the vehicle spends much of each telemetry
+// cycle transmitting, so commands are only sent straight after a downlink
+// frame arrives: the vehicle has just gone back to listening.
+use crate::hal::interface::{SpiBus, OutputPin, InputPin, DelayMs};
+use crate::drivers::radio::{Radio, PacketStatus};
+use crate::components::telemetry_frame::{CommandResponse, EngineMessage, EventMessage, Frame, HealthMessage, Message, NavMessage};
+use crate::components::uplink_frame::{Command, CommandFrame, CommandResult, SequenceRecord};
+use crate::kernel::sim::Instant;
+use crate::kernel::sync;
+use crate::config;
+use crate::error::{Result, RocketError};
+use std::fs::File;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufWriter, Write};
+
+// Received frames and sent commands (type "uplink", no signal columns)
+const RECORDING_HEADER: &str = "time_s,rssi_dbm,snr_db,seq,timestamp_ms,type,contents,raw";
+const MAX_PACKET_LEN: usize = 255; // SX127x FIFO payload limit
+const RECENT_EVENTS: usize = 5; // Shown on the dashboard
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkQuality {
+    pub packets_received: u32, // Decoded successfully
+    pub packets_lost: u32,     // Inferred from gaps in the sequence number
+    pub crc_errors: u32,       // Dropped by the radio
+    pub undecodable: u32,      // Passed the radio's CRC but not a valid frame
+    pub last_status: Option<PacketStatus>,
+    pub last_rx: Option<Instant>,
+}
+
+// What became of an uplink command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutcome {
+    Answered(CommandResult),
+    TimedOut, // No answer after every retry
+}
+
+struct PendingCommand {
+    frame: CommandFrame,
+    first_sent: Instant,
+    last_sent: Instant,
+    resends: u32,
+}
+
+impl LinkQuality {
+    // Fraction of the packets sent that never made it (lost or corrupted)
+    pub fn loss_ratio(&self) -> f32 {
+        let missing = self.packets_lost + self.undecodable;
+        let total = self.packets_received + missing;
+        if total == 0 { 0.0 } else { missing as f32 / total as f32 }
+    }
+}
+
+pub struct GroundStation<SPI, CS, IRQ, DELAY>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    DELAY: DelayMs,
+{
+    radio: Radio<SPI, CS, IRQ, DELAY>,
+    recording: BufWriter<File>,
+    nav: Option<NavMessage>,
+    engine: Option<EngineMessage>,
+    health: Option<HealthMessage>,
+    events: VecDeque<(u32, EventMessage)>, // Most recent last, with their vehicle time in ms
+    vehicle_time_ms: Option<u32>,          // Timestamp of the newest frame
+    link: LinkQuality,
+    next_sequence: Option<u16>,
+    key: Vec<u8>,
+    sequence_path: String, // Where the last command sequence number used is kept
+    next_command_sequence: u32,
+    queued_commands: VecDeque<CommandFrame>,
+    pending_command: Option<PendingCommand>,
+    outcomes: BTreeMap<u32, (Command, CommandOutcome)>,
+    uplink_window: bool, // A downlink frame just arrived
+}
+
+impl<SPI, CS, IRQ, DELAY> GroundStation<SPI, CS, IRQ, DELAY>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    IRQ: InputPin,
+    DELAY: DelayMs,
+{
+    // Takes an initialised radio (already listening) and starts a new
+    // recording. `key` authenticates commands and must match the vehicle's.
+    // Command sequence numbers carry on from the record at `sequence_path`,
+    // since the vehicle rejects any it has already seen.
+    pub fn new(radio: Radio<SPI, CS, IRQ, DELAY>, recording_path: &str, sequence_path: &str, key: &[u8]) -> Result<Self> {
+        let file = File::create(recording_path)
+            .map_err(|e| RocketError::Configuration(format!("Failed to create recording {}: {}", recording_path, e)))?;
+        let mut recording = BufWriter::new(file);
+        writeln!(recording, "{}", RECORDING_HEADER).map_err(recording_error)?;
+        println!("[Ground] Recording to {}", recording_path);
+        let last_sequence = SequenceRecord::load(sequence_path).map_or_else(
+            |e| {
+                println!("[Ground] No command sequence record ({}), starting from #1.", e);
+                0
+            },
+            |record| record.sequence,
+        );
+        Ok(Self {
+            radio,
+            recording,
+            nav: None,
+            engine: None,
+            health: None,
+            events: VecDeque::with_capacity(RECENT_EVENTS),
+            vehicle_time_ms: None,
+            link: LinkQuality::default(),
+            next_sequence: None,
+            key: key.to_vec(),
+            sequence_path: sequence_path.to_string(),
+            next_command_sequence: last_sequence + 1,
+            queued_commands: VecDeque::new(),
+            pending_command: None,
+            outcomes: BTreeMap::new(),
+            uplink_window: false,
+        })
+    }
+
+    pub fn nav(&self) -> Option<NavMessage> {
+        self.nav
+    }
+
+    pub fn engine(&self) -> Option<EngineMessage> {
+        self.engine
+    }
+
+    pub fn health(&self) -> Option<HealthMessage> {
+        self.health
+    }
+
+    pub fn link_quality(&self) -> LinkQuality {
+        self.link
+    }
+
+    // Queue a command for the vehicle. Returns its sequence number, under
+    // which `command_outcome` reports the answer.
+    pub fn send_command(&mut self, command: Command) -> u32 {
+        let sequence = self.next_command_sequence;
+        self.next_command_sequence += 1;
+        if let Err(e) = (SequenceRecord { sequence, result: None }).save(&self.sequence_path) {
+            eprintln!("[Ground] {}", e);
+        }
+        self.queued_commands.push_back(CommandFrame { sequence, command });
+        sequence
+    }
+
+    // None while the command is queued or awaiting its answer
+    pub fn command_outcome(&self, sequence: u32) -> Option<CommandOutcome> {
+        self.outcomes.get(&sequence).map(|(_, outcome)| *outcome)
+    }
+
+    // Take in whatever the radio has received since the last poll, then
+    // resend or send the next command as due. Nothing comes in while a
+    // command is still going out.
+    pub fn poll(&mut self) -> Result<()> {
+        if !self.radio.transmit_done()? {
+            return Ok(());
+        }
+        self.receive()?;
+        self.service_uplink()
+    }
+
+    fn receive(&mut self) -> Result<()> {
+        let mut buffer = [0u8; MAX_PACKET_LEN];
+        let len = self.radio.receive_packet(&mut buffer)?;
+        self.link.crc_errors = self.radio.stats().crc_errors;
+        if len == 0 {
+            return Ok(());
+        }
+        let now = sync::get_time();
+        let raw = &buffer[..len];
+        let status = self.radio.last_packet_status();
+        self.link.last_status = status;
+        self.link.last_rx = Some(now);
+
+        let frame = match Frame::decode(raw) {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.link.undecodable += 1;
+                println!("[Ground] Undecodable {}-byte packet: {}", len, e);
+                return self.record(now, status, Err(&e.to_string()), raw);
+            }
+        };
+        if let Some(expected) = self.next_sequence {
+            // Sequence numbers wrap; anything "behind" is a duplicate or reordering
+            let gap = frame.sequence.wrapping_sub(expected);
+            if gap < u16::MAX / 2 {
+                self.link.packets_lost += gap as u32;
+            }
+        }
+        self.next_sequence = Some(frame.sequence.wrapping_add(1));
+        self.link.packets_received += 1;
+        self.uplink_window = true;
+        self.vehicle_time_ms = Some(frame.timestamp_ms);
+        match frame.message {
+            Message::Nav(m) => self.nav = Some(m),
+            Message::Engine(m) => self.engine = Some(m),
+            Message::Health(m) => self.health = Some(m),
+            Message::Event(event) => {
+                println!("[Ground] Event at T+{:.2} s: {}", frame.timestamp_ms as f32 / 1000.0, event_text(&event));
+                if self.events.len() == RECENT_EVENTS {
+                    self.events.pop_front();
+                }
+                self.events.push_back((frame.timestamp_ms, event));
+            }
+            Message::CommandResponse(response) => self.command_answered(response, now),
+        }
+        self.record(now, status, Ok(&frame), raw)
+    }
+
+    fn command_answered(&mut self, response: CommandResponse, now: Instant) {
+        match &self.pending_command {
+            Some(pending) if pending.frame.sequence == response.sequence => {
+                println!(
+                    "[Ground] Command #{} {:?}: {:?} after {} ms",
+                    response.sequence,
+                    pending.frame.command,
+                    response.result,
+                    now.duration_since(pending.first_sent).as_millis()
+                );
+                let outcome = (pending.frame.command, CommandOutcome::Answered(response.result));
+                self.outcomes.insert(response.sequence, outcome);
+                self.pending_command = None;
+            }
+            _ => println!("[Ground] Answer to command #{} that isn't pending: {:?}", response.sequence, response.result),
+        }
+    }
+
+    fn service_uplink(&mut self) -> Result<()> {
+        let now = sync::get_time();
+        let window = std::mem::take(&mut self.uplink_window);
+        if let Some(pending) = &self.pending_command {
+            if now.duration_since(pending.last_sent) < config::GROUND_COMMAND_TIMEOUT {
+                return Ok(());
+            }
+            if pending.resends >= config::GROUND_COMMAND_RETRIES {
+                println!("[Ground] Command #{} {:?} timed out", pending.frame.sequence, pending.frame.command);
+                self.outcomes.insert(pending.frame.sequence, (pending.frame.command, CommandOutcome::TimedOut));
+                self.pending_command = None;
+            } else {
+                if window {
+                    // Same sequence number: the vehicle answers a repeat without re-executing it
+                    let frame = pending.frame;
+                    println!("[Ground] Resending command #{} {:?}", frame.sequence, frame.command);
+                    self.transmit(frame, now)?;
+                    if let Some(pending) = &mut self.pending_command {
+                        pending.last_sent = now;
+                        pending.resends += 1;
+                    }
+                }
+                return Ok(());
+            }
+        }
+        if !window {
+            return Ok(());
+        }
+        if let Some(frame) = self.queued_commands.pop_front() {
+            println!("[Ground] Sending command #{} {:?}", frame.sequence, frame.command);
+            self.transmit(frame, now)?;
+            self.pending_command = Some(PendingCommand { frame, first_sent: now, last_sent: now, resends: 0 });
+        }
+        Ok(())
+    }
+
+    fn transmit(&mut self, frame: CommandFrame, now: Instant) -> Result<()> {
+        let bytes = frame.encode(&self.key);
+        self.radio.start_transmit(&bytes)?; // Finished by a later poll
+        let mut row = format!("{:.3},,,{},,uplink,\"{:?}\",", now.as_secs_f64(), frame.sequence, frame.command);
+        for b in &bytes {
+            row.push_str(&format!("{:02x}", b));
+        }
+        writeln!(self.recording, "{}", row).map_err(recording_error)
+    }
+
+    // One text block with the latest vehicle state and link health
+    pub fn print_dashboard(&self) {
+        let now = sync::get_time();
+        println!("[Ground] ======== t = {:.1} s ========", now.as_secs_f64());
+        if let Some(t) = self.vehicle_time_ms {
+            println!("[Ground] Vehicle time: {:.1} s", t as f32 / 1000.0);
+        }
+        match &self.nav {
+            Some(n) => println!(
+                "[Ground] Phase: {:?} | Altitude: {:.1} m | Velocity: {:.1} m/s | Tilt: {:.1} deg",
+                n.phase,
+                n.altitude,
+                n.vertical_velocity,
+                n.tilt.to_degrees()
+            ),
+            None => println!("[Ground] No nav state received yet"),
+        }
+        match &self.engine {
+            Some(e) => println!(
+                "[Ground] Engine: {:?}{}{}{} | Fuel valve: {} | Oxidizer valve: {}",
+                e.state,
+                if e.armed { " ARMED" } else { " safe" },
+                if e.test_mode { " (test mode)" } else { "" },
+                if e.igniter_lit { " IGNITER LIT" } else { "" },
+                valve_text(e.fuel_valve_open),
+                valve_text(e.oxidizer_valve_open)
+            ),
+            None => println!("[Ground] No engine state received yet"),
+        }
+        if let Some(e) = &self.engine {
+            println!(
+                "[Ground] Pressures: fuel tank {:.1} bar | oxidizer tank {:.1} bar | chamber {:.1} bar",
+                e.fuel_tank_pressure / 1e5,
+                e.oxidizer_tank_pressure / 1e5,
+                e.chamber_pressure / 1e5
+            );
+        }
+        if let Some(h) = &self.health {
+            println!(
+                "[Ground] Health: IMU {:.1} C, {} FIFO overflows | {} baro rejections | {} deadline misses, {} task errors",
+                h.imu_temp, h.imu_overflows, h.baro_rejections, h.deadline_misses, h.task_errors
+            );
+        }
+        for (t, event) in &self.events {
+            println!("[Ground] Event T+{:.2} s: {}", *t as f32 / 1000.0, event_text(event));
+        }
+
+        let link = &self.link;
+        let state = match link.last_rx {
+            Some(at) if now.duration_since(at) <= config::GROUND_LINK_TIMEOUT => "UP",
+            Some(_) => "LOST",
+            None => "WAITING",
+        };
+        let signal = link
+            .last_status
+            .map_or("-".to_string(), |s| format!("RSSI {:.1} dBm, SNR {:.1} dB", s.rssi, s.snr));
+        let age = link
+            .last_rx
+            .map_or("-".to_string(), |at| format!("{:.1} s ago", now.duration_since(at).as_secs_f32()));
+        println!("[Ground] Link: {} | {} | last packet {}", state, signal, age);
+        println!(
+            "[Ground] Packets: {} received, {} lost, {} CRC errors, {} undecodable ({:.1}% loss)",
+            link.packets_received,
+            link.packets_lost,
+            link.crc_errors,
+            link.undecodable,
+            link.loss_ratio() * 100.0
+        );
+        if let Some(pending) = &self.pending_command {
+            println!(
+                "[Ground] Uplink: command #{} {:?} awaiting answer ({} resends)",
+                pending.frame.sequence, pending.frame.command, pending.resends
+            );
+        } else if let Some((sequence, (command, outcome))) = self.outcomes.iter().next_back() {
+            println!("[Ground] Uplink: last command #{} {:?}: {:?}", sequence, command, outcome);
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.recording.flush().map_err(recording_error)
+    }
+
+    // `frame` is the decoded frame, or why the packet couldn't be decoded
+    fn record(&mut self, rx_time: Instant, status: Option<PacketStatus>, frame: std::result::Result<&Frame, &str>, raw: &[u8]) -> Result<()> {
+        let mut row = format!("{:.3}", rx_time.as_secs_f64());
+        match status {
+            Some(s) => row.push_str(&format!(",{:.1},{:.2}", s.rssi, s.snr)),
+            None => row.push_str(",,"),
+        }
+        match frame {
+            Ok(f) => {
+                let contents = match &f.message {
+                    Message::Nav(m) => format!("{:?}", m),
+                    Message::Engine(m) => format!("{:?}", m),
+                    Message::Health(m) => format!("{:?}", m),
+                    Message::Event(m) => format!("{:?}", m),
+                    Message::CommandResponse(m) => format!("{:?}", m),
+                };
+                row.push_str(&format!(",{},{},{},\"{}\"", f.sequence, f.timestamp_ms, f.message.name(), contents));
+            }
+            Err(reason) => row.push_str(&format!(",,,invalid,\"{}\"", reason)),
+        }
+        row.push(',');
+        for b in raw {
+            row.push_str(&format!("{:02x}", b));
+        }
+        writeln!(self.recording, "{}", row).map_err(recording_error)
+    }
+}
+
+fn valve_text(open: bool) -> &'static str {
+    if open { "OPEN" } else { "closed" }
+}
+
+fn event_text(event: &EventMessage) -> String {
+    match event {
+        EventMessage::PhaseChange { from, to } => format!("phase {:?} -> {:?}", from, to),
+        EventMessage::EngineStateChange { from, to } => format!("engine {:?} -> {:?}", from, to),
+        EventMessage::EngineAborted(reason) => format!("ENGINE ABORT: {:?}", reason),
+        EventMessage::Deployment { parachute, trigger } => format!("{:?} deployed ({:?})", parachute, trigger),
+    }
+}
+
+fn recording_error(e: std::io::Error) -> RocketError {
+    RocketError::Configuration(format!("Failed to write ground recording: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{engine_control::EngineState, flight_state::FlightPhase, navigation::AccelSource};
+    use crate::drivers::radio::RadioConfig;
+    use crate::hal::dummy_hal::{self, DummyDelay, DummyPin, DummySpi};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::hal::radio_sim::LinkParams;
+    use crate::kernel::sim::{self, ClockMode};
+    use std::time::Duration;
+
+    type SimRadio = Radio<DummySpi, DummyPin, DummyPin, DummyDelay>;
+
+    const KEY: &[u8] = b"test-key";
+
+    const NAV: NavMessage = NavMessage {
+        altitude: 812.5,
+        vertical_velocity: -3.25,
+        vertical_accel: -9.5,
+        accel: [0.1, -0.2, 31.0],
+        gyro: [0.01, -0.02, 1.5],
+        tilt: 0.05,
+        sample_count: 1234,
+        phase: FlightPhase::Coast,
+        accel_source: AccelSource::LowG,
+    };
+
+    // A ground station recording to temporary files, and the vehicle's radio
+    struct Rig {
+        ground: GroundStation<DummySpi, DummyPin, DummyPin, DummyDelay>,
+        vehicle: SimRadio,
+        recording_path: String,
+        sequence_path: String,
+        next_sequence: u16,
+    }
+
+    impl Rig {
+        fn new(name: &str) -> Self {
+            sim::init(ClockMode::Simulated, 1);
+            dummy_hal::reset();
+            dummy_hal::set_link_params(LinkParams { packet_loss: 0.0, bit_error_rate: 0.0, fading: 0.0, ..LinkParams::default() });
+            let hal = dummy_hal::get_dummy_hal();
+            let radio = |bus, cs, irq| {
+                let pin = |id| hal.get_gpio_pin(id).unwrap();
+                Radio::new(hal.get_spi_bus(bus).unwrap(), pin(cs), pin(irq), hal.get_delay_timer(), RadioConfig::default()).unwrap()
+            };
+            let path = |file: &str| {
+                std::env::temp_dir()
+                    .join(format!("ground_{}_{}_{}", std::process::id(), name, file))
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            let (recording_path, sequence_path) = (path("recording.csv"), path("sequence.bin"));
+            let _ = std::fs::remove_file(&sequence_path);
+            let ground_radio = radio(config::DUMMY_GROUND_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_CS_PIN, config::DUMMY_GROUND_RADIO_IRQ_PIN);
+            let ground = GroundStation::new(ground_radio, &recording_path, &sequence_path, KEY).unwrap();
+            let vehicle = radio(config::DUMMY_RADIO_SPI_BUS, config::DUMMY_RADIO_CS_PIN, config::DUMMY_RADIO_IRQ_PIN);
+            Rig { ground, vehicle, recording_path, sequence_path, next_sequence: 0 }
+        }
+
+        // Send raw bytes down and let the ground station take them in
+        fn downlink_raw(&mut self, bytes: &[u8]) {
+            self.vehicle.send_packet(bytes).unwrap();
+            sim::sleep(Duration::from_millis(5));
+            self.ground.poll().unwrap();
+        }
+
+        fn downlink(&mut self, message: Message) {
+            let frame = Frame { sequence: self.next_sequence, timestamp_ms: sim::now().as_micros() as u32 / 1000, message };
+            self.next_sequence += 1;
+            self.downlink_raw(&frame.encode());
+        }
+
+        // The command the vehicle heard, once the ground's transmission is over
+        fn uplinked(&mut self) -> Option<CommandFrame> {
+            sim::sleep(Duration::from_millis(200));
+            self.ground.poll().unwrap();
+            let mut buffer = [0u8; MAX_PACKET_LEN];
+            let len = self.vehicle.receive_packet(&mut buffer).unwrap();
+            (len > 0).then(|| CommandFrame::decode(&buffer[..len], KEY).unwrap())
+        }
+
+        // Data rows of the recording so far
+        fn rows(&mut self) -> Vec<String> {
+            self.ground.flush().unwrap();
+            let recording = std::fs::read_to_string(&self.recording_path).unwrap();
+            let mut lines = recording.lines().map(str::to_string);
+            assert_eq!(lines.next().as_deref(), Some(RECORDING_HEADER));
+            lines.collect()
+        }
+    }
+
+    impl Drop for Rig {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.recording_path);
+            let _ = std::fs::remove_file(&self.sequence_path);
+        }
+    }
+
+    #[test]
+    fn receive_updates_the_state_and_records_the_frame() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("receive");
+        assert_eq!(rig.ground.nav(), None);
+
+        rig.downlink(Message::Nav(NAV));
+
+        assert_eq!(rig.ground.nav(), Some(NAV));
+        assert_eq!(rig.ground.engine(), None);
+        let link = rig.ground.link_quality();
+        assert_eq!((link.packets_received, link.packets_lost, link.undecodable), (1, 0, 0));
+        let rows = rig.rows();
+        assert_eq!(rows.len(), 1);
+        let fields: Vec<&str> = rows[0].splitn(7, ',').collect();
+        assert_eq!(fields[3], "0"); // Frame sequence number
+        assert_eq!(fields[5], Message::Nav(NAV).name());
+        assert!(fields[6].starts_with("\"NavMessage { altitude: 812.5,"), "{}", rows[0]);
+        // Ends with the raw packet, as received
+        let hex = rows[0].rsplit_once(',').unwrap().1;
+        let raw: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+        assert_eq!(Frame::decode(&raw).unwrap().message, Message::Nav(NAV));
+
+        let engine = EngineMessage {
+            state: EngineState::Running,
+            fuel_valve_open: true,
+            oxidizer_valve_open: true,
+            armed: true,
+            test_mode: false,
+            igniter_lit: false,
+            fuel_tank_pressure: 2.75e6,
+            oxidizer_tank_pressure: 2.5e6,
+            chamber_pressure: 1.9e6,
+        };
+        rig.downlink(Message::Engine(engine));
+        assert_eq!(rig.ground.engine(), Some(engine));
+        assert_eq!(rig.ground.nav(), Some(NAV));
+        assert_eq!(rig.rows().len(), 2);
+    }
+
+    #[test]
+    fn corrupted_frame_is_recorded_as_an_error() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("corrupted");
+        let mut bytes = Frame { sequence: 0, timestamp_ms: 0, message: Message::Nav(NAV) }.encode();
+        bytes[14] ^= 0x40; // Survives the radio's CRC, which covers the corrupted bytes
+
+        rig.downlink_raw(&bytes);
+        rig.ground.poll().unwrap();
+
+        assert_eq!(rig.ground.nav(), None);
+        assert_eq!(rig.ground.link_quality().undecodable, 1);
+        let rows = rig.rows();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].contains(",,,invalid,\"CRC mismatch\","), "{}", rows[0]);
+        let raw: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(rows[0].ends_with(&raw), "{}", rows[0]);
+
+        // The next good frame is taken as usual
+        rig.downlink(Message::Nav(NAV));
+        assert_eq!(rig.ground.nav(), Some(NAV));
+    }
+
+    #[test]
+    fn command_outcome_follows_the_vehicle_answer() {
+        let _exclusive = sim::exclusive();
+        let mut rig = Rig::new("outcome");
+        let sequence = rig.ground.send_command(Command::Ping);
+        assert_eq!(sequence, 1);
+
+        // Held until a downlink frame shows the vehicle is listening
+        rig.ground.poll().unwrap();
+        assert_eq!(rig.uplinked(), None);
+        rig.downlink(Message::Nav(NAV));
+        assert_eq!(rig.uplinked(), Some(CommandFrame { sequence, command: Command::Ping }));
+        assert_eq!(rig.ground.command_outcome(sequence), None);
+
+        rig.downlink(Message::CommandResponse(CommandResponse { sequence, result: CommandResult::Ack }));
+        assert_eq!(rig.ground.command_outcome(sequence), Some(CommandOutcome::Answered(CommandResult::Ack)));
+        let rows = rig.rows();
+        assert!(rows.iter().any(|row| row.contains(",uplink,\"Ping\",")), "{:?}", rows);
+
+        // Unanswered: resent on each downlink window, then given up
+        let sequence = rig.ground.send_command(Command::Ping);
+        let mut sent = 0;
+        while rig.ground.command_outcome(sequence).is_none() {
+            assert!(sent <= config::GROUND_COMMAND_RETRIES + 1, "still pending");
+            rig.downlink(Message::Nav(NAV));
+            if rig.uplinked().is_some() {
+                sent += 1;
+            }
+            sim::sleep(config::GROUND_COMMAND_TIMEOUT);
+        }
+        assert_eq!(sent, config::GROUND_COMMAND_RETRIES + 1);
+        assert_eq!(rig.ground.command_outcome(sequence), Some(CommandOutcome::TimedOut));
+    }
+}
@@ -1,15 +1,20 @@
 // Simulation implementation of the HAL traits
 use crate::hal::interface::*;
+use crate::hal::flight_sim::{VehicleParams, VehicleSim, VehicleState, atmosphere, STANDARD_GRAVITY};
+use crate::hal::radio_sim::{AirChannel, LinkParams, Sx127x};
+use crate::hal::servo_sim::ServoSim;
+use crate::hal::feed_sim::{FeedSim, Transducer};
+use crate::drivers::barometer::Calibration;
 use crate::error::{HalError, HalResult};
+use crate::kernel::sim::{self, Instant};
 use crate::config;
 use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::Mutex,
+    time::Duration,
 };
 use rand::Rng;
 use lazy_static::lazy_static;
-use chrono::Utc;
 
 // --- Simulated Hardware State ---
 // Use Mutex for interior mutability needed for simulation state.
@@ -17,24 +22,537 @@ use chrono::Utc;
 // WARNING: Global mutable state is generally discouraged, but simplifies this example.
 struct DummyHardwareState {
     gpio_pins: HashMap<u8, bool>, // Pin number -> state (true=high, false=low)
-    i2c_devices: HashMap<u8, Vec<u8>>, // Device address -> Register data
-    spi_devices: HashMap<u8, Vec<u8>>, // Bus ID -> Dummy data buffer
+    open_circuits: HashSet<u8>, // Valve and igniter pins whose circuit is broken
+    igniter_dud: bool,          // Igniter fires but doesn't light the engine
+    combustion: bool,           // Engine lit: needs the igniter to start, then both propellants
+    ematch_heating: HashMap<u8, f64>, // Pyro pin -> s its e-match has carried current without a break
+    i2c_devices: HashMap<u8, Vec<u8>>, // Device address -> Register data (256-byte map)
+    i2c_pointers: HashMap<u8, u8>,     // Device address -> Current register pointer
+    spi_devices: BTreeMap<u8, Sx127x>, // Bus ID -> Radio on that bus (ordered, for determinism)
+    air: AirChannel,                   // Between the radios
     last_delay: Instant,
+    vehicle: VehicleSim, // Physics model behind the simulated sensors
+    sim_epoch: Instant,  // Kernel time corresponding to vehicle time 0
+    imu_next_sample: Option<Instant>, // When the IMU latches its next sample (None: on the next access)
+    imu_fifo: VecDeque<u8>,
+    pwm_outputs: BTreeMap<u8, Vec<(Instant, f32)>>, // Channel -> every duty cycle set, in order
+    throttle_servo: ServoSim,
+    feed: FeedSim, // Tank and chamber pressures
 }
 
 impl DummyHardwareState {
     fn new() -> Self {
         let mut i2c_devices = HashMap::new();
-        // Pre-populate some dummy I2C device data (e.g., for IMU)
-        i2c_devices.insert(config::DUMMY_IMU_ADDR, vec![0u8; 14]); // 6 accel, 2 temp, 6 gyro
+        // Pre-populate the register maps of the simulated I2C devices
+        i2c_devices.insert(config::DUMMY_IMU_ADDR, imu_power_on_registers());
+        i2c_devices.insert(config::DUMMY_BARO_ADDR, baro_power_on_registers());
+        i2c_devices.insert(config::DUMMY_HIGH_G_ADDR, high_g_power_on_registers());
+
+        let mut spi_devices = BTreeMap::new();
+        spi_devices.insert(config::DUMMY_RADIO_SPI_BUS, Sx127x::new());
+        spi_devices.insert(config::DUMMY_GROUND_RADIO_SPI_BUS, Sx127x::new());
 
         DummyHardwareState {
             gpio_pins: HashMap::new(),
+            open_circuits: HashSet::new(),
+            igniter_dud: false,
+            combustion: false,
+            ematch_heating: HashMap::new(),
             i2c_devices,
-            spi_devices: HashMap::new(),
-            last_delay: Instant::now(),
+            i2c_pointers: HashMap::new(),
+            spi_devices,
+            air: AirChannel::new(LinkParams::default()),
+            last_delay: sim::now(),
+            vehicle: VehicleSim::new(VehicleParams::default()),
+            sim_epoch: sim::now(),
+            imu_next_sample: None,
+            imu_fifo: VecDeque::with_capacity(IMU_FIFO_SIZE),
+            pwm_outputs: BTreeMap::new(),
+            throttle_servo: ServoSim::new(),
+            feed: FeedSim::new(),
+        }
+    }
+
+    // Bring the vehicle model up to the current time. The IMU samples on
+    // its own clock, so its samples are latched on the way.
+    fn step_vehicle(&mut self) {
+        self.update_imu_registers();
+        self.step_vehicle_to(sim::now());
+    }
+
+    // Advance the vehicle model to `time` in fixed physics steps
+    fn step_vehicle_to(&mut self, time: Instant) {
+        let target = time.duration_since(self.sim_epoch).as_secs_f64();
+        let dt = config::SIM_PHYSICS_STEP.as_secs_f64();
+        // Thrust only while the engine is lit, scaled by the throttle valve
+        // in series with the propellant valves. It lights when both
+        // propellants reach a burning igniter and goes out when either stops.
+        let feeding = self.energized(config::DUMMY_VALVE_PIN) && self.energized(config::DUMMY_VALVE_PIN + 1);
+        if !feeding {
+            self.combustion = false;
+        } else if self.energized(config::DUMMY_IGNITER_PIN) && !self.igniter_dud {
+            self.combustion = true;
+        }
+        self.throttle_servo.advance_to(time);
+        let position = self.throttle_servo.position();
+        let throttle = if self.combustion { position } else { 0.0 };
+        let flow = |open: bool| if open { position } else { 0.0 };
+        let fuel_flow = flow(self.energized(config::DUMMY_VALVE_PIN));
+        let oxidizer_flow = flow(self.energized(config::DUMMY_VALVE_PIN + 1));
+        // E-matches heat while current flows through them, and cool off
+        // completely as soon as it stops
+        let live_pyros: Vec<u8> = PYRO_PINS.into_iter().filter(|&pin| self.ematch_live(pin)).collect();
+        self.ematch_heating.retain(|pin, _| live_pyros.contains(pin));
+        while self.vehicle.state().time + dt <= target {
+            self.vehicle.step(dt, throttle);
+            self.feed.step(dt, fuel_flow, oxidizer_flow, self.vehicle.state().thrust);
+            for &pin in &live_pyros {
+                self.heat_ematch(pin, dt);
+            }
+        }
+    }
+
+    // Pyro channels have their own supply, independent of the arm switch
+    fn ematch_live(&self, pyro_pin: u8) -> bool {
+        self.gpio_pins.get(&pyro_pin).cloned().unwrap_or(false) && !self.open_circuits.contains(&pyro_pin)
+    }
+
+    // Once an e-match has carried current for EMATCH_FIRE_TIME it fires its
+    // charge, deploying the parachute, and burns open
+    fn heat_ematch(&mut self, pyro_pin: u8, dt: f64) {
+        if self.open_circuits.contains(&pyro_pin) {
+            return; // Already fired
+        }
+        let heating = self.ematch_heating.entry(pyro_pin).or_insert(0.0);
+        *heating += dt;
+        if *heating < EMATCH_FIRE_TIME {
+            return;
+        }
+        self.open_circuits.insert(pyro_pin);
+        self.ematch_heating.remove(&pyro_pin);
+        if pyro_pin == config::DUMMY_DROGUE_PYRO_PIN {
+            self.vehicle.deploy_drogue();
+        } else {
+            self.vehicle.deploy_main();
+        }
+    }
+
+    // A valve opens (or the igniter fires) when its driver pin is high, the
+    // arm switch is closed (it is in series with actuator power) and its
+    // circuit is intact
+    fn energized(&self, output_pin: u8) -> bool {
+        let pin = |id| self.gpio_pins.get(&id).cloned().unwrap_or(false);
+        pin(output_pin) && pin(config::DUMMY_ARM_SWITCH_PIN) && !self.open_circuits.contains(&output_pin)
+    }
+
+    // Pressure transducer reading, from the feed system brought up to now
+    fn transducer_counts(&mut self, transducer: Transducer) -> u16 {
+        self.step_vehicle();
+        self.feed.transducer_counts(transducer)
+    }
+
+    // Latch every IMU sample due by now, each from the vehicle state at its
+    // own sample time, honoring the sleep bit and sample-rate divider
+    fn update_imu_registers(&mut self) {
+        let Some(regs) = self.i2c_devices.get(&config::DUMMY_IMU_ADDR) else {
+            return;
+        };
+        if regs[IMU_REG_PWR_MGMT_1] & IMU_SLEEP != 0 {
+            self.imu_next_sample = None;
+            return; // Asleep: data registers hold their last values
+        }
+        let period = imu_sample_period(regs);
+        let now = sim::now();
+        let mut next = self.imu_next_sample.unwrap_or(now);
+        while next <= now {
+            self.step_vehicle_to(next);
+            self.latch_imu_sample();
+            next = next + period;
+        }
+        self.imu_next_sample = Some(next);
+    }
+
+    // Sample the vehicle into the IMU data registers (MPU6050 layout) with
+    // the configured full-scale ranges and DLPF, and queue it in the FIFO
+    fn latch_imu_sample(&mut self) {
+        let regs = &self.i2c_devices[&config::DUMMY_IMU_ADDR];
+        let dlpf = (regs[IMU_REG_CONFIG] & 0x07) as usize;
+        let accel_lsb_per_g = 16384.0 / (1 << ((regs[IMU_REG_ACCEL_CONFIG] >> 3) & 0x03)) as f64;
+        let gyro_lsb_per_dps = IMU_GYRO_SENSITIVITY[((regs[IMU_REG_GYRO_CONFIG] >> 3) & 0x03) as usize];
+        // White noise shrinks with the square root of the filter bandwidth
+        let noise_scale = (IMU_DLPF_BANDWIDTH[dlpf] / IMU_DLPF_BANDWIDTH[0]).sqrt();
+
+        let truth = *self.vehicle.state();
+        let (air_temp, _, _) = atmosphere(truth.position[2]);
+        let board_temp = air_temp - 273.15 + 10.0; // Board runs warmer than ambient
+        let mut values = [0i16; 7];
+        sim::with_rng(|rng| {
+            for axis in 0..3 {
+                // Each part has its own biases, drifting linearly with temperature
+                let drift = board_temp - IMU_BIAS_REFERENCE_TEMP;
+                let accel_error = IMU_ACCEL_OFFSET[axis] + IMU_ACCEL_TEMP_DRIFT[axis] * drift;
+                let gyro_error = IMU_GYRO_BIAS[axis] + IMU_GYRO_TEMP_DRIFT[axis] * drift;
+                let accel = truth.specific_force[axis] + accel_error + gaussian(rng, 0.05 * noise_scale);
+                let gyro = truth.angular_rate[axis] + gyro_error + gaussian(rng, 0.002 * noise_scale);
+                values[axis] = saturate(accel / STANDARD_GRAVITY * accel_lsb_per_g);
+                values[4 + axis] = saturate(gyro.to_degrees() * gyro_lsb_per_dps);
+            }
+            let temp_c = board_temp + gaussian(rng, 0.1);
+            values[3] = saturate((temp_c - 36.53) * 340.0);
+        });
+
+        let regs = self.i2c_devices.get_mut(&config::DUMMY_IMU_ADDR).unwrap();
+        for (i, value) in values.iter().enumerate() {
+            let reg = IMU_REG_ACCEL_XOUT_H + 2 * i;
+            regs[reg..reg + 2].copy_from_slice(&value.to_be_bytes());
+        }
+        regs[IMU_REG_INT_STATUS] |= IMU_INT_DATA_RDY;
+
+        if regs[IMU_REG_USER_CTRL] & IMU_USER_CTRL_FIFO_EN == 0 {
+            return;
+        }
+        // FIFO_EN selects which words are queued, always in register order
+        let fifo_en = regs[IMU_REG_FIFO_EN];
+        let selected = [
+            (IMU_FIFO_ACCEL, &values[0..3]),
+            (IMU_FIFO_TEMP, &values[3..4]),
+            (IMU_FIFO_XG, &values[4..5]),
+            (IMU_FIFO_YG, &values[5..6]),
+            (IMU_FIFO_ZG, &values[6..7]),
+        ];
+        for (_, words) in selected.iter().filter(|(bit, _)| fifo_en & bit != 0) {
+            for byte in words.iter().flat_map(|w| w.to_be_bytes()) {
+                if self.imu_fifo.len() == IMU_FIFO_SIZE {
+                    // Full: the oldest byte is overwritten
+                    self.imu_fifo.pop_front();
+                    regs[IMU_REG_INT_STATUS] |= IMU_INT_FIFO_OFLOW;
+                }
+                self.imu_fifo.push_back(byte);
+            }
+        }
+    }
+
+    // Level of the IMU's INT pin: latched high while an enabled interrupt is
+    // pending (an unlatched 50 us pulse is not modelled)
+    fn imu_interrupt_asserted(&self) -> bool {
+        let regs = &self.i2c_devices[&config::DUMMY_IMU_ADDR];
+        regs[IMU_REG_INT_PIN_CFG] & IMU_LATCH_INT_EN != 0 && regs[IMU_REG_INT_STATUS] & regs[IMU_REG_INT_ENABLE] != 0
+    }
+
+    // Run a BMP280 conversion of the static pressure and temperature at the
+    // vehicle's altitude. In sleep mode the data registers are left alone;
+    // a forced-mode conversion drops the device back to sleep afterwards.
+    fn update_baro_registers(&mut self) {
+        let truth = *self.vehicle.state();
+        let (air_temp, air_pressure, _) = atmosphere(truth.position[2]);
+        let (temp_c, pressure) = sim::with_rng(|rng| {
+            (air_temp - 273.15 + 10.0 + gaussian(rng, 0.05), air_pressure + gaussian(rng, 1.5))
+        });
+        let Some(regs) = self.i2c_devices.get_mut(&config::DUMMY_BARO_ADDR) else {
+            return;
+        };
+        let ctrl_meas = regs[BARO_REG_CTRL_MEAS];
+        match ctrl_meas & 0x03 {
+            0b00 => return, // Sleep
+            0b11 => {}      // Normal: free-running conversions
+            _ => regs[BARO_REG_CTRL_MEAS] = ctrl_meas & !0x03, // Forced: one shot
+        }
+        let adc_t = bmp280_raw_temperature(temp_c);
+        let adc_p = bmp280_raw_pressure(pressure, BARO_CALIBRATION.t_fine(adc_t));
+        let data = [
+            (adc_p >> 12) as u8, (adc_p >> 4) as u8, ((adc_p & 0x0F) << 4) as u8,
+            (adc_t >> 12) as u8, (adc_t >> 4) as u8, ((adc_t & 0x0F) << 4) as u8,
+        ];
+        regs[BARO_REG_PRESS_MSB..BARO_REG_PRESS_MSB + 6].copy_from_slice(&data);
+    }
+
+    // Refresh the ADXL375 data registers (49 mg/LSB, 13-bit) while measuring
+    fn update_high_g_registers(&mut self) {
+        let truth = *self.vehicle.state();
+        let Some(regs) = self.i2c_devices.get_mut(&config::DUMMY_HIGH_G_ADDR) else {
+            return;
+        };
+        if regs[HIGH_G_REG_POWER_CTL] & HIGH_G_MEASURE == 0 {
+            return; // Standby
+        }
+        let counts = sim::with_rng(|rng| {
+            truth.specific_force.map(|a| {
+                let g = (a + gaussian(rng, 0.35)) / STANDARD_GRAVITY;
+                (g / 0.049).round().clamp(-4096.0, 4095.0) as i16
+            })
+        });
+        for (i, count) in counts.iter().enumerate() {
+            let reg = HIGH_G_REG_DATAX0 + 2 * i;
+            regs[reg..reg + 2].copy_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    // Side effects of a register write (the value itself is already stored)
+    fn register_written(&mut self, address: u8, reg: u8, value: u8) {
+        if address == config::DUMMY_IMU_ADDR && reg as usize == IMU_REG_PWR_MGMT_1 && value & IMU_DEVICE_RESET != 0 {
+            println!("[HAL] IMU device reset");
+            let who_am_i = self.i2c_devices[&address][IMU_REG_WHO_AM_I]; // Hard-wired
+            let mut regs = imu_power_on_registers();
+            regs[IMU_REG_WHO_AM_I] = who_am_i;
+            self.i2c_devices.insert(address, regs);
+            self.imu_next_sample = None;
+            self.imu_fifo.clear();
         }
+        if address == config::DUMMY_IMU_ADDR && reg as usize == IMU_REG_USER_CTRL && value & IMU_USER_CTRL_FIFO_RESET != 0 {
+            // Self-clearing. The sample clock restarts with the FIFO, so the
+            // first queued sample lands one sample period after the reset.
+            self.step_vehicle();
+            let regs = self.i2c_devices.get_mut(&address).unwrap();
+            regs[IMU_REG_USER_CTRL] &= !IMU_USER_CTRL_FIFO_RESET;
+            if regs[IMU_REG_PWR_MGMT_1] & IMU_SLEEP == 0 {
+                self.imu_next_sample = Some(sim::now() + imu_sample_period(regs));
+            }
+            self.imu_fifo.clear();
+        }
+        if address == config::DUMMY_BARO_ADDR && reg as usize == BARO_REG_RESET && value == BARO_RESET_COMMAND {
+            println!("[HAL] Barometer soft reset");
+            let chip_id = self.i2c_devices[&address][BARO_REG_CHIP_ID]; // Hard-wired
+            let mut regs = baro_power_on_registers();
+            regs[BARO_REG_CHIP_ID] = chip_id;
+            self.i2c_devices.insert(address, regs);
+        }
+    }
+
+    // Bring a device's data registers up to date before the bus reads them
+    fn refresh_device(&mut self, address: u8) {
+        if address == config::DUMMY_IMU_ADDR {
+            // Sensor data follows the simulated flight (noise added in physical units)
+            self.step_vehicle();
+        } else if address == config::DUMMY_BARO_ADDR {
+            self.step_vehicle();
+            self.update_baro_registers();
+        } else if address == config::DUMMY_HIGH_G_ADDR {
+            self.step_vehicle();
+            self.update_high_g_registers();
+        }
+    }
+
+    // Antenna position of the radio on SPI bus `bus_id`, world frame
+    fn radio_position(&self, bus_id: u8) -> [f64; 3] {
+        if bus_id == config::DUMMY_GROUND_RADIO_SPI_BUS {
+            self.air.params.ground_station
+        } else {
+            self.vehicle.state().position
+        }
+    }
+
+    // Put finished transmissions on the air and deliver what has arrived
+    fn update_radios(&mut self) {
+        self.step_vehicle();
+        let now = sim::now();
+        let buses: Vec<u8> = self.spi_devices.keys().cloned().collect();
+        for &from in &buses {
+            let Some(tx) = self.spi_devices.get_mut(&from).unwrap().update(now) else {
+                continue;
+            };
+            let origin = self.radio_position(from);
+            let receivers: Vec<(u8, f64)> = buses
+                .iter()
+                .filter(|&&to| to != from)
+                .map(|&to| {
+                    let p = self.radio_position(to);
+                    (to, ((p[0] - origin[0]).powi(2) + (p[1] - origin[1]).powi(2) + (p[2] - origin[2]).powi(2)).sqrt())
+                })
+                .collect();
+            self.air.transmit(from, &tx, &receivers);
+        }
+        self.air.deliver(now, &mut self.spi_devices);
+    }
+
+    // Value the bus sees when reading `reg`, with any read side effects
+    fn read_register(&mut self, address: u8, reg: u8) -> u8 {
+        if address == config::DUMMY_IMU_ADDR {
+            match reg as usize {
+                IMU_REG_FIFO_R_W => return self.imu_fifo.pop_front().unwrap_or(0),
+                IMU_REG_FIFO_COUNT_H => return (self.imu_fifo.len() >> 8) as u8,
+                IMU_REG_FIFO_COUNT_L => return self.imu_fifo.len() as u8,
+                IMU_REG_INT_STATUS => {
+                    // Cleared by reading
+                    let regs = self.i2c_devices.get_mut(&address).unwrap();
+                    return std::mem::take(&mut regs[IMU_REG_INT_STATUS]);
+                }
+                _ => {}
+            }
+        }
+        self.i2c_devices[&address][reg as usize]
+    }
+}
+
+// Whether the register pointer stays put on reads of `reg` (FIFO ports)
+fn is_stream_register(address: u8, reg: u8) -> bool {
+    address == config::DUMMY_IMU_ADDR && reg as usize == IMU_REG_FIFO_R_W
+}
+
+// SPI bus of the radio whose DIO0 is wired to `pin_id`
+fn radio_on_irq_pin(pin_id: u8) -> Option<u8> {
+    match pin_id {
+        config::DUMMY_RADIO_IRQ_PIN => Some(config::DUMMY_RADIO_SPI_BUS),
+        config::DUMMY_GROUND_RADIO_IRQ_PIN => Some(config::DUMMY_GROUND_RADIO_SPI_BUS),
+        _ => None,
+    }
+}
+
+const PYRO_PINS: [u8; 2] = [config::DUMMY_DROGUE_PYRO_PIN, config::DUMMY_MAIN_PYRO_PIN];
+const EMATCH_FIRE_TIME: f64 = 0.02; // s of current an e-match needs to fire
+
+// Output pin whose circuit the continuity sense on `pin_id` checks
+fn continuity_sensed_pin(pin_id: u8) -> Option<u8> {
+    match pin_id {
+        p if p == config::DUMMY_VALVE_CONTINUITY_PIN => Some(config::DUMMY_VALVE_PIN),
+        p if p == config::DUMMY_VALVE_CONTINUITY_PIN + 1 => Some(config::DUMMY_VALVE_PIN + 1),
+        config::DUMMY_IGNITER_CONTINUITY_PIN => Some(config::DUMMY_IGNITER_PIN),
+        config::DUMMY_DROGUE_CONTINUITY_PIN => Some(config::DUMMY_DROGUE_PYRO_PIN),
+        config::DUMMY_MAIN_CONTINUITY_PIN => Some(config::DUMMY_MAIN_PYRO_PIN),
+        _ => None,
+    }
+}
+
+// --- Simulated I2C devices ---
+
+// MPU6050 register map (only what the simulation models)
+const IMU_REG_SMPLRT_DIV: usize = 0x19;
+const IMU_REG_CONFIG: usize = 0x1A;       // DLPF_CFG in bits 2:0
+const IMU_REG_GYRO_CONFIG: usize = 0x1B;  // FS_SEL in bits 4:3
+const IMU_REG_ACCEL_CONFIG: usize = 0x1C; // AFS_SEL in bits 4:3
+const IMU_REG_FIFO_EN: usize = 0x23;
+const IMU_REG_INT_PIN_CFG: usize = 0x37;
+const IMU_REG_INT_ENABLE: usize = 0x38;
+const IMU_REG_INT_STATUS: usize = 0x3A;
+const IMU_REG_ACCEL_XOUT_H: usize = 0x3B; // Accel, temp, gyro: 7 big-endian words
+const IMU_REG_USER_CTRL: usize = 0x6A;
+const IMU_REG_PWR_MGMT_1: usize = 0x6B;
+const IMU_REG_FIFO_COUNT_H: usize = 0x72;
+const IMU_REG_FIFO_COUNT_L: usize = 0x73;
+const IMU_REG_FIFO_R_W: usize = 0x74;
+const IMU_REG_WHO_AM_I: usize = 0x75;
+const IMU_DEVICE_RESET: u8 = 0x80;
+const IMU_SLEEP: u8 = 0x40;
+const IMU_USER_CTRL_FIFO_EN: u8 = 0x40;
+const IMU_USER_CTRL_FIFO_RESET: u8 = 0x04;
+const IMU_LATCH_INT_EN: u8 = 0x20;
+const IMU_INT_FIFO_OFLOW: u8 = 0x10;
+const IMU_INT_DATA_RDY: u8 = 0x01;
+// FIFO_EN bits
+const IMU_FIFO_TEMP: u8 = 0x80;
+const IMU_FIFO_XG: u8 = 0x40;
+const IMU_FIFO_YG: u8 = 0x20;
+const IMU_FIFO_ZG: u8 = 0x10;
+const IMU_FIFO_ACCEL: u8 = 0x08;
+const IMU_FIFO_SIZE: usize = 1024; // bytes
+// Accelerometer bandwidth (Hz) for each DLPF_CFG setting
+const IMU_DLPF_BANDWIDTH: [f64; 8] = [260.0, 184.0, 94.0, 44.0, 21.0, 10.0, 5.0, 260.0];
+// Bias errors of the simulated part at IMU_BIAS_REFERENCE_TEMP, and their
+// true drift with temperature. The bench coefficients in config.rs are a
+// measurement of the drift, so they are close to these but not exact.
+const IMU_ACCEL_OFFSET: [f64; 3] = [0.15, -0.10, 0.20]; // m/s^2
+const IMU_GYRO_BIAS: [f64; 3] = [0.010, -0.008, 0.005]; // rad/s
+const IMU_ACCEL_TEMP_DRIFT: [f64; 3] = [0.0044, 0.0026, -0.0057]; // m/s^2 per degree C
+const IMU_GYRO_TEMP_DRIFT: [f64; 3] = [0.00045, -0.00027, 0.00024]; // rad/s per degree C
+const IMU_BIAS_REFERENCE_TEMP: f64 = 25.0; // degrees C
+// Gyro LSB per deg/s for each FS_SEL setting
+const IMU_GYRO_SENSITIVITY: [f64; 4] = [131.0, 65.5, 32.8, 16.4];
+
+// Time between samples: the gyro output rate (8 kHz with the DLPF off,
+// 1 kHz with it on) divided by 1 + SMPLRT_DIV
+fn imu_sample_period(regs: &[u8]) -> Duration {
+    let dlpf = regs[IMU_REG_CONFIG] & 0x07;
+    let output_rate = if dlpf == 0 || dlpf == 7 { 8000.0 } else { 1000.0 };
+    Duration::from_secs_f64((1.0 + regs[IMU_REG_SMPLRT_DIV] as f64) / output_rate)
+}
+
+fn imu_power_on_registers() -> Vec<u8> {
+    let mut regs = vec![0u8; 256];
+    regs[IMU_REG_PWR_MGMT_1] = IMU_SLEEP; // Powers up asleep
+    regs[IMU_REG_WHO_AM_I] = 0x68;
+    regs
+}
+
+// ADXL375 register map
+const HIGH_G_REG_DEVID: usize = 0x00;
+const HIGH_G_REG_POWER_CTL: usize = 0x2D;
+const HIGH_G_REG_DATAX0: usize = 0x32;
+const HIGH_G_MEASURE: u8 = 0x08;
+
+fn high_g_power_on_registers() -> Vec<u8> {
+    let mut regs = vec![0u8; 256];
+    regs[HIGH_G_REG_DEVID] = 0xE5;
+    regs
+}
+
+// BMP280 register map
+const BARO_REG_CALIB: usize = 0x88; // 24 bytes of little-endian trimming coefficients
+const BARO_REG_CHIP_ID: usize = 0xD0;
+const BARO_REG_RESET: usize = 0xE0;
+const BARO_REG_CTRL_MEAS: usize = 0xF4;
+const BARO_REG_PRESS_MSB: usize = 0xF7; // Pressure then temperature, 20 bits each
+const BARO_RESET_COMMAND: u8 = 0xB6;
+
+// Trimming coefficients burned into the simulated part (datasheet example values)
+const BARO_CALIBRATION: Calibration = Calibration {
+    dig_t1: 27504,
+    dig_t2: 26435,
+    dig_t3: -1000,
+    dig_p1: 36477,
+    dig_p: [-10685, 3024, 2855, 140, -7, 15500, -14600, 6000],
+};
+
+fn baro_power_on_registers() -> Vec<u8> {
+    let c = BARO_CALIBRATION;
+    let mut regs = vec![0u8; 256];
+    let mut calib = Vec::with_capacity(24);
+    calib.extend_from_slice(&c.dig_t1.to_le_bytes());
+    calib.extend_from_slice(&c.dig_t2.to_le_bytes());
+    calib.extend_from_slice(&c.dig_t3.to_le_bytes());
+    calib.extend_from_slice(&c.dig_p1.to_le_bytes());
+    for coefficient in c.dig_p {
+        calib.extend_from_slice(&coefficient.to_le_bytes());
+    }
+    regs[BARO_REG_CALIB..BARO_REG_CALIB + 24].copy_from_slice(&calib);
+    regs[BARO_REG_CHIP_ID] = 0x58;
+    // Data registers read 0x80000 until the first conversion
+    regs[BARO_REG_PRESS_MSB..BARO_REG_PRESS_MSB + 6].copy_from_slice(&[0x80, 0, 0, 0x80, 0, 0]);
+    regs
+}
+
+// The part only reports raw ADC counts, so the model runs the datasheet
+// compensation (the driver's) backwards: bisect for the count that
+// compensates to the value.
+fn bmp280_raw_temperature(temp_c: f64) -> i32 {
+    let target = (temp_c * 5120.0).round() as i32; // t_fine units
+    let (mut lo, mut hi) = (0i32, 0xFFFFF);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if BARO_CALIBRATION.t_fine(mid) < target { lo = mid + 1 } else { hi = mid }
+    }
+    lo
+}
+
+fn bmp280_raw_pressure(pressure: f64, t_fine: i32) -> i32 {
+    let target = (pressure * 256.0).round() as i64;
+    // Compensated pressure falls as the raw count rises
+    let (mut lo, mut hi) = (0i32, 0xFFFFF);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if BARO_CALIBRATION.pressure(mid, t_fine).unwrap_or(0) > target { lo = mid + 1 } else { hi = mid }
     }
+    lo
+}
+
+// Convert to a sensor count, clipping like a real ADC would
+fn saturate(value: f64) -> i16 {
+    value.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
+// Normally distributed noise with standard deviation `sigma` (Box-Muller)
+pub(crate) fn gaussian<R: Rng>(rng: &mut R, sigma: f64) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
 }
 
 lazy_static! {
@@ -52,6 +570,9 @@ pub struct DummyPin {
 impl OutputPin for DummyPin {
     fn set_high(&mut self) -> HalResult<()> {
         let mut state = HW_STATE.lock().unwrap();
+        if PYRO_PINS.contains(&self.pin_id) {
+            state.step_vehicle(); // The e-match has been cold up to now
+        }
         println!("[HAL] GPIO Pin {} -> HIGH", self.pin_id);
         state.gpio_pins.insert(self.pin_id, true);
         Ok(())
@@ -59,6 +580,9 @@ impl OutputPin for DummyPin {
 
     fn set_low(&mut self) -> HalResult<()> {
         let mut state = HW_STATE.lock().unwrap();
+        if PYRO_PINS.contains(&self.pin_id) {
+            state.step_vehicle(); // Count the whole pulse before it ends
+        }
          println!("[HAL] GPIO Pin {} -> LOW", self.pin_id);
         state.gpio_pins.insert(self.pin_id, false);
         Ok(())
@@ -67,7 +591,21 @@ impl OutputPin for DummyPin {
 
 impl InputPin for DummyPin {
     fn is_high(&self) -> HalResult<bool> {
-        let state = HW_STATE.lock().unwrap();
+        let mut state = HW_STATE.lock().unwrap();
+        if self.pin_id == config::DUMMY_IMU_INT_PIN {
+            // Driven by the IMU rather than stored
+            state.step_vehicle();
+            return Ok(state.imu_interrupt_asserted());
+        }
+        if let Some(bus_id) = radio_on_irq_pin(self.pin_id) {
+            // Radio DIO0
+            state.update_radios();
+            return Ok(state.spi_devices[&bus_id].dio0());
+        }
+        if let Some(output_pin) = continuity_sensed_pin(self.pin_id) {
+            // Continuity sense: high while the matching circuit is intact
+            return Ok(!state.open_circuits.contains(&output_pin));
+        }
         let pin_state = state.gpio_pins.get(&self.pin_id).cloned().unwrap_or(false); // Default low if not set
         // println!("[HAL] GPIO Pin {} Read -> {}", self.pin_id, if pin_state { "HIGH" } else { "LOW" });
         Ok(pin_state)
@@ -81,71 +619,51 @@ pub struct DummyI2c {
 }
 
 impl I2cBus for DummyI2c {
+    // First byte sets the device's register pointer; any further bytes are
+    // written from there on, auto-incrementing like most I2C sensors.
     fn write(&mut self, address: u8, bytes: &[u8]) -> HalResult<()> {
         let mut state = HW_STATE.lock().unwrap();
         println!("[HAL] I2C[{}] Write to 0x{:02X}: {:02X?}", self.bus_id, address, bytes);
-        if let Some(device_data) = state.i2c_devices.get_mut(&address) {
-            // Simulate writing to registers - very basic simulation
-            // Assumes first byte is register address, rest is data
-            if bytes.len() > 1 {
-                let reg_addr = bytes[0] as usize;
-                let data_to_write = &bytes[1..];
-                if reg_addr + data_to_write.len() <= device_data.len() {
-                    device_data[reg_addr..reg_addr + data_to_write.len()].copy_from_slice(data_to_write);
-                } else {
-                    println!("[HAL] I2C[{}] Write out of bounds to 0x{:02X}", self.bus_id, address);
-                     // Don't return error, just log for simulation
-                }
+        if !state.i2c_devices.contains_key(&address) {
+            return Err(HalError::UnexpectedDevice);
+        }
+        if let Some((&reg_addr, data)) = bytes.split_first() {
+            for (i, &value) in data.iter().enumerate() {
+                let reg = reg_addr.wrapping_add(i as u8);
+                state.i2c_devices.get_mut(&address).unwrap()[reg as usize] = value;
+                state.register_written(address, reg, value);
             }
-            Ok(())
-        } else {
-            Err(HalError::UnexpectedDevice)
+            state.i2c_pointers.insert(address, reg_addr.wrapping_add(data.len() as u8));
         }
+        Ok(())
     }
 
+    // Reads from the register pointer set by the last write, auto-incrementing
+    // (except on a FIFO port, which streams out the queue)
     fn read(&mut self, address: u8, buffer: &mut [u8]) -> HalResult<()> {
         let mut state = HW_STATE.lock().unwrap();
         println!("[HAL] I2C[{}] Read from 0x{:02X} ({} bytes)", self.bus_id, address, buffer.len());
-        if let Some(device_data) = state.i2c_devices.get_mut(&address) {
-            // Simulate reading sensor data with some noise
-            let mut rng = rand::thread_rng();
-            let data_len = device_data.len().min(buffer.len());
-
-            // Simulate reading from start of device memory for simplicity
-             buffer[..data_len].copy_from_slice(&device_data[..data_len]);
-
-             // Add some noise (example for IMU-like data)
-            if address == config::DUMMY_IMU_ADDR && data_len >= 14 {
-                 // Simulate noise on accelerometer/gyro data (bytes 0-5, 8-13)
-                for i in (0..6).chain(8..14) {
-                    if i < buffer.len() {
-                        buffer[i] = buffer[i].wrapping_add(rng.gen_range(0..5)); // Add small random noise
-                    }
-                }
-                // Simulate slight temperature drift (bytes 6-7)
-                 if 7 < buffer.len() {
-                      let temp_change: i8 = rng.gen_range(-1..2); // -1, 0, or 1
-                      let temp_bytes = &mut buffer[6..8];
-                      let current_temp = i16::from_be_bytes([temp_bytes[0], temp_bytes[1]]);
-                      let new_temp = current_temp.wrapping_add(temp_change as i16);
-                      let new_bytes = new_temp.to_be_bytes();
-                      temp_bytes[0] = new_bytes[0];
-                      temp_bytes[1] = new_bytes[1];
-                 }
+        if !state.i2c_devices.contains_key(&address) {
+            return Err(HalError::UnexpectedDevice);
+        }
+        state.refresh_device(address);
+        let mut reg = state.i2c_pointers.get(&address).cloned().unwrap_or(0);
+        for byte in buffer.iter_mut() {
+            *byte = state.read_register(address, reg);
+            if !is_stream_register(address, reg) {
+                reg = reg.wrapping_add(1);
             }
-
-            println!("[HAL] I2C[{}] Read data: {:02X?}", self.bus_id, &buffer[..data_len]);
-            Ok(())
-        } else {
-            Err(HalError::UnexpectedDevice)
         }
+        state.i2c_pointers.insert(address, reg);
+        println!("[HAL] I2C[{}] Read data: {:02X?}", self.bus_id, buffer);
+        Ok(())
     }
 
     fn write_read(&mut self, address: u8, bytes_to_write: &[u8], buffer_to_read: &mut [u8]) -> HalResult<()> {
         // Simulate as separate write then read for simplicity
         self.write(address, bytes_to_write)?;
         // Add small delay to simulate bus turnaround
-        std::thread::sleep(Duration::from_micros(50));
+        sim::sleep(Duration::from_micros(50));
         self.read(address, buffer_to_read)
     }
 }
@@ -157,25 +675,86 @@ pub struct DummySpi {
 }
 
 impl SpiBus for DummySpi {
+    // One transaction with the radio on this bus (chip select is managed by
+    // the driver around each call)
     fn transfer<'w>(&mut self, buffer: &'w mut [u8]) -> HalResult<&'w [u8]> {
         let mut state = HW_STATE.lock().unwrap();
         println!("[HAL] SPI[{}] Transfer: {:02X?}", self.bus_id, buffer);
-        // Simulate loopback or reading predefined data
-        let device_data = state.spi_devices.entry(self.bus_id).or_insert_with(|| vec![0xFF; buffer.len()]); // Default to 0xFF if not present
-        let read_len = buffer.len().min(device_data.len());
-        let response = device_data[..read_len].to_vec(); // Copy data to send back
-        // You could modify device_data based on `buffer` here if simulating write
-        buffer[..read_len].copy_from_slice(&response);
-        println!("[HAL] SPI[{}] Received: {:02X?}", self.bus_id, &buffer[..read_len]);
-        Ok(&buffer[..read_len])
+        state.update_radios();
+        let radio = state.spi_devices.get_mut(&self.bus_id).ok_or(HalError::UnexpectedDevice)?;
+        radio.transfer(sim::now(), buffer);
+        println!("[HAL] SPI[{}] Received: {:02X?}", self.bus_id, buffer);
+        Ok(buffer)
     }
 
     fn write(&mut self, bytes: &[u8]) -> HalResult<()> {
-         let _state = HW_STATE.lock().unwrap();
-         println!("[HAL] SPI[{}] Write: {:02X?}", self.bus_id, bytes);
-         // Simulate writing to a device - maybe store the written bytes?
-         // For now, just log it.
-         Ok(())
+        let mut state = HW_STATE.lock().unwrap();
+        println!("[HAL] SPI[{}] Write: {:02X?}", self.bus_id, bytes);
+        state.update_radios();
+        let radio = state.spi_devices.get_mut(&self.bus_id).ok_or(HalError::UnexpectedDevice)?;
+        radio.transfer(sim::now(), &mut bytes.to_vec()); // MISO ignored
+        Ok(())
+    }
+}
+
+// -- PWM --
+#[derive(Debug, Clone)]
+pub struct DummyPwm {
+    channel: u8,
+}
+
+impl PwmOutput for DummyPwm {
+    fn set_duty_cycle(&mut self, duty: f32) -> HalResult<()> {
+        if !(0.0..=1.0).contains(&duty) {
+            return Err(HalError::ConfigurationError(format!("PWM{} duty cycle {} out of range", self.channel, duty)));
+        }
+        let mut state = HW_STATE.lock().unwrap();
+        let now = sim::now();
+        let history = state.pwm_outputs.entry(self.channel).or_default();
+        if history.last().map(|&(_, last)| last) == Some(duty) {
+            return Ok(()); // Unchanged: nothing new to record
+        }
+        println!("[HAL] PWM{} duty -> {:.2}%", self.channel, duty * 100.0);
+        history.push((now, duty));
+        if self.channel == config::DUMMY_THROTTLE_PWM_CHANNEL {
+            let pulse_us = duty as f64 * config::DUMMY_PWM_PERIOD.as_micros() as f64;
+            state.throttle_servo.set_pulse(now, pulse_us);
+        }
+        Ok(())
+    }
+
+    fn duty_cycle(&self) -> f32 {
+        let state = HW_STATE.lock().unwrap();
+        state.pwm_outputs.get(&self.channel).and_then(|h| h.last()).map_or(0.0, |&(_, duty)| duty)
+    }
+
+    fn period(&self) -> Duration {
+        config::DUMMY_PWM_PERIOD
+    }
+}
+
+// -- ADC --
+#[derive(Debug, Clone)]
+pub struct DummyAdc {
+    adc_id: u8,
+}
+
+impl Adc<u16> for DummyAdc {
+    type Error = HalError;
+
+    // 12-bit conversion of one channel
+    fn read(&mut self, channel: u8) -> HalResult<u16> {
+        let mut state = HW_STATE.lock().unwrap();
+        match channel {
+            config::DUMMY_THROTTLE_FEEDBACK_CHANNEL => {
+                state.throttle_servo.advance_to(sim::now());
+                Ok(state.throttle_servo.feedback_counts())
+            }
+            config::DUMMY_FUEL_TANK_PRESSURE_CHANNEL => Ok(state.transducer_counts(Transducer::FuelTank)),
+            config::DUMMY_OXIDIZER_TANK_PRESSURE_CHANNEL => Ok(state.transducer_counts(Transducer::OxidizerTank)),
+            config::DUMMY_CHAMBER_PRESSURE_CHANNEL => Ok(state.transducer_counts(Transducer::Chamber)),
+            _ => Err(HalError::ReadError(format!("ADC{} has no channel {}", self.adc_id, channel))),
+        }
     }
 }
 
@@ -185,12 +764,12 @@ pub struct DummyDelay;
 
 impl DelayUs for DummyDelay {
     fn delay_us(&mut self, us: u32) {
-        // Use std::thread::sleep for simulation
+        // Sleep on the kernel clock so delays are virtual in simulated mode
         // Add a small base delay to simulate overhead if desired
         let delay_duration = Duration::from_micros(us as u64);
         // println!("[HAL] Delaying for {} us", us); // Can be noisy
-        std::thread::sleep(delay_duration);
-        HW_STATE.lock().unwrap().last_delay = Instant::now(); // Update last delay time
+        sim::sleep(delay_duration);
+        HW_STATE.lock().unwrap().last_delay = sim::now(); // Update last delay time
     }
 }
 
@@ -198,8 +777,8 @@ impl DelayMs for DummyDelay {
     fn delay_ms(&mut self, ms: u32) {
         let delay_duration = Duration::from_millis(ms as u64);
         // println!("[HAL] Delaying for {} ms", ms);
-        std::thread::sleep(delay_duration);
-        HW_STATE.lock().unwrap().last_delay = Instant::now(); // Update last delay time
+        sim::sleep(delay_duration);
+        HW_STATE.lock().unwrap().last_delay = sim::now(); // Update last delay time
     }
 }
 
@@ -212,6 +791,8 @@ impl FullHardwareAbstraction for DummyHal {
     type I2cController = DummyI2c;
     type SpiController = DummySpi;
     type TimerDelay = DummyDelay;
+    type PwmChannel = DummyPwm;
+    type AdcController = DummyAdc;
 
     fn get_gpio_pin(&self, pin_id: u8) -> Option<Self::GpioPin> {
         println!("[HAL] Getting GPIO Pin {}", pin_id);
@@ -226,17 +807,172 @@ impl FullHardwareAbstraction for DummyHal {
 
      fn get_spi_bus(&self, bus_id: u8) -> Option<Self::SpiController> {
         println!("[HAL] Getting SPI Bus {}", bus_id);
-         // Only provide bus 1 in this simulation
-        if bus_id == config::DUMMY_RADIO_SPI_BUS { Some(DummySpi { bus_id }) } else { None }
+        // One bus per radio: the flight radio and the ground station's
+        let radio_buses = [config::DUMMY_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_SPI_BUS];
+        if radio_buses.contains(&bus_id) { Some(DummySpi { bus_id }) } else { None }
     }
 
     fn get_delay_timer(&self) -> Self::TimerDelay {
          println!("[HAL] Getting Delay Timer");
          DummyDelay
     }
+
+    fn get_pwm_channel(&self, channel: u8) -> Option<Self::PwmChannel> {
+        println!("[HAL] Getting PWM Channel {}", channel);
+        // Only the throttle servo is wired up
+        if channel == config::DUMMY_THROTTLE_PWM_CHANNEL { Some(DummyPwm { channel }) } else { None }
+    }
+
+    fn get_adc(&self, adc_id: u8) -> Option<Self::AdcController> {
+        println!("[HAL] Getting ADC {}", adc_id);
+        if adc_id == config::DUMMY_ADC_ID { Some(DummyAdc { adc_id }) } else { None }
+    }
 }
 
 // Helper function to get the singleton instance
 pub fn get_dummy_hal() -> DummyHal {
     DummyHal
 }
+
+// --- Simulation control (not part of the HAL traits) ---
+
+// Replace the simulated vehicle, e.g. to fly a different motor or wind profile.
+// Restarts the flight from the pad at the current time.
+pub fn set_vehicle_params(params: VehicleParams) {
+    let mut state = HW_STATE.lock().unwrap();
+    state.vehicle = VehicleSim::new(params);
+    state.sim_epoch = sim::now();
+}
+
+// Change the impairments of the simulated radio link
+pub fn set_link_params(params: LinkParams) {
+    HW_STATE.lock().unwrap().air.params = params;
+}
+
+// Move the physical arm switch (closed = ARM). It powers the valves.
+pub fn set_arm_switch(closed: bool) {
+    println!("[HAL] Arm switch {}", if closed { "closed (ARM)" } else { "open (SAFE)" });
+    HW_STATE.lock().unwrap().gpio_pins.insert(config::DUMMY_ARM_SWITCH_PIN, closed);
+}
+
+// Break (or repair) the valve coil, igniter or e-match circuit driven by
+// `output_pin`: it stops responding and its continuity sense reads low
+pub fn set_open_circuit(output_pin: u8, open: bool) {
+    let mut state = HW_STATE.lock().unwrap();
+    if open {
+        state.open_circuits.insert(output_pin);
+    } else {
+        state.open_circuits.remove(&output_pin);
+    }
+}
+
+// Make the igniter a dud: intact and firing, but the engine never lights
+pub fn set_igniter_dud(dud: bool) {
+    HW_STATE.lock().unwrap().igniter_dud = dud;
+}
+
+// Pressurize the propellant tanks, or vent them
+pub fn set_tanks_pressurized(pressurized: bool) {
+    println!("[HAL] Propellant tanks {}", if pressurized { "pressurized" } else { "vented" });
+    HW_STATE.lock().unwrap().feed.set_pressurized(pressurized);
+}
+
+// True tank or chamber pressure, Pa gauge
+pub fn feed_pressure(transducer: Transducer) -> f64 {
+    let mut state = HW_STATE.lock().unwrap();
+    state.step_vehicle();
+    state.feed.pressure(transducer)
+}
+
+// Overwrite a register of a simulated I2C device behind the driver's back,
+// e.g. a different WHO_AM_I to stand in another part. Identity registers
+// keep the poked value across a device reset.
+pub fn set_i2c_register(address: u8, register: u8, value: u8) {
+    let mut state = HW_STATE.lock().unwrap();
+    if let Some(regs) = state.i2c_devices.get_mut(&address) {
+        regs[register as usize] = value;
+    }
+}
+
+// Every duty cycle set on a PWM channel, with the time it was set
+pub fn pwm_history(channel: u8) -> Vec<(Instant, f32)> {
+    HW_STATE.lock().unwrap().pwm_outputs.get(&channel).cloned().unwrap_or_default()
+}
+
+// True position of the throttle servo, 0 (closed) to 1 (full travel)
+pub fn throttle_servo_position() -> f64 {
+    let mut state = HW_STATE.lock().unwrap();
+    state.throttle_servo.advance_to(sim::now());
+    state.throttle_servo.position()
+}
+
+// Return every simulated device to its power-on state (pins low, arm switch
+// open, vehicle on the pad). Call after kernel::sim::init when replaying a
+// run in-process.
+pub fn reset() {
+    *HW_STATE.lock().unwrap() = DummyHardwareState::new();
+}
+
+// Truth state of the simulated vehicle, brought up to the current time
+pub fn vehicle_state() -> VehicleState {
+    let mut state = HW_STATE.lock().unwrap();
+    state.step_vehicle();
+    *state.vehicle.state()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::imu::{Imu, ImuConfig};
+    use crate::kernel::{sim::ClockMode, sync::Mutex, task};
+
+    // Ignite at 0.5 s and record every accelerometer sample for 2 s of flight
+    fn record_flight(seed: u64) -> Vec<[f32; 3]> {
+        sim::init(ClockMode::Simulated, seed);
+        reset();
+        set_arm_switch(true);
+        let hal = get_dummy_hal();
+        let mut imu = Imu::new(hal.get_i2c_bus(0).unwrap(), hal.get_gpio_pin(config::DUMMY_IMU_INT_PIN).unwrap(), hal.get_delay_timer(), config::DUMMY_IMU_ADDR, ImuConfig::default()).unwrap();
+        let mut fuel = hal.get_gpio_pin(config::DUMMY_VALVE_PIN).unwrap();
+        let mut oxidizer = hal.get_gpio_pin(config::DUMMY_VALVE_PIN + 1).unwrap();
+        let mut igniter = hal.get_gpio_pin(config::DUMMY_IGNITER_PIN).unwrap();
+        let samples = Mutex::new(Vec::new());
+
+        let sampler = {
+            let samples = samples.clone();
+            task::spawn("Sampler", move || {
+                for _ in 0..100 {
+                    let data = imu.read_data()?;
+                    samples.lock()?.push(data.accel);
+                    sim::sleep(Duration::from_millis(20));
+                }
+                Ok(())
+            })
+        };
+        let igniter = task::spawn("Igniter", move || {
+            sim::sleep(Duration::from_millis(500));
+            igniter.set_high()?;
+            fuel.set_high()?;
+            oxidizer.set_high()?;
+            Ok(())
+        });
+        sampler.join().unwrap();
+        igniter.join().unwrap();
+
+        assert!(sim::now().as_secs_f64() >= 2.0);
+        let recorded = samples.lock().unwrap().clone();
+        recorded
+    }
+
+    #[test]
+    fn simulated_flight_replays_bit_for_bit() {
+        let _exclusive = sim::exclusive();
+        let first = record_flight(1);
+        let second = record_flight(1);
+        assert_eq!(first.len(), 100);
+        assert_eq!(first, second);
+        // The vehicle actually lifted off (boost is above 2g)
+        assert!(first.last().unwrap()[2] > 19.0);
+        assert_ne!(first, record_flight(2));
+    }
+}
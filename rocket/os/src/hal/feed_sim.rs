@@ -0,0 +1,104 @@
+// Propellant feed system behind the simulated pressure transducers: two
+// tanks held up by a pressurant regulator, and the combustion chamber.
+// Tank pressure droops with propellant flow and recovers when it stops;
+// chamber pressure follows thrust, with a little cold-flow pressure when
+// propellant flows without burning. Pressures are Pa gauge, each following
+// its target with a first-order lag.
+use crate::hal::dummy_hal::gaussian;
+use crate::hal::servo_sim::ADC_FULL_SCALE;
+use crate::kernel::sim;
+
+const REGULATOR_PRESSURE: f64 = 3.0e6; // Pa, tank pressure with no flow
+const REGULATOR_DROOP: f64 = 0.3e6; // Pa, tank pressure lost at full flow
+const TANK_TIME_CONSTANT: f64 = 0.15; // s
+const CHAMBER_PRESSURE_PER_NEWTON: f64 = 3800.0; // Pa/N, about 2 MPa at full thrust
+const COLD_FLOW_PRESSURE: f64 = 0.8e5; // Pa, both propellants at full flow, unlit
+const CHAMBER_TIME_CONSTANT: f64 = 0.02; // s
+const TRANSDUCER_NOISE: f64 = 2.0; // counts
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transducer {
+    FuelTank,
+    OxidizerTank,
+    Chamber,
+}
+
+// Output of one physical transducer: linear from zero_counts at 0 Pa to
+// full_scale_counts at full_scale_pressure
+struct TransducerResponse {
+    zero_counts: f64,
+    full_scale_counts: f64,
+    full_scale_pressure: f64,
+}
+
+// The units fitted to the simulated vehicle. Their bench calibrations are
+// in config.
+const FUEL_TANK_RESPONSE: TransducerResponse =
+    TransducerResponse { zero_counts: 405.0, full_scale_counts: 3690.0, full_scale_pressure: 5.0e6 };
+const OXIDIZER_TANK_RESPONSE: TransducerResponse =
+    TransducerResponse { zero_counts: 418.0, full_scale_counts: 3679.0, full_scale_pressure: 5.0e6 };
+const CHAMBER_RESPONSE: TransducerResponse =
+    TransducerResponse { zero_counts: 412.0, full_scale_counts: 3684.0, full_scale_pressure: 3.5e6 };
+
+pub struct FeedSim {
+    fuel_tank: f64,
+    oxidizer_tank: f64,
+    chamber: f64,
+    pressurized: bool, // Regulator open; vented tanks bleed down to ambient
+}
+
+impl FeedSim {
+    // Tanks come up pressurized, as loaded on the pad
+    pub fn new() -> Self {
+        FeedSim { fuel_tank: REGULATOR_PRESSURE, oxidizer_tank: REGULATOR_PRESSURE, chamber: 0.0, pressurized: true }
+    }
+
+    pub fn set_pressurized(&mut self, pressurized: bool) {
+        self.pressurized = pressurized;
+    }
+
+    pub fn pressure(&self, transducer: Transducer) -> f64 {
+        match transducer {
+            Transducer::FuelTank => self.fuel_tank,
+            Transducer::OxidizerTank => self.oxidizer_tank,
+            Transducer::Chamber => self.chamber,
+        }
+    }
+
+    // Advance by `dt` with each propellant's flow (0 to 1 of full flow) and
+    // the engine's thrust
+    pub fn step(&mut self, dt: f64, fuel_flow: f64, oxidizer_flow: f64, thrust: f64) {
+        let tank_target = |flow: f64| if self.pressurized { REGULATOR_PRESSURE - REGULATOR_DROOP * flow } else { 0.0 };
+        let (fuel_target, oxidizer_target) = (tank_target(fuel_flow), tank_target(oxidizer_flow));
+        let chamber_target = if thrust > 0.0 {
+            CHAMBER_PRESSURE_PER_NEWTON * thrust
+        } else {
+            COLD_FLOW_PRESSURE * fuel_flow.min(oxidizer_flow)
+        };
+        let tank_alpha = 1.0 - (-dt / TANK_TIME_CONSTANT).exp();
+        let chamber_alpha = 1.0 - (-dt / CHAMBER_TIME_CONSTANT).exp();
+        self.fuel_tank += (fuel_target - self.fuel_tank) * tank_alpha;
+        self.oxidizer_tank += (oxidizer_target - self.oxidizer_tank) * tank_alpha;
+        self.chamber += (chamber_target - self.chamber) * chamber_alpha;
+    }
+
+    // Transducer reading, in ADC counts
+    pub fn transducer_counts(&self, transducer: Transducer) -> u16 {
+        let response = match transducer {
+            Transducer::FuelTank => &FUEL_TANK_RESPONSE,
+            Transducer::OxidizerTank => &OXIDIZER_TANK_RESPONSE,
+            Transducer::Chamber => &CHAMBER_RESPONSE,
+        };
+        let fraction = self.pressure(transducer) / response.full_scale_pressure;
+        let counts = response.zero_counts
+            + (response.full_scale_counts - response.zero_counts) * fraction
+            + sim::with_rng(|rng| gaussian(rng, TRANSDUCER_NOISE));
+        counts.round().clamp(0.0, ADC_FULL_SCALE as f64) as u16
+    }
+}
+
+impl Default for FeedSim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
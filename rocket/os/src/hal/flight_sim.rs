@@ -0,0 +1,435 @@
+// 6-DOF vehicle dynamics used by the dummy HAL to generate sensor data.
+// World frame: x east, y north, z up (origin at the launch pad).
+// Body frame: z along the vehicle axis (nose up), x/y lateral.
+// All math is f64; drivers only ever see the quantized register values.
+
+pub type Vec3 = [f64; 3];
+pub type Quat = [f64; 4]; // [w, x, y, z], body -> world
+
+pub const STANDARD_GRAVITY: f64 = 9.80665; // m/s^2
+const SEA_LEVEL_TEMP: f64 = 288.15; // K
+const SEA_LEVEL_PRESSURE: f64 = 101_325.0; // Pa
+const LAPSE_RATE: f64 = 0.0065; // K/m
+const GAS_CONSTANT_AIR: f64 = 287.05; // J/(kg K)
+const PARACHUTE_INFLATION_TIME: f64 = 0.5; // s, drag area ramps up over this after deployment
+
+// Static description of the simulated vehicle and its environment
+#[derive(Debug, Clone)]
+pub struct VehicleParams {
+    pub dry_mass: f64,                // kg
+    pub propellant_mass: f64,         // kg, burned in proportion to delivered impulse
+    pub thrust_curve: Vec<(f64, f64)>, // (burn time s, thrust N), linearly interpolated
+    pub drag_coefficient: f64,        // Axial Cd
+    pub reference_area: f64,          // m^2
+    pub normal_force_slope: f64,      // CN_alpha, per rad
+    pub stability_margin: f64,        // m, centre of pressure behind centre of gravity
+    pub pitch_damping: f64,           // N m s/rad
+    pub inertia: Vec3,                // Principal moments of inertia (body x, y, z), kg m^2
+    pub wind: Vec3,                   // World-frame wind velocity, m/s
+    pub launch_tilt: f64,             // Rail tilt from vertical towards +x, rad
+    pub rail_length: f64,             // m, rotation is locked until this far up the rail
+    pub drogue_drag_area: f64,        // Cd * A of the drogue, m^2
+    pub main_drag_area: f64,          // Cd * A of the main, m^2
+}
+
+impl Default for VehicleParams {
+    fn default() -> Self {
+        VehicleParams {
+            dry_mass: 8.0,
+            propellant_mass: 1.5,
+            thrust_curve: vec![(0.0, 0.0), (0.1, 540.0), (0.5, 500.0), (3.2, 460.0), (3.6, 0.0)],
+            drag_coefficient: 0.45,
+            reference_area: 0.00785, // 100 mm body tube
+            normal_force_slope: 10.0,
+            stability_margin: 0.15,
+            pitch_damping: 0.8,
+            inertia: [2.2, 2.2, 0.02],
+            wind: [3.0, 0.0, 0.0],
+            launch_tilt: 2f64.to_radians(),
+            rail_length: 3.0,
+            drogue_drag_area: 0.35, // ~20 m/s under drogue
+            main_drag_area: 5.5,    // ~5 m/s under main
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightStatus {
+    OnPad,
+    Airborne,
+    Landed,
+}
+
+// Instantaneous truth state of the simulated vehicle
+#[derive(Debug, Clone, Copy)]
+pub struct VehicleState {
+    pub time: f64,            // s since simulation start
+    pub position: Vec3,       // m, world
+    pub velocity: Vec3,       // m/s, world
+    pub attitude: Quat,       // body -> world
+    pub angular_rate: Vec3,   // rad/s, body
+    pub specific_force: Vec3, // m/s^2, body (what an ideal accelerometer reads)
+    pub mass: f64,            // kg
+    pub thrust: f64,          // N
+    pub burn_time: f64,       // s of full-throttle engine operation so far
+    pub status: FlightStatus,
+    pub drogue_deployed: Option<f64>, // Time the drogue came out
+    pub main_deployed: Option<f64>,
+}
+
+pub struct VehicleSim {
+    params: VehicleParams,
+    state: VehicleState,
+    total_impulse: f64,
+    delivered_impulse: f64,
+    rail_travel: f64,
+}
+
+impl VehicleSim {
+    pub fn new(params: VehicleParams) -> Self {
+        let total_impulse = curve_impulse(&params.thrust_curve);
+        let half = params.launch_tilt / 2.0;
+        let state = VehicleState {
+            time: 0.0,
+            position: [0.0; 3],
+            velocity: [0.0; 3],
+            attitude: [half.cos(), 0.0, half.sin(), 0.0], // Rotation about +y tilts the nose towards +x
+            angular_rate: [0.0; 3],
+            specific_force: [0.0; 3],
+            mass: params.dry_mass + params.propellant_mass,
+            thrust: 0.0,
+            burn_time: 0.0,
+            status: FlightStatus::OnPad,
+            drogue_deployed: None,
+            main_deployed: None,
+        };
+        let mut sim = VehicleSim { params, state, total_impulse, delivered_impulse: 0.0, rail_travel: 0.0 };
+        sim.state.specific_force = sim.rest_specific_force();
+        sim
+    }
+
+    pub fn params(&self) -> &VehicleParams {
+        &self.params
+    }
+
+    pub fn state(&self) -> &VehicleState {
+        &self.state
+    }
+
+    // Parachutes come out on the next step and inflate over
+    // PARACHUTE_INFLATION_TIME. Deploying one twice changes nothing.
+    pub fn deploy_drogue(&mut self) {
+        if self.state.drogue_deployed.is_none() {
+            println!("[SIM] Drogue out at t={:.2}s, {:.0} m", self.state.time, self.state.position[2]);
+            self.state.drogue_deployed = Some(self.state.time);
+        }
+    }
+
+    pub fn deploy_main(&mut self) {
+        if self.state.main_deployed.is_none() {
+            println!("[SIM] Main out at t={:.2}s, {:.0} m", self.state.time, self.state.position[2]);
+            self.state.main_deployed = Some(self.state.time);
+        }
+    }
+
+    // Combined Cd * A of the parachutes out so far, allowing for inflation
+    fn parachute_drag_area(&self) -> f64 {
+        let inflated = |deployed: Option<f64>, area: f64| match deployed {
+            Some(t) => area * ((self.state.time - t) / PARACHUTE_INFLATION_TIME).clamp(0.0, 1.0),
+            None => 0.0,
+        };
+        inflated(self.state.drogue_deployed, self.params.drogue_drag_area)
+            + inflated(self.state.main_deployed, self.params.main_drag_area)
+    }
+
+    // Advance the simulation by one step of `dt` seconds.
+    // `throttle` is the fraction of full propellant flow reaching the engine
+    // (0 with the valves shut). Thrust scales with it and the thrust curve is
+    // worked through at the same rate, so a throttled burn lasts longer.
+    pub fn step(&mut self, dt: f64, throttle: f64) {
+        self.state.time += dt;
+        if self.state.status == FlightStatus::Landed {
+            return;
+        }
+
+        // --- Propulsion ---
+        let throttle = throttle.clamp(0.0, 1.0);
+        let thrust = if throttle > 0.0 { throttle * interpolate(&self.params.thrust_curve, self.state.burn_time) } else { 0.0 };
+        if throttle > 0.0 {
+            self.state.burn_time += throttle * dt;
+            self.delivered_impulse += thrust * dt;
+        }
+        let burned_fraction = if self.total_impulse > 0.0 {
+            (self.delivered_impulse / self.total_impulse).min(1.0)
+        } else {
+            0.0
+        };
+        let mass = self.params.dry_mass + self.params.propellant_mass * (1.0 - burned_fraction);
+        self.state.thrust = thrust;
+        self.state.mass = mass;
+
+        // --- Aerodynamics (forces in body frame) ---
+        let q = self.state.attitude;
+        let (_, _, density) = atmosphere(self.state.position[2]);
+        let air_world = sub(self.state.velocity, self.params.wind);
+        let air_body = rotate_inv(q, air_world);
+        let airspeed = norm(air_body);
+        let dynamic_pressure = 0.5 * density * airspeed * airspeed;
+        let mut aero_body = [0.0; 3];
+        let mut aero_moment = [0.0; 3];
+        if airspeed > 1e-3 {
+            let qa = dynamic_pressure * self.params.reference_area;
+            // Axial drag opposes motion along the body axis
+            aero_body[2] = -qa * self.params.drag_coefficient * air_body[2] / airspeed;
+            // Normal force from angle of attack, small-angle approximation
+            aero_body[0] = -qa * self.params.normal_force_slope * air_body[0] / airspeed;
+            aero_body[1] = -qa * self.params.normal_force_slope * air_body[1] / airspeed;
+            // Normal force acts at the centre of pressure, behind the CG
+            aero_moment = cross([0.0, 0.0, -self.params.stability_margin], [aero_body[0], aero_body[1], 0.0]);
+        }
+        let body_force = add(aero_body, [0.0, 0.0, thrust]);
+        let mut non_gravity_world = rotate(q, body_force);
+        // Parachute drag opposes the motion through the air
+        let air_speed = norm(air_world);
+        if air_speed > 1e-3 {
+            let chute = scale(air_world, -0.5 * density * air_speed * self.parachute_drag_area());
+            non_gravity_world = add(non_gravity_world, chute);
+        }
+
+        // --- Translation ---
+        let mut accel = scale(non_gravity_world, 1.0 / mass);
+        accel[2] -= STANDARD_GRAVITY;
+        if self.state.status == FlightStatus::OnPad {
+            if accel[2] <= 0.0 {
+                // Held by the pad until thrust exceeds weight
+                self.state.velocity = [0.0; 3];
+                self.state.angular_rate = [0.0; 3];
+                self.state.specific_force = self.rest_specific_force();
+                return;
+            }
+            println!("[SIM] Liftoff at t={:.2}s", self.state.time);
+            self.state.status = FlightStatus::Airborne;
+        }
+        let on_rail = self.rail_travel < self.params.rail_length;
+        if on_rail {
+            // The rail removes all motion except along the vehicle axis
+            let axis = rotate(q, [0.0, 0.0, 1.0]);
+            accel = scale(axis, dot(accel, axis).max(0.0));
+        }
+        self.state.velocity = add(self.state.velocity, scale(accel, dt));
+        if on_rail {
+            let axis = rotate(q, [0.0, 0.0, 1.0]);
+            self.state.velocity = scale(axis, dot(self.state.velocity, axis).max(0.0));
+            self.rail_travel += norm(self.state.velocity) * dt;
+        }
+        self.state.position = add(self.state.position, scale(self.state.velocity, dt));
+        let mut gravity_free = accel;
+        gravity_free[2] += STANDARD_GRAVITY;
+        self.state.specific_force = rotate_inv(q, gravity_free);
+
+        // --- Rotation ---
+        if on_rail {
+            self.state.angular_rate = [0.0; 3];
+        } else {
+            let w = self.state.angular_rate;
+            let inertia = self.params.inertia;
+            let damping = [-self.params.pitch_damping * w[0], -self.params.pitch_damping * w[1], 0.0];
+            let moment = add(aero_moment, damping);
+            let iw = [inertia[0] * w[0], inertia[1] * w[1], inertia[2] * w[2]];
+            let gyroscopic = cross(w, iw);
+            let mut w_dot = [0.0; 3];
+            for axis in 0..3 {
+                w_dot[axis] = (moment[axis] - gyroscopic[axis]) / inertia[axis];
+            }
+            self.state.angular_rate = add(w, scale(w_dot, dt));
+            self.state.attitude = integrate_attitude(q, self.state.angular_rate, dt);
+        }
+
+        // --- Ground contact ---
+        if self.state.position[2] <= 0.0 && self.state.velocity[2] < 0.0 {
+            println!(
+                "[SIM] Touchdown at t={:.2}s, impact speed {:.1} m/s",
+                self.state.time,
+                norm(self.state.velocity)
+            );
+            self.state.position[2] = 0.0;
+            self.state.velocity = [0.0; 3];
+            self.state.angular_rate = [0.0; 3];
+            self.state.status = FlightStatus::Landed;
+            self.state.specific_force = self.rest_specific_force();
+        }
+    }
+
+    // Reading of an ideal accelerometer while the vehicle is at rest
+    fn rest_specific_force(&self) -> Vec3 {
+        rotate_inv(self.state.attitude, [0.0, 0.0, STANDARD_GRAVITY])
+    }
+}
+
+// International Standard Atmosphere (troposphere).
+// Returns (temperature K, pressure Pa, density kg/m^3) at geometric altitude `h` m.
+pub fn atmosphere(h: f64) -> (f64, f64, f64) {
+    let temperature = SEA_LEVEL_TEMP - LAPSE_RATE * h.clamp(0.0, 11_000.0);
+    let pressure = SEA_LEVEL_PRESSURE
+        * (temperature / SEA_LEVEL_TEMP).powf(STANDARD_GRAVITY / (GAS_CONSTANT_AIR * LAPSE_RATE));
+    let density = pressure / (GAS_CONSTANT_AIR * temperature);
+    (temperature, pressure, density)
+}
+
+fn interpolate(curve: &[(f64, f64)], t: f64) -> f64 {
+    for pair in curve.windows(2) {
+        let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+        if t >= t0 && t <= t1 {
+            return if t1 > t0 { v0 + (v1 - v0) * (t - t0) / (t1 - t0) } else { v1 };
+        }
+    }
+    0.0 // Before the first or after the last point
+}
+
+fn curve_impulse(curve: &[(f64, f64)]) -> f64 {
+    curve.windows(2).map(|p| 0.5 * (p[0].1 + p[1].1) * (p[1].0 - p[0].0)).sum()
+}
+
+// --- Small vector / quaternion helpers ---
+pub fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn norm(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn quat_mul(a: Quat, b: Quat) -> Quat {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+// Rotate a body-frame vector into the world frame
+pub fn rotate(q: Quat, v: Vec3) -> Vec3 {
+    let r = quat_mul(quat_mul(q, [0.0, v[0], v[1], v[2]]), [q[0], -q[1], -q[2], -q[3]]);
+    [r[1], r[2], r[3]]
+}
+
+// Rotate a world-frame vector into the body frame
+pub fn rotate_inv(q: Quat, v: Vec3) -> Vec3 {
+    rotate([q[0], -q[1], -q[2], -q[3]], v)
+}
+
+fn integrate_attitude(q: Quat, w: Vec3, dt: f64) -> Quat {
+    let dq = quat_mul(q, [0.0, w[0], w[1], w[2]]);
+    let mut next = [q[0] + 0.5 * dq[0] * dt, q[1] + 0.5 * dq[1] * dt, q[2] + 0.5 * dq[2] * dt, q[3] + 0.5 * dq[3] * dt];
+    let n = (next.iter().map(|c| c * c).sum::<f64>()).sqrt();
+    for c in next.iter_mut() {
+        *c /= n;
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.001; // s
+
+    // Straight up, in still air
+    fn vertical() -> VehicleParams {
+        VehicleParams { wind: [0.0; 3], launch_tilt: 0.0, ..VehicleParams::default() }
+    }
+
+    // Fly at full throttle until the thrust curve is used up
+    fn burn(sim: &mut VehicleSim) {
+        let burn_time = sim.params().thrust_curve.last().unwrap().0;
+        while sim.state().burn_time <= burn_time {
+            sim.step(DT, 1.0);
+        }
+    }
+
+    // Nose direction in the world frame
+    fn axis(sim: &VehicleSim) -> Vec3 {
+        rotate(sim.state().attitude, [0.0, 0.0, 1.0])
+    }
+
+    #[test]
+    fn rests_on_the_pad_under_gravity() {
+        let mut sim = VehicleSim::new(VehicleParams::default());
+        for _ in 0..2000 {
+            sim.step(DT, 0.0);
+        }
+        let state = sim.state();
+        assert_eq!(state.status, FlightStatus::OnPad);
+        assert_eq!(state.position, [0.0; 3]);
+        assert_eq!(state.velocity, [0.0; 3]);
+        // The accelerometer reads 1 g, along the (tilted) rail
+        assert!((norm(state.specific_force) - STANDARD_GRAVITY).abs() < 1e-9);
+        let tilt = VehicleParams::default().launch_tilt;
+        assert!((state.specific_force[2] - STANDARD_GRAVITY * tilt.cos()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn burns_down_to_the_dry_mass() {
+        for throttle in [1.0, 0.5] {
+            let mut sim = VehicleSim::new(vertical());
+            let params = sim.params().clone();
+            assert_eq!(sim.state().mass, params.dry_mass + params.propellant_mass);
+            // Throttled, the same propellant lasts longer
+            let burn_time = params.thrust_curve.last().unwrap().0;
+            let mut time = 0.0;
+            while sim.state().burn_time <= burn_time {
+                sim.step(DT, throttle);
+                time += DT;
+            }
+            assert!((time - burn_time / throttle).abs() < 0.01, "{} s at {}", time, throttle);
+            assert!((sim.state().mass - params.dry_mass).abs() < 1e-3, "{} kg at {}", sim.state().mass, throttle);
+            sim.step(DT, throttle);
+            assert_eq!(sim.state().thrust, 0.0);
+        }
+    }
+
+    #[test]
+    fn coasts_to_the_ballistic_apogee_without_drag() {
+        let mut sim = VehicleSim::new(VehicleParams { drag_coefficient: 0.0, ..vertical() });
+        burn(&mut sim);
+        let (height, speed) = (sim.state().position[2], sim.state().velocity[2]);
+        while sim.state().velocity[2] > 0.0 {
+            sim.step(DT, 0.0);
+        }
+        let ballistic = height + speed * speed / (2.0 * STANDARD_GRAVITY);
+        assert!((sim.state().position[2] - ballistic).abs() < 0.5, "{} m vs {} m", sim.state().position[2], ballistic);
+        // In flight the accelerometer reads nothing at all
+        assert!(norm(sim.state().specific_force) < 1e-9);
+    }
+
+    #[test]
+    fn flies_straight_up_in_still_air() {
+        let mut sim = VehicleSim::new(vertical());
+        burn(&mut sim);
+        while sim.state().velocity[2] > 0.0 {
+            sim.step(DT, 0.0);
+            assert!((axis(&sim)[2] - 1.0).abs() < 1e-9);
+        }
+        let state = sim.state();
+        assert!(state.position[2] > 500.0);
+        assert!(state.position[0].abs() < 1e-6 && state.position[1].abs() < 1e-6);
+        assert_eq!(state.angular_rate, [0.0; 3]);
+    }
+}
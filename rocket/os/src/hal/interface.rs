@@ -1,6 +1,7 @@
 // Defines the Hardware Abstraction Layer traits
 // These describe *what* hardware can do, not *how*.
 use crate::error::HalError;
+use std::time::Duration;
 type HalResult<T> = std::result::Result<T, HalError>;
 
 // --- Digital I/O ---
@@ -53,7 +54,15 @@ pub trait Adc<WORD> {
     fn read(&mut self, channel: u8) -> std::result::Result<WORD, Self::Error>; // Use associated error type
 }
 
-// Add other traits as needed (UART, PWM, etc.)
+// --- PWM ---
+pub trait PwmOutput {
+    // Fraction of each period the output is high, 0.0 to 1.0
+    fn set_duty_cycle(&mut self, duty: f32) -> HalResult<()>;
+    fn duty_cycle(&self) -> f32;
+    fn period(&self) -> Duration;
+}
+
+// Add other traits as needed (UART, etc.)
 
 // Marker trait for a complete HAL implementation for a board/chip
 pub trait FullHardwareAbstraction {
@@ -61,6 +70,8 @@ pub trait FullHardwareAbstraction {
     type I2cController: I2cBus;
     type SpiController: SpiBus;
     type TimerDelay: Delay;
+    type PwmChannel: PwmOutput;
+    type AdcController: Adc<u16, Error = HalError>;
     // Add other peripheral types here...
 
     // Methods to get instances of peripherals
@@ -68,5 +79,7 @@ pub trait FullHardwareAbstraction {
     fn get_i2c_bus(&self, bus_id: u8) -> Option<Self::I2cController>;
     fn get_spi_bus(&self, bus_id: u8) -> Option<Self::SpiController>;
     fn get_delay_timer(&self) -> Self::TimerDelay;
+    fn get_pwm_channel(&self, channel: u8) -> Option<Self::PwmChannel>;
+    fn get_adc(&self, adc_id: u8) -> Option<Self::AdcController>;
     // ...
 }
@@ -1,2 +1,6 @@
 pub mod interface;
 pub mod dummy_hal; // The simulation implementation
+pub mod flight_sim; // Vehicle dynamics driving the simulated sensors
+pub mod radio_sim; // SX127x register model behind the simulated SPI bus
+pub mod servo_sim; // Throttle servo behind the simulated PWM channel and ADC
+pub mod feed_sim; // Tank and chamber pressures behind the simulated transducers
@@ -0,0 +1,562 @@
+// SX127x register model used by the dummy HAL behind a simulated SPI bus,
+// and the air channel between the simulated radios.
+//
+// The model covers what a LoRa-mode driver touches: operating modes, the
+// 256-byte FIFO and its pointers, IRQ flags (with mask and DIO0 mapping),
+// packet RSSI/SNR and the version register. FSK/OOK mode only stores
+// registers. Transmissions last their LoRa time on air; the channel then
+// decides per receiver, from a link budget over the distance between the
+// antennas, whether and how the packet arrives.
+use crate::hal::dummy_hal::gaussian;
+use crate::kernel::sim::{self, Instant};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const REG_FIFO: usize = 0x00;
+const REG_OP_MODE: usize = 0x01;
+const REG_FRF_MSB: usize = 0x06;
+const REG_PA_CONFIG: usize = 0x09;
+const REG_OCP: usize = 0x0B;
+const REG_LNA: usize = 0x0C;
+const REG_FIFO_ADDR_PTR: usize = 0x0D;
+const REG_FIFO_TX_BASE_ADDR: usize = 0x0E;
+const REG_FIFO_RX_BASE_ADDR: usize = 0x0F;
+const REG_FIFO_RX_CURRENT_ADDR: usize = 0x10;
+const REG_IRQ_FLAGS_MASK: usize = 0x11;
+const REG_IRQ_FLAGS: usize = 0x12;
+const REG_RX_NB_BYTES: usize = 0x13;
+const REG_PKT_SNR_VALUE: usize = 0x19;
+const REG_PKT_RSSI_VALUE: usize = 0x1A;
+const REG_RSSI_VALUE: usize = 0x1B;
+const REG_MODEM_CONFIG_1: usize = 0x1D;
+const REG_MODEM_CONFIG_2: usize = 0x1E;
+const REG_SYMB_TIMEOUT_LSB: usize = 0x1F;
+const REG_PREAMBLE_MSB: usize = 0x20;
+const REG_PREAMBLE_LSB: usize = 0x21;
+const REG_PAYLOAD_LENGTH: usize = 0x22;
+const REG_MODEM_CONFIG_3: usize = 0x26;
+const REG_SYNC_WORD: usize = 0x39;
+const REG_DIO_MAPPING_1: usize = 0x40;
+const REG_VERSION: usize = 0x42;
+const REG_PA_DAC: usize = 0x4D;
+const REGISTER_COUNT: usize = 0x80;
+
+const LONG_RANGE_MODE: u8 = 0x80;
+const MODE_MASK: u8 = 0x07;
+const MODE_SLEEP: u8 = 0x00;
+const MODE_STDBY: u8 = 0x01;
+const MODE_TX: u8 = 0x03;
+const MODE_RX_CONTINUOUS: u8 = 0x05;
+const MODE_RX_SINGLE: u8 = 0x06;
+const IRQ_RX_DONE: u8 = 0x40;
+const IRQ_PAYLOAD_CRC_ERROR: u8 = 0x20;
+const IRQ_VALID_HEADER: u8 = 0x10;
+const IRQ_TX_DONE: u8 = 0x08;
+const IRQ_CAD_DONE: u8 = 0x04;
+const PA_BOOST: u8 = 0x80;
+const PA_DAC_HIGH_POWER: u8 = 0x07;
+
+const CRYSTAL_HZ: f64 = 32e6;
+const HF_PORT_MIN_HZ: f64 = 779e6;
+const RSSI_OFFSET_HF: f64 = -157.0; // dBm
+const RSSI_OFFSET_LF: f64 = -164.0;
+const NOISE_FIGURE: f64 = 6.0; // dB, receiver
+const THERMAL_NOISE_DENSITY: f64 = -174.0; // dBm/Hz at 290 K
+const MAX_REPORTED_SNR: f64 = 10.0; // dB, the chip's SNR estimate saturates around here
+
+// Modulation settings, decoded from the registers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModemSettings {
+    pub frf: u32, // Carrier frequency in synthesizer steps (32 MHz / 2^19)
+    pub spreading_factor: u8,
+    pub bandwidth: f64,  // Hz
+    pub coding_rate: u8, // 1..=4 for 4/5..4/8
+    pub implicit_header: bool,
+    pub crc: bool,
+    pub preamble_length: u16,
+    pub low_data_rate_optimize: bool,
+    pub sync_word: u8,
+}
+
+impl ModemSettings {
+    pub fn frequency(&self) -> f64 {
+        self.frf as f64 * CRYSTAL_HZ / (1u32 << 19) as f64
+    }
+
+    // Semtech AN1200.13
+    pub fn time_on_air(&self, payload_len: usize) -> Duration {
+        let sf = self.spreading_factor as f64;
+        let symbol_time = (1u32 << self.spreading_factor) as f64 / self.bandwidth;
+        let de = if self.low_data_rate_optimize { 1.0 } else { 0.0 };
+        let ih = if self.implicit_header { 1.0 } else { 0.0 };
+        let crc = if self.crc { 1.0 } else { 0.0 };
+        let bits = 8.0 * payload_len as f64 - 4.0 * sf + 28.0 + 16.0 * crc - 20.0 * ih;
+        let payload = 8.0 + ((bits / (4.0 * (sf - 2.0 * de))).ceil() * (self.coding_rate as f64 + 4.0)).max(0.0);
+        Duration::from_secs_f64((self.preamble_length as f64 + 4.25 + payload) * symbol_time)
+    }
+
+    // A receiver only locks onto packets sent with the same carrier,
+    // chirp and sync word
+    fn compatible(&self, other: &ModemSettings) -> bool {
+        self.frf == other.frf
+            && self.spreading_factor == other.spreading_factor
+            && self.bandwidth == other.bandwidth
+            && self.sync_word == other.sync_word
+    }
+
+    // dBm of thermal noise in the receive bandwidth
+    fn noise_floor(&self) -> f64 {
+        THERMAL_NOISE_DENSITY + 10.0 * self.bandwidth.log10() + NOISE_FIGURE
+    }
+
+    // Lowest SNR the demodulator still decodes (datasheet table 13)
+    fn demodulation_floor(&self) -> f64 {
+        -2.5 * (self.spreading_factor as f64 - 4.0)
+    }
+
+    fn rssi_offset(&self) -> f64 {
+        if self.frequency() >= HF_PORT_MIN_HZ { RSSI_OFFSET_HF } else { RSSI_OFFSET_LF }
+    }
+}
+
+// A packet that has just finished transmitting
+#[derive(Debug, Clone)]
+pub struct Transmission {
+    pub payload: Vec<u8>,
+    pub modem: ModemSettings,
+    pub tx_power: f64, // dBm at the antenna port
+    pub ended: Instant, // When the last symbol went out
+}
+
+pub struct Sx127x {
+    regs: [u8; REGISTER_COUNT],
+    fifo: [u8; 256],
+    tx_done_at: Option<Instant>, // Pending transmission completes at this time
+    rx_addr: u8,                 // Where the next received packet goes in the FIFO
+}
+
+impl Sx127x {
+    // Power-on register values (datasheet table 41)
+    pub fn new() -> Self {
+        let mut regs = [0u8; REGISTER_COUNT];
+        regs[REG_OP_MODE] = 0x09; // FSK, low frequency mode, standby
+        regs[REG_FRF_MSB..REG_FRF_MSB + 3].copy_from_slice(&[0x6C, 0x80, 0x00]); // 434 MHz
+        regs[REG_PA_CONFIG] = 0x4F;
+        regs[REG_OCP] = 0x2B;
+        regs[REG_LNA] = 0x20;
+        regs[REG_FIFO_TX_BASE_ADDR] = 0x80;
+        regs[REG_MODEM_CONFIG_1] = 0x72;
+        regs[REG_MODEM_CONFIG_2] = 0x70;
+        regs[REG_SYMB_TIMEOUT_LSB] = 0x64;
+        regs[REG_PREAMBLE_LSB] = 0x08;
+        regs[REG_PAYLOAD_LENGTH] = 0x01;
+        regs[REG_MODEM_CONFIG_3] = 0x04;
+        regs[REG_SYNC_WORD] = 0x12;
+        regs[REG_VERSION] = 0x12;
+        regs[REG_PA_DAC] = 0x84;
+        Sx127x { regs, fifo: [0; 256], tx_done_at: None, rx_addr: 0 }
+    }
+
+    fn mode(&self) -> u8 {
+        self.regs[REG_OP_MODE] & MODE_MASK
+    }
+
+    fn lora(&self) -> bool {
+        self.regs[REG_OP_MODE] & LONG_RANGE_MODE != 0
+    }
+
+    fn listening(&self) -> bool {
+        self.lora() && matches!(self.mode(), MODE_RX_CONTINUOUS | MODE_RX_SINGLE)
+    }
+
+    pub fn modem(&self) -> ModemSettings {
+        let r = &self.regs;
+        ModemSettings {
+            frf: u32::from_be_bytes([0, r[REG_FRF_MSB], r[REG_FRF_MSB + 1], r[REG_FRF_MSB + 2]]),
+            spreading_factor: (r[REG_MODEM_CONFIG_2] >> 4).clamp(6, 12),
+            bandwidth: [7_800.0, 10_400.0, 15_600.0, 20_800.0, 31_250.0, 41_700.0, 62_500.0, 125_000.0, 250_000.0, 500_000.0]
+                [((r[REG_MODEM_CONFIG_1] >> 4) as usize).min(9)],
+            coding_rate: ((r[REG_MODEM_CONFIG_1] >> 1) & 0x07).clamp(1, 4),
+            implicit_header: r[REG_MODEM_CONFIG_1] & 0x01 != 0,
+            crc: r[REG_MODEM_CONFIG_2] & 0x04 != 0,
+            preamble_length: u16::from_be_bytes([r[REG_PREAMBLE_MSB], r[REG_PREAMBLE_LSB]]),
+            low_data_rate_optimize: r[REG_MODEM_CONFIG_3] & 0x08 != 0,
+            sync_word: r[REG_SYNC_WORD],
+        }
+    }
+
+    // Output power, dBm (datasheet 5.4.3)
+    fn tx_power(&self) -> f64 {
+        let pa_config = self.regs[REG_PA_CONFIG];
+        let output_power = (pa_config & 0x0F) as f64;
+        if pa_config & PA_BOOST == 0 {
+            let max_power = 10.8 + 0.6 * ((pa_config >> 4) & 0x07) as f64;
+            max_power - (15.0 - output_power)
+        } else if self.regs[REG_PA_DAC] & 0x07 == PA_DAC_HIGH_POWER {
+            output_power + 5.0
+        } else {
+            output_power + 2.0
+        }
+    }
+
+    // One SPI transaction: address byte (bit 7 set for a write), then data.
+    // Bursts auto-increment the address, except on RegFifo where every byte
+    // goes through the FIFO pointer. Read data replaces `buffer` in place.
+    pub fn transfer(&mut self, now: Instant, buffer: &mut [u8]) {
+        let Some((&address, data)) = buffer.split_first() else {
+            return;
+        };
+        let write = address & 0x80 != 0;
+        let mut reg = (address & 0x7F) as usize;
+        let data = data.to_vec();
+        for (i, value) in data.into_iter().enumerate() {
+            buffer[i + 1] = if write {
+                self.write_register(now, reg, value);
+                0
+            } else {
+                self.read_register(reg)
+            };
+            if reg != REG_FIFO {
+                reg = (reg + 1) % REGISTER_COUNT;
+            }
+        }
+        buffer[0] = 0;
+    }
+
+    fn read_register(&mut self, reg: usize) -> u8 {
+        match reg {
+            REG_FIFO => {
+                let ptr = self.regs[REG_FIFO_ADDR_PTR];
+                self.regs[REG_FIFO_ADDR_PTR] = ptr.wrapping_add(1);
+                self.fifo[ptr as usize]
+            }
+            REG_RSSI_VALUE if self.lora() => {
+                let modem = self.modem();
+                (modem.noise_floor() - modem.rssi_offset()).round().clamp(0.0, 255.0) as u8
+            }
+            _ => self.regs[reg],
+        }
+    }
+
+    fn write_register(&mut self, now: Instant, reg: usize, value: u8) {
+        match reg {
+            REG_FIFO => {
+                let ptr = self.regs[REG_FIFO_ADDR_PTR];
+                self.fifo[ptr as usize] = value;
+                self.regs[REG_FIFO_ADDR_PTR] = ptr.wrapping_add(1);
+            }
+            REG_OP_MODE => {
+                // The modem can only be switched in sleep mode
+                let long_range = if self.mode() == MODE_SLEEP { value & LONG_RANGE_MODE } else { self.regs[reg] & LONG_RANGE_MODE };
+                let was_listening = self.listening();
+                self.regs[reg] = long_range | (value & !LONG_RANGE_MODE);
+                if self.lora() && self.mode() == MODE_TX {
+                    if self.tx_done_at.is_none() {
+                        let len = self.regs[REG_PAYLOAD_LENGTH] as usize;
+                        self.tx_done_at = Some(now + self.modem().time_on_air(len));
+                    }
+                } else {
+                    self.tx_done_at = None; // Leaving TX aborts the transmission
+                }
+                if self.listening() && !was_listening {
+                    self.rx_addr = self.regs[REG_FIFO_RX_BASE_ADDR];
+                }
+            }
+            REG_IRQ_FLAGS => self.regs[reg] &= !value, // Write 1 to clear
+            REG_VERSION | REG_RX_NB_BYTES | REG_FIFO_RX_CURRENT_ADDR | REG_PKT_SNR_VALUE | REG_PKT_RSSI_VALUE => {} // Read-only
+            _ => self.regs[reg] = value,
+        }
+    }
+
+    fn raise_irq(&mut self, flag: u8) {
+        if self.regs[REG_IRQ_FLAGS_MASK] & flag == 0 {
+            self.regs[REG_IRQ_FLAGS] |= flag;
+        }
+    }
+
+    // Complete a transmission that is due by `now`
+    pub fn update(&mut self, now: Instant) -> Option<Transmission> {
+        let done_at = self.tx_done_at?;
+        if done_at > now {
+            return None;
+        }
+        self.tx_done_at = None;
+        let base = self.regs[REG_FIFO_TX_BASE_ADDR];
+        let len = self.regs[REG_PAYLOAD_LENGTH];
+        let payload = (0..len).map(|i| self.fifo[base.wrapping_add(i) as usize]).collect();
+        self.raise_irq(IRQ_TX_DONE);
+        self.regs[REG_OP_MODE] = (self.regs[REG_OP_MODE] & !MODE_MASK) | MODE_STDBY;
+        Some(Transmission { payload, modem: self.modem(), tx_power: self.tx_power(), ended: done_at })
+    }
+
+    // Hand a packet to the demodulator as it finishes arriving. Returns
+    // false if the radio wasn't listening on a compatible channel.
+    fn receive(&mut self, arrival: &Arrival) -> bool {
+        let modem = self.modem();
+        if !self.listening() || !modem.compatible(&arrival.modem) {
+            return false;
+        }
+        let start = self.rx_addr;
+        for &byte in &arrival.payload {
+            self.fifo[self.rx_addr as usize] = byte;
+            self.rx_addr = self.rx_addr.wrapping_add(1);
+        }
+        self.regs[REG_FIFO_RX_CURRENT_ADDR] = start;
+        self.regs[REG_RX_NB_BYTES] = arrival.payload.len() as u8;
+        // Inverse of the packet RSSI formula the datasheet gives (5.5.5)
+        let snr = arrival.snr.min(MAX_REPORTED_SNR);
+        let above_offset = arrival.rssi - modem.rssi_offset();
+        let pkt_rssi = if snr < 0.0 { above_offset - snr } else { above_offset * 15.0 / 16.0 };
+        self.regs[REG_PKT_RSSI_VALUE] = pkt_rssi.round().clamp(0.0, 255.0) as u8;
+        self.regs[REG_PKT_SNR_VALUE] = (snr * 4.0).round() as i8 as u8;
+        self.raise_irq(IRQ_VALID_HEADER);
+        if arrival.crc_error {
+            self.raise_irq(IRQ_PAYLOAD_CRC_ERROR);
+        }
+        self.raise_irq(IRQ_RX_DONE);
+        if self.mode() == MODE_RX_SINGLE {
+            self.regs[REG_OP_MODE] = (self.regs[REG_OP_MODE] & !MODE_MASK) | MODE_STDBY;
+        }
+        true
+    }
+
+    // Level of the DIO0 pin for the flag it is mapped to
+    pub fn dio0(&self) -> bool {
+        let flag = match self.regs[REG_DIO_MAPPING_1] >> 6 {
+            0b00 => IRQ_RX_DONE,
+            0b01 => IRQ_TX_DONE,
+            0b10 => IRQ_CAD_DONE,
+            _ => return false,
+        };
+        self.regs[REG_IRQ_FLAGS] & flag != 0
+    }
+}
+
+impl Default for Sx127x {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Impairments of the simulated air channel
+#[derive(Debug, Clone)]
+pub struct LinkParams {
+    pub packet_loss: f64,        // Probability a packet is lost whatever the signal (interference)
+    pub bit_error_rate: f64,     // Probability each delivered bit is flipped
+    pub latency: Duration,       // From the end of transmission to RxDone at the receiver
+    pub ground_station: [f64; 3], // m, world-frame position of the ground antenna
+    pub antenna_gain: f64,       // dBi, at each end
+    pub path_loss_exponent: f64, // 2 in free space, more near the ground
+    pub fading: f64,             // dB, 1-sigma log-normal fading per packet
+}
+
+impl Default for LinkParams {
+    fn default() -> Self {
+        LinkParams {
+            packet_loss: 0.02,
+            bit_error_rate: 1e-5,
+            latency: Duration::from_millis(2),
+            ground_station: [300.0, -150.0, 2.0],
+            antenna_gain: 2.0,
+            path_loss_exponent: 2.0,
+            fading: 3.0,
+        }
+    }
+}
+
+// A packet on its way to one receiver
+struct Arrival {
+    at: Instant,
+    to: u8, // Receiving radio (SPI bus id)
+    payload: Vec<u8>,
+    modem: ModemSettings,
+    rssi: f64, // dBm
+    snr: f64,  // dB
+    crc_error: bool,
+}
+
+pub struct AirChannel {
+    pub params: LinkParams,
+    in_flight: Vec<Arrival>,
+}
+
+impl AirChannel {
+    pub fn new(params: LinkParams) -> Self {
+        AirChannel { params, in_flight: Vec::new() }
+    }
+
+    // Propagate a finished transmission to each receiver, given as
+    // (SPI bus id, distance in m). Lost packets never arrive. Arrivals are
+    // timed from the end of the transmission, however late it is noticed.
+    pub fn transmit(&mut self, from: u8, tx: &Transmission, receivers: &[(u8, f64)]) {
+        let p = &self.params;
+        let frequency = tx.modem.frequency();
+        for &(to, distance) in receivers {
+            // Log-distance path loss, anchored at free space over the first metre
+            let loss_1m = 20.0 * frequency.log10() - 147.55;
+            let path_loss = loss_1m + 10.0 * p.path_loss_exponent * distance.max(1.0).log10();
+            let (rssi, lost, flipped) = sim::with_rng(|rng| {
+                let rssi = tx.tx_power + 2.0 * p.antenna_gain - path_loss + gaussian(rng, p.fading);
+                let lost = rng.gen_bool(p.packet_loss.clamp(0.0, 1.0));
+                let flipped: Vec<usize> =
+                    (0..tx.payload.len() * 8).filter(|_| rng.gen_bool(p.bit_error_rate.clamp(0.0, 1.0))).collect();
+                (rssi, lost, flipped)
+            });
+            let snr = rssi - tx.modem.noise_floor();
+            let outcome = if snr < tx.modem.demodulation_floor() {
+                "lost, below sensitivity"
+            } else if lost {
+                "lost"
+            } else if flipped.is_empty() {
+                "delivered"
+            } else {
+                "delivered with bit errors"
+            };
+            println!(
+                "[HAL] RF {} -> {}: {} bytes, {:.1} ms on air, {:.0} m, RSSI {:.1} dBm, SNR {:.1} dB: {}",
+                from,
+                to,
+                tx.payload.len(),
+                tx.modem.time_on_air(tx.payload.len()).as_secs_f64() * 1e3,
+                distance,
+                rssi,
+                snr,
+                outcome
+            );
+            if snr < tx.modem.demodulation_floor() || lost {
+                continue;
+            }
+            let mut payload = tx.payload.clone();
+            for bit in &flipped {
+                payload[bit / 8] ^= 1 << (bit % 8);
+            }
+            self.in_flight.push(Arrival {
+                at: tx.ended + p.latency,
+                to,
+                payload,
+                modem: tx.modem,
+                rssi,
+                snr,
+                crc_error: !flipped.is_empty() && tx.modem.crc,
+            });
+        }
+    }
+
+    // Hand every packet due by `now` to its receiver
+    pub fn deliver(&mut self, now: Instant, radios: &mut BTreeMap<u8, Sx127x>) {
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight).into_iter().partition(|a| a.at <= now);
+        self.in_flight = pending;
+        for arrival in due {
+            let received = radios.get_mut(&arrival.to).is_some_and(|r| r.receive(&arrival));
+            if !received {
+                println!("[HAL] RF packet for {} missed (receiver not listening on that channel)", arrival.to);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::drivers::radio::{LinkStats, Radio, RadioConfig};
+    use crate::hal::dummy_hal::{self, DummyDelay, DummyPin, DummySpi};
+    use crate::hal::interface::FullHardwareAbstraction;
+    use crate::kernel::sim::ClockMode;
+
+    const PACKETS: u32 = 400;
+    const PACKET_LEN: usize = 20;
+
+    fn radio(bus: u8, cs: u8, irq: u8) -> Radio<DummySpi, DummyPin, DummyPin, DummyDelay> {
+        let hal = dummy_hal::get_dummy_hal();
+        let (spi, cs, irq) = (hal.get_spi_bus(bus).unwrap(), hal.get_gpio_pin(cs).unwrap(), hal.get_gpio_pin(irq).unwrap());
+        Radio::new(spi, cs, irq, hal.get_delay_timer(), RadioConfig::default()).unwrap()
+    }
+
+    // Send PACKETS packets from the flight radio over a link with `params`
+    // and return the ground radio's stats
+    fn send_over(params: LinkParams) -> LinkStats {
+        sim::init(ClockMode::Simulated, 3);
+        dummy_hal::reset();
+        dummy_hal::set_link_params(params);
+        let mut flight = radio(config::DUMMY_RADIO_SPI_BUS, config::DUMMY_RADIO_CS_PIN, config::DUMMY_RADIO_IRQ_PIN);
+        let mut ground = radio(config::DUMMY_GROUND_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_CS_PIN, config::DUMMY_GROUND_RADIO_IRQ_PIN);
+        let mut buffer = [0u8; 255];
+        for i in 0..PACKETS {
+            let packet = [i as u8; PACKET_LEN];
+            flight.send_packet(&packet).unwrap();
+            sim::sleep(Duration::from_millis(5));
+            match ground.receive_packet(&mut buffer).unwrap() {
+                0 => {}
+                len => assert_eq!(buffer[..len], packet), // The CRC catches every corrupted packet
+            }
+        }
+        assert_eq!(flight.stats().packets_sent, PACKETS);
+        ground.stats()
+    }
+
+    // Within 4 sigma of the expected count
+    fn assert_binomial(count: u32, p: f64) {
+        let n = PACKETS as f64;
+        let sigma = (n * p * (1.0 - p)).sqrt();
+        assert!((count as f64 - n * p).abs() < 4.0 * sigma, "{} of {}, expected {:.0}", count, PACKETS, n * p);
+    }
+
+    #[test]
+    fn link_impairments_show_in_the_delivery_stats() {
+        let _exclusive = sim::exclusive();
+        let clean = LinkParams { packet_loss: 0.0, bit_error_rate: 0.0, fading: 0.0, ..LinkParams::default() };
+        assert_eq!(send_over(clean.clone()), LinkStats { packets_sent: 0, packets_received: PACKETS, crc_errors: 0 });
+
+        let stats = send_over(LinkParams { packet_loss: 0.25, ..clean.clone() });
+        assert_binomial(stats.packets_received, 0.75);
+        assert_eq!(stats.crc_errors, 0);
+
+        // A packet arrives corrupted if any of its bits flips
+        let bit_error_rate: f64 = 1e-3;
+        let corrupted = 1.0 - (1.0 - bit_error_rate).powi(8 * PACKET_LEN as i32);
+        let stats = send_over(LinkParams { bit_error_rate, ..clean.clone() });
+        assert_binomial(stats.crc_errors, corrupted);
+        assert_eq!(stats.packets_received + stats.crc_errors, PACKETS);
+
+        // Out of range: below the demodulation floor nothing arrives
+        let stats = send_over(LinkParams { ground_station: [0.0, 0.0, 2.0e6], ..clean });
+        assert_eq!(stats, LinkStats::default());
+    }
+
+    #[test]
+    fn packets_take_their_time_on_air_plus_latency() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 3);
+        dummy_hal::reset();
+        let latency = Duration::from_millis(20);
+        dummy_hal::set_link_params(LinkParams { packet_loss: 0.0, bit_error_rate: 0.0, fading: 0.0, latency, ..LinkParams::default() });
+        let mut flight = radio(config::DUMMY_RADIO_SPI_BUS, config::DUMMY_RADIO_CS_PIN, config::DUMMY_RADIO_IRQ_PIN);
+        let mut ground = radio(config::DUMMY_GROUND_RADIO_SPI_BUS, config::DUMMY_GROUND_RADIO_CS_PIN, config::DUMMY_GROUND_RADIO_IRQ_PIN);
+
+        // SF7/125 kHz, 4/5, 8-symbol preamble, CRC: 40.25 symbols of 1.024 ms
+        // for 10 bytes (Semtech's LoRa calculator agrees)
+        let config = RadioConfig::default();
+        assert_eq!(config.time_on_air(10).as_micros(), 41_216);
+        let mut chip = Sx127x::new();
+        chip.regs[REG_OP_MODE] = LONG_RANGE_MODE | MODE_STDBY;
+        chip.regs[REG_MODEM_CONFIG_1] = 0x72;
+        chip.regs[REG_MODEM_CONFIG_2] = 0x74;
+        // The driver's estimate agrees with the model's, up to f32 rounding
+        for len in [1, 10, 64, 255] {
+            let (model, driver) = (chip.modem().time_on_air(len).as_micros(), config.time_on_air(len).as_micros());
+            assert!(model.abs_diff(driver) <= 1, "{} bytes: {} vs {} us", len, model, driver);
+        }
+
+        let started = sim::now();
+        flight.send_packet(&[0x42; 10]).unwrap();
+        let sent = sim::now();
+        assert!(sent - started >= config.time_on_air(10));
+        let mut buffer = [0u8; 255];
+        sim::sleep(latency - Duration::from_millis(2));
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), 0, "arrived before the link latency");
+        sim::sleep(Duration::from_millis(3));
+        assert_eq!(ground.receive_packet(&mut buffer).unwrap(), 10);
+    }
+}
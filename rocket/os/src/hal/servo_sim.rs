@@ -0,0 +1,69 @@
+// Position servo used by the dummy HAL: driven by pulse width on a
+// simulated PWM channel, read back through a feedback potentiometer on the
+// simulated ADC. The servo slews at a fixed speed towards the position the
+// last valid pulse asked for and holds when the pulses stop. Until it is
+// first driven it sits fully open, where its return spring leaves it.
+use crate::hal::dummy_hal::gaussian;
+use crate::kernel::sim::{self, Instant};
+
+// Pulse widths for the ends of travel; wider or narrower pulses clip
+pub const PULSE_MIN_US: f64 = 1000.0;
+pub const PULSE_MAX_US: f64 = 2000.0;
+// Anything outside this is not a servo pulse (e.g. a 0% duty cycle)
+const PULSE_VALID_US: (f64, f64) = (500.0, 2500.0);
+const FULL_TRAVEL_TIME: f64 = 0.3; // s, end to end at full speed
+// The feedback pot spans this fraction of the ADC reference across travel
+const FEEDBACK_SPAN: (f64, f64) = (0.1, 0.9);
+const FEEDBACK_NOISE: f64 = 1.5; // counts
+pub const ADC_FULL_SCALE: u16 = 4095; // 12-bit converter
+
+pub struct ServoSim {
+    position: f64,       // 0 (closed) to 1 (full travel)
+    target: Option<f64>, // None until the first valid pulse
+    last_update: Instant,
+}
+
+impl ServoSim {
+    pub fn new() -> Self {
+        ServoSim { position: 1.0, target: None, last_update: sim::now() }
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    // Move towards the target for the time since the last update. Earlier
+    // times (the vehicle model catching up) leave it where it is.
+    pub fn advance_to(&mut self, now: Instant) {
+        if now <= self.last_update {
+            return;
+        }
+        let dt = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+        if let Some(target) = self.target {
+            let step = dt / FULL_TRAVEL_TIME;
+            self.position += (target - self.position).clamp(-step, step);
+        }
+    }
+
+    pub fn set_pulse(&mut self, now: Instant, pulse_us: f64) {
+        self.advance_to(now);
+        if pulse_us < PULSE_VALID_US.0 || pulse_us > PULSE_VALID_US.1 {
+            return; // No pulses: hold position
+        }
+        self.target = Some(((pulse_us - PULSE_MIN_US) / (PULSE_MAX_US - PULSE_MIN_US)).clamp(0.0, 1.0));
+    }
+
+    // Feedback pot reading, in ADC counts
+    pub fn feedback_counts(&self) -> u16 {
+        let fraction = FEEDBACK_SPAN.0 + (FEEDBACK_SPAN.1 - FEEDBACK_SPAN.0) * self.position;
+        let counts = fraction * ADC_FULL_SCALE as f64 + sim::with_rng(|rng| gaussian(rng, FEEDBACK_NOISE));
+        counts.round().clamp(0.0, ADC_FULL_SCALE as f64) as u16
+    }
+}
+
+impl Default for ServoSim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,2 +1,3 @@
 pub mod sync;
 pub mod task;
+pub mod sim; // Clock and randomness source (real or simulated)
@@ -0,0 +1,318 @@
+// Simulation runtime: time source and randomness for the whole OS.
+//
+// In RealTime mode this is a thin layer over std::time / std::thread::sleep.
+// In Simulated mode time is virtual: only one task thread runs at a time,
+// and the clock jumps straight to the next wake-up once every task is
+// sleeping. Hand-over order is fully determined by (wake time, priority,
+// spawn order), so together with the seeded RNG a run is reproducible
+// bit-for-bit. A task only gives up the CPU when it sleeps or waits on a
+// lock, so a higher-priority release "preempts" at those points.
+use std::{
+    cell::Cell,
+    cmp::Reverse,
+    collections::BTreeMap,
+    ops::{Add, Sub},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex as StdMutex, MutexGuard as StdMutexGuard,
+    },
+    time::Duration,
+};
+use lazy_static::lazy_static;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockMode {
+    RealTime,  // Wall clock, tasks run freely as OS threads
+    Simulated, // Virtual clock, tasks take turns (faster than real time)
+}
+
+// Point in time since the clock was initialised (microsecond resolution).
+// Replaces std::time::Instant so the same code runs on either clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    micros: u64,
+}
+
+impl Instant {
+    pub fn from_micros(micros: u64) -> Self {
+        Instant { micros }
+    }
+
+    pub fn as_micros(&self) -> u64 {
+        self.micros
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.micros as f64 / 1e6
+    }
+
+    // Saturates to zero if `earlier` is actually later
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_micros(self.micros.saturating_sub(earlier.micros))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+    fn add(self, rhs: Duration) -> Instant {
+        Instant { micros: self.micros + rhs.as_micros() as u64 }
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+// What a participating thread is doing, from the clock's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Running,
+    Sleeping(u64), // Runnable once virtual time reaches this (us)
+    Waiting(Option<u64>), // Blocked on a kernel Mutex/Channel until notified (or the timeout)
+    Detached,      // Blocked outside the clock (e.g. joining a task)
+}
+
+struct ClockState {
+    mode: ClockMode,
+    real_epoch: std::time::Instant,
+    now_us: u64, // Virtual time (Simulated mode only)
+    next_id: u64,
+    slots: BTreeMap<u64, Slot>, // Keyed by registration order
+    priorities: BTreeMap<u64, u8>, // Higher value runs first on equal wake times
+    running: Option<u64>,
+    rng: StdRng,
+}
+
+impl ClockState {
+    // Hand the CPU to the earliest runnable thread, advancing virtual time
+    fn dispatch(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let next = self
+            .slots
+            .iter()
+            .filter_map(|(&id, slot)| match slot {
+                Slot::Sleeping(wake) | Slot::Waiting(Some(wake)) => Some((*wake, Reverse(self.priorities[&id]), id)),
+                _ => None,
+            })
+            .min();
+        if let Some((wake, _, id)) = next {
+            self.now_us = self.now_us.max(wake);
+            self.slots.insert(id, Slot::Running);
+            self.running = Some(id);
+            CLOCK_CHANGED.notify_all();
+        }
+    }
+
+    // Id of the calling thread, registering it if this is its first contact.
+    // An unregistered thread is assumed to be the one currently executing
+    // (e.g. main during start-up) unless a task already holds the CPU.
+    fn current_id(&mut self) -> u64 {
+        if let Some(id) = THREAD_ID.with(|t| t.get()) {
+            return id;
+        }
+        let id = self.register(0); // Threads outside the task system rank lowest
+        if self.running.is_none() {
+            self.slots.insert(id, Slot::Running);
+            self.running = Some(id);
+        }
+        THREAD_ID.with(|t| t.set(Some(id)));
+        id
+    }
+
+    fn register(&mut self, priority: u8) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.insert(id, Slot::Sleeping(self.now_us));
+        self.priorities.insert(id, priority);
+        id
+    }
+
+    // Give up the CPU in state `slot` and block until it is handed back
+    fn yield_as(mut guard: StdMutexGuard<'_, ClockState>, id: u64, slot: Slot) -> StdMutexGuard<'_, ClockState> {
+        guard.slots.insert(id, slot);
+        if guard.running == Some(id) {
+            guard.running = None;
+        }
+        guard.dispatch();
+        while guard.running != Some(id) {
+            guard = CLOCK_CHANGED.wait(guard).unwrap();
+        }
+        guard
+    }
+}
+
+lazy_static! {
+    static ref CLOCK: StdMutex<ClockState> = StdMutex::new(ClockState {
+        mode: ClockMode::RealTime,
+        real_epoch: std::time::Instant::now(),
+        now_us: 0,
+        next_id: 0,
+        slots: BTreeMap::new(),
+        priorities: BTreeMap::new(),
+        running: None,
+        rng: StdRng::seed_from_u64(0),
+    });
+    static ref CLOCK_CHANGED: Condvar = Condvar::new();
+}
+
+// Fast path check so RealTime mode never touches the clock lock for mutexes
+static SIMULATED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static THREAD_ID: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn clock() -> StdMutexGuard<'static, ClockState> {
+    CLOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// (Re)start the clock at zero in the given mode and reseed the RNG.
+// Must be called before any task is spawned.
+pub fn init(mode: ClockMode, seed: u64) {
+    let mut state = clock();
+    println!("[Kernel] Clock: {:?}, seed {}", mode, seed);
+    state.mode = mode;
+    state.real_epoch = std::time::Instant::now();
+    state.now_us = 0;
+    state.slots.clear();
+    state.priorities.clear();
+    state.running = None;
+    state.rng = StdRng::seed_from_u64(seed);
+    SIMULATED.store(mode == ClockMode::Simulated, Ordering::SeqCst);
+    THREAD_ID.with(|t| t.set(None));
+}
+
+pub fn mode() -> ClockMode {
+    clock().mode
+}
+
+pub fn now() -> Instant {
+    let state = clock();
+    match state.mode {
+        ClockMode::RealTime => Instant::from_micros(state.real_epoch.elapsed().as_micros() as u64),
+        ClockMode::Simulated => Instant::from_micros(state.now_us),
+    }
+}
+
+pub fn sleep(duration: Duration) {
+    if !SIMULATED.load(Ordering::SeqCst) {
+        std::thread::sleep(duration);
+        return;
+    }
+    let mut state = clock();
+    let id = state.current_id();
+    let wake = state.now_us + duration.as_micros() as u64;
+    drop(ClockState::yield_as(state, id, Slot::Sleeping(wake)));
+}
+
+pub fn sleep_until(wake: Instant) {
+    sleep(wake.duration_since(now()));
+}
+
+// Run `f` with the shared simulation RNG (seeded by `init`)
+pub fn with_rng<R, F: FnOnce(&mut StdRng) -> R>(f: F) -> R {
+    f(&mut clock().rng)
+}
+
+// --- Hooks for the rest of the kernel ---
+
+// Called by the spawning thread: reserves a slot for a new task so it is
+// ordered deterministically, before the OS thread even exists.
+pub(crate) fn register_task(priority: u8) -> Option<u64> {
+    if !SIMULATED.load(Ordering::SeqCst) {
+        return None;
+    }
+    let mut state = clock();
+    state.current_id(); // The spawner keeps running until it blocks
+    Some(state.register(priority))
+}
+
+// Called first thing on the new task's thread
+pub(crate) fn task_started(id: Option<u64>) {
+    if let Some(id) = id {
+        THREAD_ID.with(|t| t.set(Some(id)));
+        let mut state = clock();
+        while state.running != Some(id) {
+            state = CLOCK_CHANGED.wait(state).unwrap();
+        }
+    }
+}
+
+// Called when a task's function returns (or unwinds)
+pub(crate) fn task_finished(id: Option<u64>) {
+    if let Some(id) = id {
+        let mut state = clock();
+        state.slots.remove(&id);
+        state.priorities.remove(&id);
+        if state.running == Some(id) {
+            state.running = None;
+        }
+        state.dispatch();
+    }
+}
+
+// Run a blocking wait that is not governed by the clock (e.g. a thread join)
+pub(crate) fn block_outside<R, F: FnOnce() -> R>(f: F) -> R {
+    if !SIMULATED.load(Ordering::SeqCst) {
+        return f();
+    }
+    let id = {
+        let mut state = clock();
+        let id = state.current_id();
+        state.slots.insert(id, Slot::Detached);
+        state.running = state.running.filter(|&r| r != id);
+        state.dispatch();
+        id
+    };
+    let result = f();
+    let state = clock();
+    let now = state.now_us;
+    drop(ClockState::yield_as(state, id, Slot::Sleeping(now)));
+    result
+}
+
+// Give up the CPU until some kernel object changes (notify_event) or
+// `timeout` is reached. Callers re-check their condition afterwards.
+pub(crate) fn wait_for_event(timeout: Option<Instant>) {
+    let mut state = clock();
+    let id = state.current_id();
+    drop(ClockState::yield_as(state, id, Slot::Waiting(timeout.map(|t| t.as_micros()))));
+}
+
+// A kernel Mutex was released or a Channel changed: make all waiters runnable
+pub(crate) fn notify_event() {
+    if !SIMULATED.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut state = clock();
+    let now = state.now_us;
+    for slot in state.slots.values_mut() {
+        if let Slot::Waiting(_) = slot {
+            *slot = Slot::Sleeping(now);
+        }
+    }
+}
+
+pub(crate) fn is_simulated() -> bool {
+    SIMULATED.load(Ordering::SeqCst)
+}
+
+// The clock is process-wide, and so is the simulated hardware driven by it:
+// a test that uses either holds this for its whole run
+#[cfg(test)]
+pub(crate) fn exclusive() -> StdMutexGuard<'static, ()> {
+    lazy_static! {
+        static ref EXCLUSIVE: StdMutex<()> = StdMutex::new(());
+    }
+    EXCLUSIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -1,7 +1,9 @@
 // Wrapper around std::sync::Mutex to mimic RTOS mutex behavior (optional)
 // In a real no_std RTOS, this would use critical sections or target-specific mutexes.
-use std::sync::{Arc, Mutex as StdMutex, MutexGuard, LockResult};
+use std::sync::{Arc, Mutex as StdMutex, MutexGuard as StdMutexGuard, TryLockError};
+use std::ops::{Deref, DerefMut};
 use crate::error::{RocketError, Result};
+use crate::kernel::sim;
 
 #[derive(Debug)]
 pub struct Mutex<T: ?Sized>(Arc<StdMutex<T>>);
@@ -21,76 +23,407 @@ impl<T: ?Sized> Clone for Mutex<T> {
 
 impl<T: ?Sized> Mutex<T> {
     pub fn lock(&self) -> Result<MutexGuard<'_, T>> {
-        match self.0.lock() {
-            Ok(guard) => Ok(guard),
-            Err(poisoned) => {
-                // In a real RTOS, poisoning might require specific handling (e.g., system reset)
-                eprintln!("WARN: Mutex poisoned! Attempting recovery.");
-                // For simulation, try to recover by taking the lock anyway
-                Ok(poisoned.into_inner())
+        if !sim::is_simulated() {
+            return Ok(MutexGuard(self.0.lock().unwrap_or_else(Self::recover)));
+        }
+        // With the simulated clock only one task runs at a time, so a task must
+        // hand over the CPU (rather than block the thread) while the holder runs.
+        loop {
+            match self.0.try_lock() {
+                Ok(guard) => return Ok(MutexGuard(guard)),
+                Err(TryLockError::Poisoned(poisoned)) => return Ok(MutexGuard(Self::recover(poisoned))),
+                Err(TryLockError::WouldBlock) => sim::wait_for_event(None),
             }
         }
     }
+
+    fn recover(poisoned: std::sync::PoisonError<StdMutexGuard<'_, T>>) -> StdMutexGuard<'_, T> {
+        // In a real RTOS, poisoning might require specific handling (e.g., system reset)
+        eprintln!("WARN: Mutex poisoned! Attempting recovery.");
+        // For simulation, try to recover by taking the lock anyway
+        poisoned.into_inner()
+    }
+}
+
+// Guard returned by Mutex::lock; releasing it wakes tasks waiting on the lock
+pub struct MutexGuard<'a, T: ?Sized>(StdMutexGuard<'a, T>);
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        sim::notify_event();
+    }
 }
 
-// Simple blocking delay - uses std::thread::sleep for simulation
+// Blocking delay on the kernel clock (virtual time when simulated)
 pub fn sleep(duration: std::time::Duration) {
-    std::thread::sleep(duration);
+    sim::sleep(duration);
 }
 
-// Get current time - uses std::time::Instant for simulation
+// Get current time from the kernel clock.
 // A real RTOS would use a hardware timer.
-pub fn get_time() -> std::time::Instant {
-    std::time::Instant::now()
+pub fn get_time() -> sim::Instant {
+    sim::now()
+}
+
+// Bounded queue for inter-task communication, modelled on RTOS queues:
+// fixed capacity chosen at construction, an explicit policy for what a send
+// does when the queue is full, and counters so backpressure is visible.
+// Any number of senders and receivers may share one queue (each message is
+// delivered to exactly one receiver).
+// Blocking waits go through the kernel clock, so they cost no real time
+// in simulated mode. (On hardware the queue would also be ISR-safe.)
+use std::collections::VecDeque;
+use std::sync::Condvar;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    Reject,          // Full queue: the new message is dropped and send fails
+    OverwriteOldest, // Full queue: the oldest message is dropped to make room
+    Block(Duration), // Full queue: wait up to the timeout, then drop and fail
+}
+
+// Counters shared by every handle of one channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub sent: u64,      // Messages accepted into the queue
+    pub received: u64,  // Messages taken out of the queue
+    pub dropped: u64,   // Messages lost to overflow (rejected, overwritten or timed out)
+    pub high_water: usize, // Peak queue length
 }
 
-// Basic channel for inter-task communication (using std channels for simulation)
-// In a real RTOS, this would be a bounded queue, possibly ISR-safe.
-use std::sync::mpsc::{channel, Sender, Receiver, TryRecvError};
+struct ChannelState<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    senders: usize,
+    receivers: usize,
+    stats: ChannelStats,
+}
+
+struct ChannelShared<T> {
+    state: StdMutex<ChannelState<T>>,
+    changed: Condvar, // Real-time mode wake-ups; simulated mode uses the kernel clock
+}
+
+impl<T> ChannelShared<T> {
+    fn lock(&self) -> StdMutexGuard<'_, ChannelState<T>> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn notify(&self) {
+        self.changed.notify_all();
+        sim::notify_event();
+    }
+
+    // Block until notified or `deadline` passes. Returns the re-acquired guard.
+    fn wait<'a>(
+        &'a self,
+        guard: StdMutexGuard<'a, ChannelState<T>>,
+        deadline: Option<sim::Instant>,
+    ) -> StdMutexGuard<'a, ChannelState<T>> {
+        if sim::is_simulated() {
+            drop(guard);
+            sim::wait_for_event(deadline);
+            return self.lock();
+        }
+        match deadline {
+            Some(deadline) => {
+                let timeout = deadline.duration_since(get_time());
+                self.changed.wait_timeout(guard, timeout).unwrap_or_else(|p| p.into_inner()).0
+            }
+            None => self.changed.wait(guard).unwrap_or_else(|p| p.into_inner()),
+        }
+    }
+}
 
 pub struct Channel<T> {
-   tx: Sender<T>,
-   rx: Receiver<T>,
+    shared: Arc<ChannelShared<T>>,
 }
 
 impl<T> Channel<T> {
-    pub fn new() -> Self {
-        let (tx, rx) = channel();
-        Channel { tx, rx }
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(capacity > 0, "Channel capacity must be non-zero");
+        let state = ChannelState {
+            queue: VecDeque::with_capacity(capacity),
+            capacity,
+            policy,
+            senders: 0,
+            receivers: 0,
+            stats: ChannelStats::default(),
+        };
+        Channel { shared: Arc::new(ChannelShared { state: StdMutex::new(state), changed: Condvar::new() }) }
     }
 
     pub fn split(self) -> (ChannelSender<T>, ChannelReceiver<T>) {
-        (ChannelSender { tx: self.tx }, ChannelReceiver { rx: self.rx })
+        {
+            let mut state = self.shared.lock();
+            state.senders += 1;
+            state.receivers += 1;
+        }
+        (ChannelSender { shared: self.shared.clone() }, ChannelReceiver { shared: self.shared })
     }
 }
 
-#[derive(Clone)]
 pub struct ChannelSender<T> {
-    tx: Sender<T>,
+    shared: Arc<ChannelShared<T>>,
 }
 
 impl<T> ChannelSender<T> {
+    // Send according to the channel's overflow policy
     pub fn send(&self, data: T) -> Result<()> {
-        self.tx.send(data).map_err(|e| RocketError::Kernel(format!("Channel send error: {}", e)))
+        let mut state = self.shared.lock();
+        if state.receivers == 0 {
+            return Err(RocketError::Kernel("Channel send error: no receivers".to_string()));
+        }
+        match state.policy {
+            OverflowPolicy::Reject if state.queue.len() >= state.capacity => {
+                state.stats.dropped += 1;
+                Err(RocketError::Kernel("Channel full, message rejected".to_string()))
+            }
+            OverflowPolicy::OverwriteOldest if state.queue.len() >= state.capacity => {
+                state.queue.pop_front();
+                state.stats.dropped += 1;
+                Self::push(&mut state, data);
+                drop(state);
+                self.shared.notify();
+                Ok(())
+            }
+            OverflowPolicy::Block(timeout) => {
+                drop(state);
+                self.send_timeout(data, timeout)
+            }
+            _ => {
+                Self::push(&mut state, data);
+                drop(state);
+                self.shared.notify();
+                Ok(())
+            }
+        }
+    }
+
+    // Wait up to `timeout` for space, whatever the channel's policy.
+    // On timeout the message is dropped (and counted) and an error returned.
+    pub fn send_timeout(&self, data: T, timeout: Duration) -> Result<()> {
+        let deadline = get_time() + timeout;
+        let mut state = self.shared.lock();
+        loop {
+            if state.receivers == 0 {
+                return Err(RocketError::Kernel("Channel send error: no receivers".to_string()));
+            }
+            if state.queue.len() < state.capacity {
+                Self::push(&mut state, data);
+                drop(state);
+                self.shared.notify();
+                return Ok(());
+            }
+            if get_time() >= deadline {
+                state.stats.dropped += 1;
+                return Err(RocketError::Kernel("Channel send timed out".to_string()));
+            }
+            state = self.shared.wait(state, Some(deadline));
+        }
+    }
+
+    pub fn stats(&self) -> ChannelStats {
+        self.shared.lock().stats
+    }
+
+    fn push(state: &mut ChannelState<T>, data: T) {
+        state.queue.push_back(data);
+        state.stats.sent += 1;
+        state.stats.high_water = state.stats.high_water.max(state.queue.len());
+    }
+}
+
+impl<T> Clone for ChannelSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        ChannelSender { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for ChannelSender<T> {
+    fn drop(&mut self) {
+        self.shared.lock().senders -= 1;
+        self.shared.notify(); // Receivers may need to see the disconnect
     }
 }
 
 pub struct ChannelReceiver<T> {
-    rx: Receiver<T>,
+    shared: Arc<ChannelShared<T>>,
 }
 
 impl<T> ChannelReceiver<T> {
     // Blocking receive
     pub fn recv(&self) -> Result<T> {
-        self.rx.recv().map_err(|e| RocketError::Kernel(format!("Channel receive error: {}", e)))
+        self.recv_until(None)?
+            .ok_or_else(|| RocketError::Kernel("Channel receive error: disconnected".to_string()))
+    }
+
+    // Wait up to `timeout` for a message; Ok(None) if none arrived
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<T>> {
+        self.recv_until(Some(get_time() + timeout))
     }
 
     // Non-blocking receive
     pub fn try_recv(&self) -> std::result::Result<Option<T>, RocketError> {
-        match self.rx.try_recv() {
-            Ok(data) => Ok(Some(data)),
-            Err(TryRecvError::Empty) => Ok(None),
-            Err(TryRecvError::Disconnected) => Err(RocketError::Kernel("Channel disconnected".to_string())),
+        self.recv_until(Some(get_time()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> ChannelStats {
+        self.shared.lock().stats
+    }
+
+    fn recv_until(&self, deadline: Option<sim::Instant>) -> Result<Option<T>> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(data) = state.queue.pop_front() {
+                state.stats.received += 1;
+                drop(state);
+                self.shared.notify(); // Wake senders blocked on a full queue
+                return Ok(Some(data));
+            }
+            if state.senders == 0 {
+                return Err(RocketError::Kernel("Channel disconnected".to_string()));
+            }
+            if deadline.is_some_and(|d| get_time() >= d) {
+                return Ok(None);
+            }
+            state = self.shared.wait(state, deadline);
+        }
+    }
+}
+
+impl<T> Clone for ChannelReceiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().receivers += 1;
+        ChannelReceiver { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for ChannelReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receivers -= 1;
+        self.shared.notify(); // Blocked senders may need to see the disconnect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{sim::ClockMode, task};
+
+    fn drain<T>(rx: &ChannelReceiver<T>) -> Vec<T> {
+        std::iter::from_fn(|| rx.try_recv().unwrap()).collect()
+    }
+
+    #[test]
+    fn reject_drops_the_new_message() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        let (tx, rx) = Channel::new(2, OverflowPolicy::Reject).split();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert!(tx.send(3).is_err());
+        assert_eq!(drain(&rx), [1, 2]);
+        assert_eq!(rx.stats(), ChannelStats { sent: 2, received: 2, dropped: 1, high_water: 2 });
+    }
+
+    #[test]
+    fn overwrite_oldest_keeps_the_newest_messages() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        let (tx, rx) = Channel::new(2, OverflowPolicy::OverwriteOldest).split();
+        for i in 1..=5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(drain(&rx), [4, 5]);
+        assert_eq!(tx.stats(), ChannelStats { sent: 5, received: 2, dropped: 3, high_water: 2 });
+    }
+
+    #[test]
+    fn block_waits_for_space_then_times_out() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        let timeout = Duration::from_millis(10);
+        let (tx, rx) = Channel::new(1, OverflowPolicy::Block(timeout)).split();
+        tx.send(1).unwrap();
+
+        // Nobody receives: gives up after the timeout, on the kernel clock
+        let start = get_time();
+        assert!(tx.send(2).is_err());
+        assert_eq!(get_time() - start, timeout);
+
+        // A receiver makes room part-way through the wait
+        let consumer = task::spawn("Consumer", move || {
+            sleep(Duration::from_millis(4));
+            assert_eq!(rx.recv()?, 1);
+            assert_eq!(rx.recv()?, 3);
+            Ok(())
+        });
+        let start = get_time();
+        tx.send(3).unwrap();
+        assert_eq!(get_time() - start, Duration::from_millis(4));
+        let stats = tx.stats();
+        consumer.join().unwrap();
+        assert_eq!((stats.sent, stats.dropped, stats.high_water), (2, 1, 1));
+    }
+
+    #[test]
+    fn consumers_share_one_fifo() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        let (tx, rx) = Channel::new(32, OverflowPolicy::Reject).split();
+        let log = Mutex::new(Vec::new());
+        let consumers: Vec<_> = (0..3)
+            .map(|id| {
+                let (rx, log) = (rx.clone(), log.clone());
+                task::spawn("Consumer", move || {
+                    // Each message takes a while to handle, so the others get a turn
+                    while let Ok(value) = rx.recv() {
+                        log.lock()?.push((id, value));
+                        sleep(Duration::from_millis(1));
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for value in 0..30 {
+            tx.send(value).unwrap();
+        }
+        drop(tx);
+        for consumer in consumers {
+            consumer.join().unwrap();
+        }
+
+        let log = log.lock().unwrap().clone();
+        // Every message delivered exactly once, in the order sent
+        assert_eq!(log.iter().map(|&(_, value)| value).collect::<Vec<_>>(), (0..30).collect::<Vec<_>>());
+        for id in 0..3 {
+            assert_eq!(log.iter().filter(|&&(consumer, _)| consumer == id).count(), 10);
         }
+        assert_eq!(rx.stats(), ChannelStats { sent: 30, received: 30, dropped: 0, high_water: 30 });
     }
 }
@@ -1,48 +1,477 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Arc, Mutex as StdMutex, atomic::{AtomicBool, Ordering}};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use lazy_static::lazy_static;
 use crate::error::Result; // Using our top-level Result
+use crate::kernel::sim;
+use crate::config;
+
+// Priority of plain `spawn` tasks and of threads outside the task system
+pub const IDLE_PRIORITY: u8 = 0;
+
+// Static scheduling parameters of a periodic task.
+// Higher `priority` wins when several tasks are released at the same time.
+#[derive(Debug, Clone, Copy)]
+pub struct TaskConfig {
+    pub name: &'static str,
+    pub period: Duration,
+    pub deadline: Duration, // Relative to each release; usually == period
+    pub priority: u8,
+}
+
+// Run-time statistics of a periodic task, measured on the kernel clock
+#[derive(Debug, Clone, Copy)]
+pub struct TaskStats {
+    pub config: TaskConfig,
+    pub releases: u64,         // Jobs started
+    pub deadline_misses: u64,  // Jobs that finished after release + deadline
+    pub skipped_releases: u64, // Releases dropped because the previous job overran
+    pub errors: u64,           // Jobs that returned Err
+    pub wcet: Duration,        // Worst-case execution time (start -> finish)
+    pub total_exec: Duration,
+    // Worst release jitter (release -> start). Only meaningful under
+    // ClockMode::RealTime: the simulated clock charges no time for running
+    // code, so every task starts exactly at its release and this stays zero.
+    pub max_jitter: Duration,
+}
+
+impl TaskStats {
+    fn new(config: TaskConfig) -> Self {
+        TaskStats {
+            config,
+            releases: 0,
+            deadline_misses: 0,
+            skipped_releases: 0,
+            errors: 0,
+            wcet: Duration::ZERO,
+            total_exec: Duration::ZERO,
+            max_jitter: Duration::ZERO,
+        }
+    }
+
+    pub fn average_exec(&self) -> Duration {
+        if self.releases == 0 { Duration::ZERO } else { self.total_exec / self.releases as u32 }
+    }
+}
+
+impl fmt::Display for TaskStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} prio {:>2} period {:>4}ms | releases {:>6} | exec avg {:>6}us wcet {:>6}us | jitter max {:>6}us | misses {} skipped {} errors {}",
+            self.config.name,
+            self.config.priority,
+            self.config.period.as_millis(),
+            self.releases,
+            self.average_exec().as_micros(),
+            self.wcet.as_micros(),
+            self.max_jitter.as_micros(),
+            self.deadline_misses,
+            self.skipped_releases,
+            self.errors,
+        )
+    }
+}
+
+// Cooperative cancellation flag. Tasks poll it (via `should_stop`) at
+// safe points; nothing is ever killed mid-job.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+lazy_static! {
+    // Statistics of every periodic task spawned so far
+    static ref TASK_STATS: StdMutex<Vec<Arc<StdMutex<TaskStats>>>> = StdMutex::new(Vec::new());
+    // System-wide shutdown signal, observed by every task
+    static ref SHUTDOWN: StdMutex<CancellationToken> = StdMutex::new(CancellationToken::new());
+}
+
+thread_local! {
+    // Token of the task running on this thread (None outside the task system)
+    static CURRENT_TOKEN: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+// Ask every task to stop at its next safe point
+pub fn request_shutdown(reason: &str) {
+    let token = SHUTDOWN.lock().unwrap();
+    if !token.is_cancelled() {
+        println!("[Kernel] Shutdown requested: {}", reason);
+        token.cancel();
+    }
+}
+
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.lock().unwrap().is_cancelled()
+}
+
+// Clear a previous shutdown so tasks can be started again in-process
+pub fn reset_shutdown() {
+    *SHUTDOWN.lock().unwrap() = CancellationToken::new();
+}
+
+// True once the calling task was cancelled or a system shutdown was requested.
+// Long-running and one-shot tasks should check this between steps.
+pub fn should_stop() -> bool {
+    shutdown_requested() || CURRENT_TOKEN.with(|t| t.borrow().as_ref().is_some_and(|t| t.is_cancelled()))
+}
 
 // Represents a running task (thread in this simulation)
-pub struct TaskHandle(JoinHandle<Result<()>>); // Store join handle
+pub struct TaskHandle {
+    name: &'static str,
+    thread: JoinHandle<Result<()>>, // Store join handle
+    token: CancellationToken,
+    stats: Option<Arc<StdMutex<TaskStats>>>, // Periodic tasks only
+}
 
 // Spawns a new task (thread)
 // The task function `f` should return a `Result<()>`
 pub fn spawn<F>(name: &'static str, f: F) -> TaskHandle
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    spawn_with_priority(name, IDLE_PRIORITY, f)
+}
+
+fn spawn_with_priority<F>(name: &'static str, priority: u8, f: F) -> TaskHandle
 where
     F: FnOnce() -> Result<()> + Send + 'static,
 {
     let builder = thread::Builder::new().name(name.to_string());
-    let handle = builder.spawn(f).expect("Failed to spawn simulated task (thread)");
-    println!("[Kernel] Spawned task: {}", name);
-    TaskHandle(handle)
+    let clock_slot = sim::register_task(priority); // Fixes this task's place in the simulated run order
+    let token = CancellationToken::new();
+    let task_token = token.clone();
+    let handle = builder
+        .spawn(move || {
+            CURRENT_TOKEN.with(|t| *t.borrow_mut() = Some(task_token));
+            // Hands the CPU on even if `f` panics
+            struct Finished(Option<u64>);
+            impl Drop for Finished {
+                fn drop(&mut self) {
+                    sim::task_finished(self.0);
+                }
+            }
+            let _finished = Finished(clock_slot);
+            sim::task_started(clock_slot);
+            f()
+        })
+        .expect("Failed to spawn simulated task (thread)");
+    println!("[Kernel] Spawned task: {} (priority {})", name, priority);
+    TaskHandle { name, thread: handle, token, stats: None }
+}
+
+// Spawns a task that runs `job` once per `config.period`.
+// Releases are phase-locked to the spawn time; a job that overruns its
+// period causes the missed releases to be skipped rather than bunched up.
+// Errors returned by `job` are logged and counted, and the task carries on.
+// The task returns Ok(()) at the first release after it is cancelled.
+pub fn spawn_periodic<F>(config: TaskConfig, mut job: F) -> TaskHandle
+where
+    F: FnMut() -> Result<()> + Send + 'static,
+{
+    let stats = Arc::new(StdMutex::new(TaskStats::new(config)));
+    TASK_STATS.lock().unwrap().push(stats.clone());
+
+    let first_release = sim::now();
+    let task_stats = stats.clone();
+    let mut handle = spawn_with_priority(config.name, config.priority, move || {
+        let mut release = first_release;
+        loop {
+            sim::sleep_until(release);
+            if should_stop() {
+                println!("[Kernel] Task {} stopped.", config.name);
+                return Ok(());
+            }
+            let start = sim::now();
+            let result = job();
+            let finish = sim::now();
+
+            let mut next_release = release + config.period;
+            let mut skipped = 0;
+            while next_release <= finish {
+                next_release = next_release + config.period;
+                skipped += 1;
+            }
+
+            let mut stats = task_stats.lock().unwrap();
+            let exec = finish - start;
+            stats.releases += 1;
+            stats.total_exec += exec;
+            stats.wcet = stats.wcet.max(exec);
+            stats.max_jitter = stats.max_jitter.max(start - release);
+            stats.skipped_releases += skipped;
+            if finish - release > config.deadline {
+                stats.deadline_misses += 1;
+            }
+            if let Err(e) = result {
+                stats.errors += 1;
+                eprintln!("[Kernel] Task {} error: {}", config.name, e);
+            }
+            drop(stats);
+
+            release = next_release;
+        }
+    });
+    handle.stats = Some(stats);
+    handle
+}
+
+// Snapshot of the statistics of every periodic task
+pub fn statistics() -> Vec<TaskStats> {
+    TASK_STATS.lock().unwrap().iter().map(|s| *s.lock().unwrap()).collect()
 }
 
 // Waits for a task to complete (joins the thread)
 // In a real RTOS, you might wait on a task handle or event flag.
 impl TaskHandle {
     pub fn join(self) -> Result<()> {
-        match self.0.join() {
+        // Joining blocks outside the (simulated) clock, letting other tasks run
+        let name = self.name;
+        match sim::block_outside(|| self.thread.join()) {
             Ok(task_result) => task_result, // Propagate the task's own Result
             Err(e) => {
                 // This error means the thread panicked
-                eprintln!("FATAL: Task panicked: {:?}", e);
+                eprintln!("FATAL: Task {} panicked: {:?}", name, e);
                 Err(crate::error::RocketError::Kernel("Task panicked".to_string()))
             }
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Ask this task alone to stop at its next safe point
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    // Current statistics (None for non-periodic tasks)
+    pub fn stats(&self) -> Option<TaskStats> {
+        self.stats.as_ref().map(|s| *s.lock().unwrap())
+    }
 }
 
-// Basic scheduler loop (simulation - just keeps main thread alive)
+// Scheduler monitor loop, run by the main thread once all tasks are spawned.
+// The release logic lives in each periodic task; this just reports on it.
+// Returns once a shutdown has been requested (by any task).
 // A real RTOS scheduler manages task states, priorities, and context switching.
 pub fn run_scheduler() {
-    println!("[Kernel] Scheduler running (simulation - main thread waits).");
-    // In this simulation, tasks run independently as threads.
-    // The main thread could loop indefinitely, perform health checks,
-    // or wait for a shutdown signal. Here, we just park it.
-    // Joining handles happens in main.rs after this conceptually "returns".
-    loop {
-        // Prevent this thread from consuming 100% CPU
-        std::thread::park();
-        // In a real system, this might be unparked by an interrupt or shutdown signal
-    }
-    // println!("[Kernel] Scheduler stopped."); // Unreachable in this simple loop
+    monitor(None);
+}
+
+// Like run_scheduler, but requests a shutdown itself after `duration`
+// on the kernel clock, so a simulation can end on its own.
+pub fn run_for(duration: Duration) {
+    monitor(Some(sim::now() + duration));
+    request_shutdown("run duration elapsed");
+}
+
+fn monitor(until: Option<sim::Instant>) {
+    println!("[Kernel] Scheduler running (main thread reports task statistics).");
+    let mut next_report = sim::now() + config::SCHEDULER_REPORT_INTERVAL;
+    while !shutdown_requested() {
+        let wake = until.map_or(next_report, |until| until.min(next_report));
+        // Nap one tick at a time so a shutdown raised by a task is noticed promptly
+        sim::sleep(wake.duration_since(sim::now()).min(config::SIM_TICK_RATE));
+        if until.is_some_and(|until| sim::now() >= until) {
+            break;
+        }
+        if sim::now() >= next_report {
+            print_statistics();
+            next_report = next_report + config::SCHEDULER_REPORT_INTERVAL;
+        }
+    }
+}
+
+pub fn print_statistics() {
+    println!("[Kernel] Task statistics at t={:.1}s:", sim::now().as_secs_f64());
+    for stats in statistics() {
+        println!("[Kernel]   {}", stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RocketError;
+    use crate::kernel::sim::ClockMode;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    fn periodic(name: &'static str, priority: u8) -> TaskConfig {
+        TaskConfig { name, period: PERIOD, deadline: PERIOD, priority }
+    }
+
+    fn run(duration: Duration, handles: Vec<TaskHandle>) -> Vec<TaskStats> {
+        run_for(duration);
+        let stats = handles.iter().map(|h| h.stats().unwrap()).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        reset_shutdown();
+        stats
+    }
+
+    #[test]
+    fn equal_releases_dispatch_in_priority_order() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        reset_shutdown();
+        let log = Arc::new(StdMutex::new(Vec::new()));
+        // Spawned lowest priority first, so spawn order can't explain the result
+        let handles = [("low", 1), ("mid", 5), ("high", 9)]
+            .into_iter()
+            .map(|(name, priority)| {
+                let log = log.clone();
+                spawn_periodic(periodic(name, priority), move || {
+                    log.lock().unwrap().push((sim::now(), name));
+                    Ok(())
+                })
+            })
+            .collect();
+
+        let stats = run(Duration::from_millis(45), handles);
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 15);
+        for (i, release) in log.chunks(3).enumerate() {
+            let at = sim::Instant::from_micros(i as u64 * PERIOD.as_micros() as u64);
+            assert_eq!(release, [(at, "high"), (at, "mid"), (at, "low")]);
+        }
+        for stats in stats {
+            assert_eq!(stats.releases, 5);
+            assert_eq!((stats.deadline_misses, stats.skipped_releases, stats.errors), (0, 0, 0));
+        }
+    }
+
+    #[test]
+    fn overrun_skips_releases_and_misses_its_deadline() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        reset_shutdown();
+        let mut job = 0;
+        let overrunning = spawn_periodic(periodic("overrun", 5), move || {
+            job += 1;
+            // Released at 0 ms and finishes at 25 ms: the 10 and 20 ms releases are lost
+            sim::sleep(if job == 1 { Duration::from_millis(25) } else { Duration::from_millis(2) });
+            if job == 3 { Err(RocketError::Kernel("job failed".to_string())) } else { Ok(()) }
+        });
+        let neighbour = spawn_periodic(periodic("neighbour", 1), || Ok(()));
+
+        let stats = run(Duration::from_millis(95), vec![overrunning, neighbour]);
+
+        // Releases at 0, 30, 40, ... 90 ms
+        assert_eq!(stats[0].releases, 8);
+        assert_eq!(stats[0].skipped_releases, 2);
+        assert_eq!(stats[0].deadline_misses, 1);
+        assert_eq!(stats[0].errors, 1);
+        assert_eq!(stats[0].wcet, Duration::from_millis(25));
+        assert_eq!(stats[0].total_exec, Duration::from_millis(25 + 7 * 2));
+        // A lower-priority task is not held up while the overrunning job sleeps
+        assert_eq!(stats[1].releases, 10);
+        assert_eq!(stats[1].deadline_misses, 0);
+    }
+
+    #[test]
+    fn wcet_tracks_the_longest_job() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        reset_shutdown();
+        let runs = [3, 7, 4, 1];
+        let mut job = 0;
+        let handle = spawn_periodic(periodic("varying", 5), move || {
+            sim::sleep(Duration::from_millis(runs[job % runs.len()]));
+            job += 1;
+            Ok(())
+        });
+
+        let stats = run(Duration::from_millis(35), vec![handle]);
+
+        assert_eq!(stats[0].releases, 4);
+        assert_eq!(stats[0].wcet, Duration::from_millis(7));
+        assert_eq!(stats[0].average_exec(), Duration::from_micros(3750));
+        assert_eq!(stats[0].max_jitter, Duration::ZERO);
+    }
+
+    #[test]
+    fn run_for_stops_every_task_at_the_given_time() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        reset_shutdown();
+        let handles: Vec<_> = [("fast", 2, 10), ("slow", 1, 25)]
+            .into_iter()
+            .map(|(name, priority, period)| {
+                let period = Duration::from_millis(period);
+                spawn_periodic(TaskConfig { name, period, deadline: period, priority }, || Ok(()))
+            })
+            .collect();
+
+        run_for(Duration::from_millis(120));
+
+        assert_eq!(sim::now(), sim::Instant::from_micros(120_000));
+        assert!(shutdown_requested());
+        for handle in handles {
+            let stats = handle.stats().unwrap();
+            handle.join().unwrap();
+            assert_eq!(stats.releases, if stats.config.name == "fast" { 13 } else { 5 });
+        }
+        reset_shutdown();
+    }
+
+    #[test]
+    fn cancelled_task_stops_at_its_next_release() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::Simulated, 0);
+        reset_shutdown();
+        let cancelled = spawn_periodic(periodic("cancelled", 2), || Ok(()));
+        let other = spawn_periodic(periodic("other", 1), || Ok(()));
+
+        sim::sleep(Duration::from_millis(25));
+        cancelled.cancel();
+        sim::sleep(Duration::from_millis(20));
+
+        // Released at 0, 10 and 20 ms; the rest of the system carries on
+        assert_eq!(cancelled.stats().unwrap().releases, 3);
+        assert_eq!(other.stats().unwrap().releases, 5);
+        assert!(!shutdown_requested());
+        cancelled.join().unwrap();
+        request_shutdown("test");
+        other.join().unwrap();
+        reset_shutdown();
+    }
+
+    #[test]
+    fn jitter_is_measured_on_the_real_time_clock() {
+        let _exclusive = sim::exclusive();
+        sim::init(ClockMode::RealTime, 0);
+        reset_shutdown();
+        let handle = spawn_periodic(periodic("jittery", 5), || Ok(()));
+
+        let stats = run(Duration::from_millis(50), vec![handle]);
+
+        // A real thread always wakes a little after its release
+        assert!(stats[0].releases >= 3);
+        assert!(stats[0].max_jitter > Duration::ZERO);
+        assert!(stats[0].max_jitter < PERIOD);
+        sim::init(ClockMode::Simulated, 0);
+    }
 }
@@ -0,0 +1,10 @@
+// Flight software, simulated hardware and ground station, shared by the
+// flight binary (main.rs) and the ground station binary (bin/ground_station.rs)
+pub mod error;
+pub mod config;
+pub mod kernel;
+pub mod hal;
+pub mod drivers;
+pub mod components;
+pub mod flight;
+pub mod ground_station;